@@ -0,0 +1,108 @@
+use std::net::Ipv4Addr;
+
+use crate::{
+    ethernet::{EthernetAddress, ETHERTYPE_IPV4},
+    parser::{ParseError, Parser},
+};
+
+pub const ARP_HARDWARE_ETHERNET: u16 = 1;
+pub const ARP_MESSAGE_LENGTH: usize = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpOpcode {
+    Request = 1,
+    Reply = 2,
+}
+
+/// An ARP message for IPv4 over Ethernet, see RFC 826.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArpMessage {
+    pub opcode: ArpOpcode,
+    pub sender_ethernet_address: EthernetAddress,
+    pub sender_ip_address: Ipv4Addr,
+    pub target_ethernet_address: EthernetAddress,
+    pub target_ip_address: Ipv4Addr,
+}
+
+impl ArpMessage {
+    /// Asks who has `target_ip_address`, the target hardware address is left
+    /// zeroed as it is unknown.
+    pub fn request(
+        sender_ethernet_address: EthernetAddress,
+        sender_ip_address: Ipv4Addr,
+        target_ip_address: Ipv4Addr,
+    ) -> ArpMessage {
+        ArpMessage {
+            opcode: ArpOpcode::Request,
+            sender_ethernet_address,
+            sender_ip_address,
+            target_ethernet_address: EthernetAddress::default(),
+            target_ip_address,
+        }
+    }
+
+    pub fn reply(
+        sender_ethernet_address: EthernetAddress,
+        sender_ip_address: Ipv4Addr,
+        target_ethernet_address: EthernetAddress,
+        target_ip_address: Ipv4Addr,
+    ) -> ArpMessage {
+        ArpMessage {
+            opcode: ArpOpcode::Reply,
+            sender_ethernet_address,
+            sender_ip_address,
+            target_ethernet_address,
+            target_ip_address,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ARP_MESSAGE_LENGTH);
+        out.extend_from_slice(&ARP_HARDWARE_ETHERNET.to_be_bytes());
+        out.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        out.push(6);
+        out.push(4);
+        out.extend_from_slice(&(self.opcode as u16).to_be_bytes());
+        out.extend_from_slice(&self.sender_ethernet_address.0);
+        out.extend_from_slice(&self.sender_ip_address.octets());
+        out.extend_from_slice(&self.target_ethernet_address.0);
+        out.extend_from_slice(&self.target_ip_address.octets());
+        out
+    }
+
+    pub fn parse(data: &[u8]) -> Result<ArpMessage, ParseError> {
+        let mut parser = Parser::new(data);
+        let hardware_type = parser.u16()?;
+        let protocol_type = parser.u16()?;
+        let hardware_address_size = parser.u8()?;
+        let protocol_address_size = parser.u8()?;
+        let opcode = parser.u16()?;
+        if hardware_type != ARP_HARDWARE_ETHERNET
+            || protocol_type != ETHERTYPE_IPV4
+            || hardware_address_size != 6
+            || protocol_address_size != 4
+        {
+            return Err(ParseError::Unsupported);
+        }
+        let opcode = match opcode {
+            1 => ArpOpcode::Request,
+            2 => ArpOpcode::Reply,
+            _ => return Err(ParseError::Unsupported),
+        };
+
+        let mut address = || -> Result<_, ParseError> {
+            let ethernet = EthernetAddress(parser.bytes(6)?.try_into().unwrap());
+            let ip = Ipv4Addr::from(parser.u32()?);
+            Ok((ethernet, ip))
+        };
+        let (sender_ethernet_address, sender_ip_address) = address()?;
+        let (target_ethernet_address, target_ip_address) = address()?;
+        Ok(ArpMessage {
+            opcode,
+            sender_ethernet_address,
+            sender_ip_address,
+            target_ethernet_address,
+            target_ip_address,
+        })
+    }
+}
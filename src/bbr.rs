@@ -0,0 +1,334 @@
+use std::collections::VecDeque;
+
+use crate::{
+    congestion::{rescale_window, AckEvent, CongestionControl},
+    reno::Reno,
+    MAX_PAYLOAD_SIZE,
+};
+
+/// 2/ln(2), the smallest gain that doubles the sending rate every round
+/// trip in startup.
+const HIGH_GAIN: f64 = 2.885;
+/// Pacing gains probe bandwidth cycles through, one phase per min RTT.
+const PACING_GAIN_CYCLE: [f64; 8] = [1.25, 0.75, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+const PROBE_BW_CWND_GAIN: f64 = 2.0;
+/// Round trips the bandwidth estimate remembers its samples for.
+const BANDWIDTH_WINDOW: u64 = 10;
+/// How long a min RTT stays valid before probe RTT re-measures it.
+const MIN_RTT_WINDOW: u64 = 10_000;
+const PROBE_RTT_DURATION: u64 = 200;
+/// Startup ends once the bandwidth stops growing by this factor...
+const FULL_BANDWIDTH_GROWTH: f64 = 1.25;
+/// ...for this many round trips in a row.
+const FULL_BANDWIDTH_ROUNDS: u32 = 3;
+const MIN_CWND_SEGMENTS: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BbrMode {
+    /// Doubles the sending rate every round trip to find the bottleneck.
+    Startup,
+    /// Empties the queue startup built up.
+    Drain,
+    /// Cruises at the bottleneck bandwidth, periodically probing for more.
+    ProbeBw,
+    /// Briefly shrinks the window to re-measure the propagation delay.
+    ProbeRtt,
+}
+
+/// Model-based congestion control after BBR v1: the sender paces at the
+/// estimated bottleneck bandwidth and keeps about one bandwidth-delay
+/// product in flight, rather than reacting to loss.
+#[derive(Debug, Clone)]
+pub struct Bbr {
+    mss: u64,
+    mode: BbrMode,
+    cwnd: u64,
+    /// In bytes per millisecond, zero until the first RTT measurement.
+    pacing_rate: f64,
+    pacing_gain: f64,
+    cwnd_gain: f64,
+
+    /// Delivery rate samples in bytes per millisecond, with their round.
+    bandwidth_samples: VecDeque<(u64, f64)>,
+    min_rtt: Option<u64>,
+    min_rtt_stamp: u64,
+
+    delivered: u64,
+    round: u64,
+    next_round_delivered: u64,
+    round_start: bool,
+
+    full_bandwidth: f64,
+    full_bandwidth_rounds: u32,
+    filled_pipe: bool,
+
+    cycle_index: usize,
+    cycle_stamp: u64,
+
+    probe_rtt_done: Option<u64>,
+    probe_rtt_round_done: bool,
+    prior_cwnd: u64,
+}
+
+impl Default for Bbr {
+    fn default() -> Self {
+        Self::new(MAX_PAYLOAD_SIZE as u64)
+    }
+}
+
+impl Bbr {
+    pub fn new(mss: u64) -> Self {
+        Bbr {
+            mss,
+            mode: BbrMode::Startup,
+            cwnd: Reno::initial_window(mss),
+            pacing_rate: 0.0,
+            pacing_gain: HIGH_GAIN,
+            cwnd_gain: HIGH_GAIN,
+            bandwidth_samples: VecDeque::new(),
+            min_rtt: None,
+            min_rtt_stamp: 0,
+            delivered: 0,
+            round: 0,
+            next_round_delivered: 0,
+            round_start: false,
+            full_bandwidth: 0.0,
+            full_bandwidth_rounds: 0,
+            filled_pipe: false,
+            cycle_index: 0,
+            cycle_stamp: 0,
+            probe_rtt_done: None,
+            probe_rtt_round_done: false,
+            prior_cwnd: 0,
+        }
+    }
+
+    pub fn mode(&self) -> BbrMode {
+        self.mode
+    }
+
+    /// The estimated bottleneck bandwidth in bytes per millisecond, the
+    /// highest delivery rate seen over the last few round trips.
+    pub fn bottleneck_bandwidth(&self) -> f64 {
+        self.bandwidth_samples
+            .iter()
+            .map(|(_, rate)| *rate)
+            .fold(0.0, f64::max)
+    }
+
+    /// The estimated round-trip propagation delay.
+    pub fn min_rtt(&self) -> Option<u64> {
+        self.min_rtt
+    }
+
+    /// Round trips counted so far.
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    fn min_cwnd(&self) -> u64 {
+        MIN_CWND_SEGMENTS * self.mss
+    }
+
+    /// The bandwidth-delay product scaled by `gain`, or the initial window
+    /// while there is no model yet.
+    fn target_window(&self, gain: f64) -> u64 {
+        let bandwidth = self.bottleneck_bandwidth();
+        match self.min_rtt {
+            Some(min_rtt) if bandwidth > 0.0 => {
+                let bdp = bandwidth * min_rtt as f64;
+                ((gain * bdp) as u64).max(self.min_cwnd())
+            }
+            _ => Reno::initial_window(self.mss),
+        }
+    }
+
+    fn update_model(&mut self, event: &AckEvent) {
+        self.delivered += event.acked;
+        self.round_start = false;
+        if let Some(sample) = event.rate_sample {
+            if sample.prior_delivered >= self.next_round_delivered {
+                self.next_round_delivered = self.delivered;
+                self.round += 1;
+                self.round_start = true;
+            }
+            self.bandwidth_samples
+                .push_back((self.round, sample.rate()));
+        }
+        let round = self.round;
+        self.bandwidth_samples
+            .retain(|(sampled, _)| sampled + BANDWIDTH_WINDOW > round);
+
+        let expired = event.now > self.min_rtt_stamp + MIN_RTT_WINDOW;
+        if let Some(rtt) = event.rtt {
+            if self.min_rtt.is_none_or(|min_rtt| rtt <= min_rtt) || expired {
+                self.min_rtt = Some(rtt);
+                self.min_rtt_stamp = event.now;
+            }
+        }
+        if expired && self.mode != BbrMode::ProbeRtt {
+            self.enter_probe_rtt();
+        }
+    }
+
+    fn check_full_pipe(&mut self) {
+        if self.filled_pipe || !self.round_start {
+            return;
+        }
+        let bandwidth = self.bottleneck_bandwidth();
+        if bandwidth >= self.full_bandwidth * FULL_BANDWIDTH_GROWTH {
+            self.full_bandwidth = bandwidth;
+            self.full_bandwidth_rounds = 0;
+            return;
+        }
+        self.full_bandwidth_rounds += 1;
+        self.filled_pipe = self.full_bandwidth_rounds >= FULL_BANDWIDTH_ROUNDS;
+    }
+
+    fn enter_drain(&mut self) {
+        self.mode = BbrMode::Drain;
+        self.pacing_gain = 1.0 / HIGH_GAIN;
+        self.cwnd_gain = HIGH_GAIN;
+    }
+
+    fn enter_probe_bw(&mut self, now: u64) {
+        self.mode = BbrMode::ProbeBw;
+        self.cwnd_gain = PROBE_BW_CWND_GAIN;
+        self.cycle_index = 0;
+        self.cycle_stamp = now;
+        self.pacing_gain = PACING_GAIN_CYCLE[0];
+    }
+
+    fn enter_probe_rtt(&mut self) {
+        self.mode = BbrMode::ProbeRtt;
+        self.pacing_gain = 1.0;
+        self.cwnd_gain = 1.0;
+        self.prior_cwnd = self.cwnd;
+        self.probe_rtt_done = None;
+    }
+
+    fn advance_cycle(&mut self, event: &AckEvent) {
+        let elapsed = event.now - self.cycle_stamp > self.min_rtt.unwrap_or(0);
+        let drained = self.pacing_gain < 1.0 && event.in_flight <= self.target_window(1.0);
+        if elapsed || drained {
+            self.cycle_index = (self.cycle_index + 1) % PACING_GAIN_CYCLE.len();
+            self.cycle_stamp = event.now;
+            self.pacing_gain = PACING_GAIN_CYCLE[self.cycle_index];
+        }
+    }
+
+    fn probe_rtt(&mut self, event: &AckEvent) {
+        match self.probe_rtt_done {
+            None if event.in_flight <= self.min_cwnd() => {
+                self.probe_rtt_done = Some(event.now + PROBE_RTT_DURATION);
+                self.probe_rtt_round_done = false;
+                self.next_round_delivered = self.delivered;
+            }
+            None => {}
+            Some(done) => {
+                self.probe_rtt_round_done |= self.round_start;
+                if self.probe_rtt_round_done && event.now >= done {
+                    self.min_rtt_stamp = event.now;
+                    self.cwnd = self.cwnd.max(self.prior_cwnd);
+                    if self.filled_pipe {
+                        self.enter_probe_bw(event.now);
+                    } else {
+                        self.mode = BbrMode::Startup;
+                        self.pacing_gain = HIGH_GAIN;
+                        self.cwnd_gain = HIGH_GAIN;
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_mode(&mut self, event: &AckEvent) {
+        self.check_full_pipe();
+        if self.mode == BbrMode::Startup && self.filled_pipe {
+            self.enter_drain();
+        }
+        if self.mode == BbrMode::Drain && event.in_flight <= self.target_window(1.0) {
+            self.enter_probe_bw(event.now);
+        }
+        match self.mode {
+            BbrMode::ProbeBw => self.advance_cycle(event),
+            BbrMode::ProbeRtt => self.probe_rtt(event),
+            _ => {}
+        }
+    }
+
+    /// Paces at the bandwidth estimate scaled by the gain, not at all before
+    /// the first RTT measurement. Until the pipe is full the rate only goes
+    /// up and stays above the initial window per min RTT, so the tiny samples
+    /// of the first round trips cannot stall startup.
+    fn update_pacing_rate(&mut self) {
+        let Some(min_rtt) = self.min_rtt else {
+            return;
+        };
+        let rate = self.pacing_gain * self.bottleneck_bandwidth();
+        if self.filled_pipe {
+            if rate > 0.0 {
+                self.pacing_rate = rate;
+            }
+            return;
+        }
+        let initial = Reno::initial_window(self.mss) as f64 / min_rtt.max(1) as f64;
+        self.pacing_rate = self.pacing_rate.max(HIGH_GAIN * initial).max(rate);
+    }
+
+    fn update_cwnd(&mut self, event: &AckEvent) {
+        if self.mode == BbrMode::ProbeRtt {
+            self.cwnd = self.cwnd.min(self.min_cwnd());
+            return;
+        }
+        let target = self.target_window(self.cwnd_gain);
+        if self.filled_pipe {
+            self.cwnd = (self.cwnd + event.acked).min(target);
+        } else if self.cwnd < target || self.delivered < Reno::initial_window(self.mss) {
+            self.cwnd += event.acked;
+        }
+        self.cwnd = self.cwnd.max(self.min_cwnd());
+    }
+}
+
+impl CongestionControl for Bbr {
+    fn on_ack(&mut self, event: &AckEvent) {
+        self.update_model(event);
+        self.update_mode(event);
+        self.update_pacing_rate();
+        self.update_cwnd(event);
+    }
+
+    /// Loss is not a congestion signal to BBR, the model already accounts
+    /// for the queue.
+    fn on_loss(&mut self, _in_flight: u64) {}
+
+    /// Falls back to one segment, growing back from there by what each
+    /// acknowledgment delivers.
+    fn on_timeout(&mut self, _in_flight: u64) {
+        self.cwnd = self.mss;
+    }
+
+    /// Once there is a model the window follows the bandwidth-delay product
+    /// in bytes, only the initial window and the floor are in segments.
+    fn set_mss(&mut self, mss: u64) {
+        let mss = mss.max(1);
+        if self.bandwidth_samples.is_empty() {
+            self.cwnd = rescale_window(self.cwnd, self.mss, mss);
+        }
+        self.mss = mss;
+        self.cwnd = self.cwnd.max(self.min_cwnd());
+    }
+
+    fn cwnd(&self) -> u64 {
+        self.cwnd
+    }
+
+    fn ssthresh(&self) -> u64 {
+        u64::MAX
+    }
+
+    fn pacing_rate(&self) -> Option<f64> {
+        (self.pacing_rate > 0.0).then_some(self.pacing_rate)
+    }
+}
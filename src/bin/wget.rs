@@ -1,5 +1,5 @@
 use std::{
-    io::{Write, Read},
+    io::{Read, Write},
     net::{IpAddr, SocketAddr, TcpStream},
 };
 
@@ -27,7 +27,7 @@ fn get_addr(addr: &SocketAddr, host: &str, path: &str) -> String {
 
 fn lookup_domain(host: &str) -> Option<SocketAddr> {
     debug!("Looking up host {}", host);
-    Some(SocketAddr::new(*lookup_host(host).ok()?.get(0)?, 80))
+    Some(SocketAddr::new(*lookup_host(host).ok()?.first()?, 80))
 }
 
 #[derive(Parser, Debug)]
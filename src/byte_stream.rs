@@ -5,6 +5,7 @@ pub struct ByteStream {
     inner: VecDeque<u8>,
     capacity: usize,
     closed: bool,
+    error: bool,
     poped: usize,
     pushed: usize,
 }
@@ -23,9 +24,10 @@ impl std::io::Write for ByteStream {
 impl ByteStream {
     pub fn new(capacity: usize) -> Self {
         ByteStream {
-            inner: VecDeque::with_capacity(capacity),
+            inner: VecDeque::new(),
             capacity,
             closed: false,
+            error: false,
             poped: 0,
             pushed: 0,
         }
@@ -51,6 +53,14 @@ impl ByteStream {
         self.closed
     }
 
+    pub fn set_error(&mut self) {
+        self.error = true
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.error
+    }
+
     pub fn finished(&self) -> bool {
         self.is_empty() && self.closed() && self.pushed() != 0
     }
@@ -0,0 +1,31 @@
+/// The ones' complement sum from RFC 1071, shared by IPv4 and TCP.
+#[derive(Debug, Default, Clone)]
+pub struct InternetChecksum {
+    sum: u64,
+    odd: bool,
+}
+
+impl InternetChecksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, data: &[u8]) {
+        for &byte in data {
+            if self.odd {
+                self.sum += byte as u64;
+            } else {
+                self.sum += (byte as u64) << 8;
+            }
+            self.odd = !self.odd;
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        let mut sum = self.sum;
+        while sum >> 16 != 0 {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+        !(sum as u16)
+    }
+}
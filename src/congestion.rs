@@ -0,0 +1,111 @@
+use std::fmt::Debug;
+
+/// What a congestion controller learns from an acknowledgment of new data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AckEvent {
+    /// Sequence space newly acknowledged.
+    pub acked: u64,
+    /// Sequence space still in flight after the acknowledgment.
+    pub in_flight: u64,
+    /// Round-trip time measured from this acknowledgment, `None` when it
+    /// only covers retransmitted segments.
+    pub rtt: Option<u64>,
+    /// Milliseconds since the sender was created, as driven by `tick`.
+    pub now: u64,
+    /// How fast data was delivered while the newest acknowledged segment
+    /// was in flight.
+    pub rate_sample: Option<RateSample>,
+}
+
+/// A delivery rate measurement in the style of the BBR drafts: what was
+/// delivered between sending a segment and its acknowledgment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateSample {
+    /// Bytes delivered over the interval.
+    pub delivered: u64,
+    /// Length of the interval in milliseconds, never zero.
+    pub interval: u64,
+    /// Bytes the sender had delivered in total when the segment was sent.
+    pub prior_delivered: u64,
+}
+
+impl RateSample {
+    /// The delivery rate in bytes per millisecond.
+    pub fn rate(&self) -> f64 {
+        self.delivered as f64 / self.interval as f64
+    }
+}
+
+/// A congestion control algorithm, consulted by
+/// [`TcpSender::push`](crate::tcp_sender::TcpSender::push) so that no more
+/// than min(cwnd, rwnd) is ever in flight. Windows are in bytes of sequence
+/// space.
+pub trait CongestionControl: Debug {
+    /// New data was acknowledged.
+    fn on_ack(&mut self, event: &AckEvent);
+
+    /// Loss was inferred from duplicate acknowledgments while `in_flight`
+    /// was outstanding.
+    fn on_loss(&mut self, in_flight: u64);
+
+    /// The retransmission timer expired while `in_flight` was outstanding.
+    fn on_timeout(&mut self, in_flight: u64);
+
+    /// The congestion window.
+    fn cwnd(&self) -> u64;
+
+    /// The slow start threshold.
+    fn ssthresh(&self) -> u64;
+
+    /// An acknowledgment arrived that acknowledged nothing new while data
+    /// was in flight.
+    fn on_duplicate_ack(&mut self) {}
+
+    /// The sender's segment size changed, after the peer announced its MSS
+    /// or path MTU discovery found a different one. Windows keep their size
+    /// in segments.
+    fn set_mss(&mut self, _mss: u64) {}
+
+    /// Whether the algorithm is recovering from a loss, in which case an
+    /// acknowledgment that leaves data in flight means the next segment was
+    /// lost as well.
+    fn in_recovery(&self) -> bool {
+        false
+    }
+
+    /// How fast segments may leave the sender, in bytes per millisecond.
+    /// `None` sends everything the windows allow at once.
+    fn pacing_rate(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// Converts a window of `bytes` in segments of `from` bytes to the same
+/// number of segments of `to` bytes.
+pub fn rescale_window(bytes: u64, from: u64, to: u64) -> u64 {
+    if bytes == u64::MAX || from == 0 {
+        return bytes;
+    }
+    (bytes as u128 * to as u128 / from as u128).min(u64::MAX as u128) as u64
+}
+
+/// Leaves the receiver's window as the only limit, which is how the sender
+/// behaved before congestion control existed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unlimited;
+
+impl CongestionControl for Unlimited {
+    fn on_ack(&mut self, _event: &AckEvent) {}
+
+    fn on_loss(&mut self, _in_flight: u64) {}
+
+    fn on_timeout(&mut self, _in_flight: u64) {}
+
+    fn cwnd(&self) -> u64 {
+        u64::MAX
+    }
+
+    fn ssthresh(&self) -> u64 {
+        u64::MAX
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    congestion::{rescale_window, AckEvent, CongestionControl},
+    reno::Reno,
+    rtt::RttEstimator,
+    MAX_PAYLOAD_SIZE,
+};
+
+/// Scales the cubic function, in segments per second cubed.
+const C: f64 = 0.4;
+/// Multiplicative decrease factor.
+const BETA: f64 = 0.7;
+/// Growth of the Reno-equivalent window in segments per RTT, see RFC 8312
+/// section 4.2.
+const ALPHA: f64 = 3.0 * (1.0 - BETA) / (1.0 + BETA);
+
+/// Progress through one fast recovery, counted from the loss.
+#[derive(Debug, Clone, Copy)]
+struct Recovery {
+    flight: u64,
+    acked: u64,
+}
+
+/// State of the current congestion avoidance epoch, which starts with the
+/// first acknowledgment after a reduction.
+#[derive(Debug, Clone, Copy)]
+struct Epoch {
+    start: u64,
+    /// Seconds until the cubic function reaches `origin` again.
+    k: f64,
+    /// The plateau of the cubic function, in bytes.
+    origin: f64,
+    /// The window standard TCP would have, in bytes.
+    w_est: f64,
+}
+
+/// CUBIC congestion control as in RFC 8312.
+#[derive(Debug, Clone)]
+pub struct Cubic {
+    mss: u64,
+    /// In bytes, kept fractional as growth per acknowledgment is tiny.
+    cwnd: f64,
+    ssthresh: u64,
+    /// Window before the last reduction, in bytes.
+    w_max: f64,
+    w_last_max: f64,
+    epoch: Option<Epoch>,
+    rtt: RttEstimator,
+    recovery: Option<Recovery>,
+    timed_out: bool,
+    fast_convergence: bool,
+}
+
+impl Default for Cubic {
+    fn default() -> Self {
+        Self::new(MAX_PAYLOAD_SIZE as u64)
+    }
+}
+
+impl Cubic {
+    pub fn new(mss: u64) -> Self {
+        Cubic {
+            mss,
+            cwnd: Reno::initial_window(mss) as f64,
+            ssthresh: u64::MAX,
+            w_max: 0.0,
+            w_last_max: 0.0,
+            epoch: None,
+            rtt: RttEstimator::default(),
+            recovery: None,
+            timed_out: false,
+            fast_convergence: true,
+        }
+    }
+
+    /// Fast convergence releases bandwidth to new flows by remembering a
+    /// lower `w_max` when losses come before reaching the previous one.
+    pub fn fast_convergence(mut self, enabled: bool) -> Self {
+        self.fast_convergence = enabled;
+        self
+    }
+
+    /// The window before the last reduction, in bytes.
+    pub fn w_max(&self) -> u64 {
+        self.w_max as u64
+    }
+
+    fn reduce(&mut self) {
+        self.epoch = None;
+        if self.fast_convergence && self.cwnd < self.w_last_max {
+            self.w_last_max = self.cwnd;
+            self.w_max = self.cwnd * (1.0 + BETA) / 2.0;
+        } else {
+            self.w_last_max = self.cwnd;
+            self.w_max = self.cwnd;
+        }
+    }
+
+    fn start_epoch(&self, now: u64) -> Epoch {
+        let (k, origin) = if self.cwnd < self.w_max {
+            let segments = (self.w_max - self.cwnd) / self.mss as f64;
+            ((segments / C).cbrt(), self.w_max)
+        } else {
+            (0.0, self.cwnd)
+        };
+        Epoch {
+            start: now,
+            k,
+            origin,
+            w_est: self.cwnd,
+        }
+    }
+
+    fn congestion_avoidance(&mut self, event: &AckEvent) {
+        let mut epoch = match self.epoch {
+            Some(epoch) => epoch,
+            None => self.start_epoch(event.now),
+        };
+        let mss = self.mss as f64;
+        let acked = event.acked as f64;
+
+        // Aim for where the cubic function will be one RTT from now, but
+        // grow by no more than half the window per RTT (RFC 8312 section 4.1).
+        let rtt = self.rtt.srtt().unwrap_or(0) as f64 / 1000.0;
+        let t = (event.now - epoch.start) as f64 / 1000.0 + rtt;
+        let target = (epoch.origin + C * (t - epoch.k).powi(3) * mss).min(1.5 * self.cwnd);
+        if target > self.cwnd {
+            self.cwnd += (target - self.cwnd) / self.cwnd * acked;
+        }
+
+        epoch.w_est += ALPHA * mss * acked / self.cwnd;
+        if epoch.w_est > self.cwnd {
+            self.cwnd = epoch.w_est;
+        }
+        self.epoch = Some(epoch);
+    }
+}
+
+impl CongestionControl for Cubic {
+    fn on_ack(&mut self, event: &AckEvent) {
+        if let Some(rtt) = event.rtt {
+            self.rtt.sample(rtt);
+        }
+        self.timed_out = false;
+
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.acked += event.acked;
+            if recovery.acked >= recovery.flight {
+                self.recovery = None;
+            }
+            return;
+        }
+
+        if self.cwnd < self.ssthresh as f64 {
+            self.cwnd += event.acked.min(self.mss) as f64;
+            return;
+        }
+        self.congestion_avoidance(event);
+    }
+
+    fn on_loss(&mut self, in_flight: u64) {
+        if self.recovery.is_some() {
+            return;
+        }
+        self.reduce();
+        self.ssthresh = ((self.cwnd * BETA) as u64).max(2 * self.mss);
+        self.cwnd = self.ssthresh as f64;
+        self.recovery = Some(Recovery {
+            flight: in_flight,
+            acked: 0,
+        });
+    }
+
+    fn on_timeout(&mut self, _in_flight: u64) {
+        // Backing off again on the same segment is not a new congestion event.
+        if !self.timed_out {
+            self.reduce();
+            self.ssthresh = ((self.cwnd * BETA) as u64).max(2 * self.mss);
+        }
+        self.timed_out = true;
+        self.cwnd = self.mss as f64;
+        self.recovery = None;
+    }
+
+    fn set_mss(&mut self, mss: u64) {
+        let mss = mss.max(1);
+        let scale = mss as f64 / self.mss as f64;
+        self.cwnd = (self.cwnd * scale).max(mss as f64);
+        self.ssthresh = rescale_window(self.ssthresh, self.mss, mss).max(2 * mss);
+        self.w_max *= scale;
+        self.w_last_max *= scale;
+        if let Some(epoch) = self.epoch.as_mut() {
+            epoch.origin *= scale;
+            epoch.w_est *= scale;
+        }
+        self.mss = mss;
+    }
+
+    fn cwnd(&self) -> u64 {
+        self.cwnd as u64
+    }
+
+    fn ssthresh(&self) -> u64 {
+        self.ssthresh
+    }
+
+    fn in_recovery(&self) -> bool {
+        self.recovery.is_some()
+    }
+}
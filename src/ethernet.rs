@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+use crate::{
+    arp::{ArpMessage, ArpOpcode},
+    ipv4::InternetDatagram,
+    parser::{ParseError, Parser},
+};
+
+pub const ETHERNET_HEADER_LENGTH: usize = 14;
+/// The largest payload a standard Ethernet frame carries.
+pub const ETHERNET_MTU: usize = 1500;
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EthernetAddress(pub [u8; 6]);
+
+impl EthernetAddress {
+    pub const BROADCAST: EthernetAddress = EthernetAddress([0xff; 6]);
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+}
+
+impl Display for EthernetAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthernetHeader {
+    pub dst: EthernetAddress,
+    pub src: EthernetAddress,
+    pub ethertype: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthernetFrame {
+    pub header: EthernetHeader,
+    pub payload: Vec<u8>,
+}
+
+impl EthernetFrame {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ETHERNET_HEADER_LENGTH + self.payload.len());
+        out.extend_from_slice(&self.header.dst.0);
+        out.extend_from_slice(&self.header.src.0);
+        out.extend_from_slice(&self.header.ethertype.to_be_bytes());
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    pub fn parse(data: &[u8]) -> Result<EthernetFrame, ParseError> {
+        let mut parser = Parser::new(data);
+        let dst = EthernetAddress(parser.bytes(6)?.try_into().unwrap());
+        let src = EthernetAddress(parser.bytes(6)?.try_into().unwrap());
+        let ethertype = parser.u16()?;
+        Ok(EthernetFrame {
+            header: EthernetHeader {
+                dst,
+                src,
+                ethertype,
+            },
+            payload: parser.remaining().to_vec(),
+        })
+    }
+
+    pub fn from_datagram(
+        datagram: &InternetDatagram,
+        src: EthernetAddress,
+        dst: EthernetAddress,
+    ) -> Result<EthernetFrame, ParseError> {
+        Ok(EthernetFrame {
+            header: EthernetHeader {
+                dst,
+                src,
+                ethertype: ETHERTYPE_IPV4,
+            },
+            payload: datagram.serialize()?,
+        })
+    }
+
+    pub fn to_datagram(&self) -> Result<InternetDatagram, ParseError> {
+        if self.header.ethertype != ETHERTYPE_IPV4 {
+            return Err(ParseError::WrongProtocol);
+        }
+        InternetDatagram::parse(&self.payload)
+    }
+
+    /// Frames an ARP message, broadcasting requests and unicasting replies.
+    pub fn from_arp(message: &ArpMessage) -> EthernetFrame {
+        let dst = match message.opcode {
+            ArpOpcode::Request => EthernetAddress::BROADCAST,
+            ArpOpcode::Reply => message.target_ethernet_address,
+        };
+        EthernetFrame {
+            header: EthernetHeader {
+                dst,
+                src: message.sender_ethernet_address,
+                ethertype: ETHERTYPE_ARP,
+            },
+            payload: message.serialize(),
+        }
+    }
+
+    pub fn to_arp(&self) -> Result<ArpMessage, ParseError> {
+        if self.header.ethertype != ETHERTYPE_ARP {
+            return Err(ParseError::WrongProtocol);
+        }
+        ArpMessage::parse(&self.payload)
+    }
+}
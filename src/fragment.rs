@@ -0,0 +1,297 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::Ipv4Addr,
+};
+
+use crate::ipv4::{InternetDatagram, Ipv4Header, FRAGMENT_OFFSET_MASK};
+
+/// How long the fragments of one datagram are kept waiting for the rest.
+pub const DEFAULT_FRAGMENT_TIMEOUT: u64 = 30_000;
+/// Payload bytes the reassembler holds across all incomplete datagrams.
+pub const DEFAULT_FRAGMENT_MEMORY: usize = 256 * 1024;
+
+const OPTION_END: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_COPIED: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentError {
+    /// The datagram is larger than the MTU but has DF set.
+    DontFragment,
+    /// Not even eight bytes of payload fit next to the header.
+    MtuTooSmall,
+    /// A fragment would start past what the 13-bit offset field can hold.
+    OffsetTooLarge,
+}
+
+impl std::fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FragmentError::DontFragment => write!(f, "datagram needs fragmentation but DF is set"),
+            FragmentError::MtuTooSmall => write!(f, "MTU too small to carry any payload"),
+            FragmentError::OffsetTooLarge => write!(f, "fragment offset does not fit 13 bits"),
+        }
+    }
+}
+
+impl std::error::Error for FragmentError {}
+
+/// Keeps only the options with the copied flag set, which RFC 791 requires
+/// on every fragment after the first.
+fn copied_options(options: &[u8]) -> Vec<u8> {
+    let mut copied = Vec::new();
+    let mut rest = options;
+    while let Some(&kind) = rest.first() {
+        let length = match kind {
+            OPTION_END => break,
+            OPTION_NOP => 1,
+            _ => match rest.get(1) {
+                Some(&length) if length >= 2 && length as usize <= rest.len() => length as usize,
+                _ => break,
+            },
+        };
+        if kind & OPTION_COPIED != 0 {
+            copied.extend_from_slice(&rest[..length]);
+        }
+        rest = &rest[length..];
+    }
+    copied
+}
+
+impl InternetDatagram {
+    /// Splits the datagram into fragments no larger than `mtu` bytes each.
+    /// Fragments of a fragment keep their place in the original datagram.
+    pub fn fragment(&self, mtu: usize) -> Result<Vec<InternetDatagram>, FragmentError> {
+        if self.total_length() <= mtu {
+            return Ok(vec![self.clone()]);
+        }
+        if self.header.dont_fragment {
+            return Err(FragmentError::DontFragment);
+        }
+
+        let later_options = copied_options(&self.header.options);
+        let mut fragments = Vec::new();
+        let mut offset = 0;
+        while offset < self.payload.len() {
+            let mut header = self.header.clone();
+            if offset != 0 {
+                header.options = later_options.clone();
+            }
+            let room = mtu.saturating_sub(header.header_length()) / 8 * 8;
+            if room == 0 {
+                return Err(FragmentError::MtuTooSmall);
+            }
+
+            let fragment_offset = usize::from(self.header.fragment_offset) + offset / 8;
+            if fragment_offset > usize::from(FRAGMENT_OFFSET_MASK) {
+                return Err(FragmentError::OffsetTooLarge);
+            }
+
+            let end = (offset + room).min(self.payload.len());
+            header.fragment_offset = fragment_offset as u16;
+            header.more_fragments = self.header.more_fragments || end < self.payload.len();
+            fragments.push(InternetDatagram::new(
+                header,
+                self.payload[offset..end].to_vec(),
+            ));
+            offset = end;
+        }
+        Ok(fragments)
+    }
+}
+
+/// Fragments belong to the same datagram when all of these match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FragmentKey {
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub protocol: u8,
+    pub id: u16,
+}
+
+impl FragmentKey {
+    pub fn of(header: &Ipv4Header) -> Self {
+        FragmentKey {
+            src: header.src,
+            dst: header.dst,
+            protocol: header.protocol,
+            id: header.id,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingDatagram {
+    first_header: Option<Ipv4Header>,
+    pieces: BTreeMap<usize, Vec<u8>>,
+    total_length: Option<usize>,
+    size: usize,
+    age: u64,
+}
+
+impl PendingDatagram {
+    fn new() -> Self {
+        PendingDatagram {
+            first_header: None,
+            pieces: BTreeMap::new(),
+            total_length: None,
+            size: 0,
+            age: 0,
+        }
+    }
+
+    /// Stores the bytes of `data` not already held, like the byte-level
+    /// reassembler does, and returns how many new bytes were kept.
+    fn insert(&mut self, offset: usize, data: &[u8]) -> usize {
+        let end = offset + data.len();
+        let held = self
+            .pieces
+            .range(..end)
+            .map(|(start, piece)| (*start, *start + piece.len()))
+            .filter(|(_, stop)| *stop > offset)
+            .collect::<Vec<_>>();
+
+        let mut cursor = offset;
+        let mut kept = 0;
+        for (start, stop) in held.into_iter().chain([(end, end)]) {
+            if start > cursor {
+                let piece_end = start.min(end);
+                self.pieces
+                    .insert(cursor, data[cursor - offset..piece_end - offset].to_vec());
+                kept += piece_end - cursor;
+            }
+            cursor = cursor.max(stop);
+            if cursor >= end {
+                break;
+            }
+        }
+        self.size += kept;
+        kept
+    }
+
+    fn complete(&self) -> bool {
+        let Some(total) = self.total_length else {
+            return false;
+        };
+        let mut next = 0;
+        for (start, piece) in self.pieces.iter() {
+            if *start > next {
+                return false;
+            }
+            next = next.max(start + piece.len());
+        }
+        next >= total && self.first_header.is_some()
+    }
+}
+
+/// Puts fragmented datagrams back together, see RFC 791 section 3.2.
+#[derive(Debug)]
+pub struct FragmentReassembler {
+    pending: HashMap<FragmentKey, PendingDatagram>,
+    timeout: u64,
+    memory_limit: usize,
+    memory_used: usize,
+}
+
+impl Default for FragmentReassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAGMENT_TIMEOUT, DEFAULT_FRAGMENT_MEMORY)
+    }
+}
+
+impl FragmentReassembler {
+    pub fn new(timeout: u64, memory_limit: usize) -> Self {
+        FragmentReassembler {
+            pending: HashMap::new(),
+            timeout,
+            memory_limit,
+            memory_used: 0,
+        }
+    }
+
+    /// Number of datagrams still missing fragments.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    /// Accepts a datagram, returning it once all of its fragments arrived.
+    /// Unfragmented datagrams come straight back out.
+    pub fn push(&mut self, datagram: InternetDatagram) -> Option<InternetDatagram> {
+        let header = &datagram.header;
+        if !header.more_fragments && header.fragment_offset == 0 {
+            return Some(datagram);
+        }
+        if datagram.payload.len() > self.memory_limit {
+            return None;
+        }
+        // Never evict the datagram this fragment belongs to; if nothing else
+        // can go, the fragment is dropped instead.
+        let key = FragmentKey::of(header);
+        while self.memory_used + datagram.payload.len() > self.memory_limit {
+            if !self.evict_oldest(key) {
+                return None;
+            }
+        }
+
+        let offset = header.fragment_offset as usize * 8;
+        let pending = self.pending.entry(key).or_insert_with(PendingDatagram::new);
+        if offset == 0 {
+            pending.first_header = Some(datagram.header.clone());
+        }
+        if !datagram.header.more_fragments {
+            pending.total_length = Some(offset + datagram.payload.len());
+        }
+        self.memory_used += pending.insert(offset, &datagram.payload);
+
+        if !pending.complete() {
+            return None;
+        }
+        let pending = self.pending.remove(&key)?;
+        self.memory_used -= pending.size;
+
+        let mut header = pending.first_header?;
+        header.more_fragments = false;
+        header.fragment_offset = 0;
+        let total_length = pending.total_length?;
+        let payload = pending
+            .pieces
+            .into_values()
+            .flatten()
+            .take(total_length)
+            .collect();
+        Some(InternetDatagram::new(header, payload))
+    }
+
+    /// Drops datagrams whose fragments have been waiting too long.
+    pub fn tick(&mut self, ms_since: u64) {
+        let timeout = self.timeout;
+        let mut freed = 0;
+        self.pending.retain(|_, pending| {
+            pending.age += ms_since;
+            if pending.age >= timeout {
+                freed += pending.size;
+                return false;
+            }
+            true
+        });
+        self.memory_used -= freed;
+    }
+
+    fn evict_oldest(&mut self, keep: FragmentKey) -> bool {
+        let oldest = self
+            .pending
+            .iter()
+            .filter(|(key, _)| **key != keep)
+            .max_by_key(|(_, pending)| pending.age)
+            .map(|(key, _)| *key);
+        let Some(pending) = oldest.and_then(|key| self.pending.remove(&key)) else {
+            return false;
+        };
+        self.memory_used -= pending.size;
+        true
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    checksum::InternetChecksum,
+    ipv4::{InternetDatagram, Ipv4Header},
+    parser::{ParseError, Parser},
+};
+
+pub const ICMP_PROTOCOL: u8 = 1;
+pub const ICMP_HEADER_LENGTH: usize = 8;
+
+const TYPE_DESTINATION_UNREACHABLE: u8 = 3;
+const CODE_FRAGMENTATION_NEEDED: u8 = 4;
+
+/// Bytes of the offending datagram's payload an ICMP error quotes, enough
+/// for the ports and sequence number of a TCP segment (RFC 792).
+const QUOTED_PAYLOAD_LENGTH: usize = 8;
+
+/// An ICMP message as in RFC 792. Only what path MTU discovery needs is
+/// interpreted, anything else is carried as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IcmpMessage {
+    pub icmp_type: u8,
+    pub code: u8,
+    /// The four bytes after the checksum, whose meaning depends on the type.
+    pub rest: [u8; 4],
+    pub payload: Vec<u8>,
+}
+
+impl IcmpMessage {
+    /// Tells the sender of `datagram` that it has DF set but does not fit
+    /// the next hop's `mtu`, see RFC 1191 section 4.
+    pub fn fragmentation_needed(datagram: &InternetDatagram, mtu: u16) -> Result<Self, ParseError> {
+        let mut rest = [0; 4];
+        rest[2..].copy_from_slice(&mtu.to_be_bytes());
+        let mut payload = datagram.serialize()?;
+        payload.truncate(datagram.header.header_length() + QUOTED_PAYLOAD_LENGTH);
+        Ok(IcmpMessage {
+            icmp_type: TYPE_DESTINATION_UNREACHABLE,
+            code: CODE_FRAGMENTATION_NEEDED,
+            rest,
+            payload,
+        })
+    }
+
+    /// The MTU reported by a fragmentation needed message.
+    pub fn next_hop_mtu(&self) -> Option<u16> {
+        let fragmentation_needed = self.icmp_type == TYPE_DESTINATION_UNREACHABLE
+            && self.code == CODE_FRAGMENTATION_NEEDED;
+        fragmentation_needed.then(|| u16::from_be_bytes([self.rest[2], self.rest[3]]))
+    }
+
+    /// Whether the message reports an error, to which no error may be sent
+    /// in turn (RFC 1122 section 3.2.2).
+    pub fn is_error(&self) -> bool {
+        !matches!(self.icmp_type, 0 | 8 | 13 | 14 | 15 | 16)
+    }
+
+    /// The header of the datagram an error is about, and as much of its
+    /// payload as was quoted.
+    pub fn original(&self) -> Result<(Ipv4Header, &[u8]), ParseError> {
+        let (header, _) = Ipv4Header::parse(&self.payload)?;
+        let quoted = &self.payload[header.header_length()..];
+        Ok((header, quoted))
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ICMP_HEADER_LENGTH + self.payload.len());
+        out.push(self.icmp_type);
+        out.push(self.code);
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&self.rest);
+        out.extend_from_slice(&self.payload);
+        let mut checksum = InternetChecksum::new();
+        checksum.add(&out);
+        out[2..4].copy_from_slice(&checksum.value().to_be_bytes());
+        out
+    }
+
+    pub fn parse(data: &[u8]) -> Result<IcmpMessage, ParseError> {
+        let mut parser = Parser::new(data);
+        let icmp_type = parser.u8()?;
+        let code = parser.u8()?;
+        parser.u16()?;
+        let rest = parser.bytes(4)?.try_into().unwrap();
+        let mut checksum = InternetChecksum::new();
+        checksum.add(data);
+        if checksum.value() != 0 {
+            return Err(ParseError::BadChecksum);
+        }
+        Ok(IcmpMessage {
+            icmp_type,
+            code,
+            rest,
+            payload: parser.remaining().to_vec(),
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use std::net::IpAddr;
+
+use crate::{
+    ipv4::InternetDatagram,
+    ipv6::Ipv6Datagram,
+    parser::{ParseError, Parser},
+    tcp_segment::TcpSegment,
+};
+
+/// An IPv4 address was paired with an IPv6 one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FamilyMismatch;
+
+impl std::fmt::Display for FamilyMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "local and remote address families differ")
+    }
+}
+
+impl std::error::Error for FamilyMismatch {}
+
+/// A datagram of either IP version, for code that does not care which.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpDatagram {
+    V4(InternetDatagram),
+    V6(Ipv6Datagram),
+}
+
+impl IpDatagram {
+    pub fn src(&self) -> IpAddr {
+        match self {
+            IpDatagram::V4(datagram) => IpAddr::V4(datagram.header.src),
+            IpDatagram::V6(datagram) => IpAddr::V6(datagram.header.src),
+        }
+    }
+
+    pub fn dst(&self) -> IpAddr {
+        match self {
+            IpDatagram::V4(datagram) => IpAddr::V4(datagram.header.dst),
+            IpDatagram::V6(datagram) => IpAddr::V6(datagram.header.dst),
+        }
+    }
+
+    pub fn protocol(&self) -> u8 {
+        match self {
+            IpDatagram::V4(datagram) => datagram.header.protocol,
+            IpDatagram::V6(datagram) => datagram.protocol,
+        }
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            IpDatagram::V4(datagram) => &datagram.payload,
+            IpDatagram::V6(datagram) => &datagram.payload,
+        }
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, ParseError> {
+        match self {
+            IpDatagram::V4(datagram) => datagram.serialize(),
+            IpDatagram::V6(datagram) => datagram.serialize(),
+        }
+    }
+
+    /// Parses either version, telling them apart by the version nibble.
+    pub fn parse(data: &[u8]) -> Result<IpDatagram, ParseError> {
+        match Parser::new(data).u8()? >> 4 {
+            4 => InternetDatagram::parse(data).map(IpDatagram::V4),
+            6 => Ipv6Datagram::parse(data).map(IpDatagram::V6),
+            _ => Err(ParseError::BadVersion),
+        }
+    }
+
+    /// Wraps a TCP segment, picking the IP version from the addresses. Both
+    /// addresses have to be of the same family.
+    pub fn from_tcp(
+        segment: &TcpSegment,
+        src: IpAddr,
+        dst: IpAddr,
+    ) -> Result<IpDatagram, FamilyMismatch> {
+        match (src, dst) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => Ok(IpDatagram::V4(InternetDatagram::from_tcp(
+                segment, src, dst,
+            ))),
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                Ok(IpDatagram::V6(Ipv6Datagram::from_tcp(segment, src, dst)))
+            }
+            _ => Err(FamilyMismatch),
+        }
+    }
+
+    pub fn to_tcp(&self) -> Result<TcpSegment, ParseError> {
+        match self {
+            IpDatagram::V4(datagram) => datagram.to_tcp(),
+            IpDatagram::V6(datagram) => datagram.to_tcp(),
+        }
+    }
+}
+
+impl From<InternetDatagram> for IpDatagram {
+    fn from(datagram: InternetDatagram) -> Self {
+        IpDatagram::V4(datagram)
+    }
+}
+
+impl From<Ipv6Datagram> for IpDatagram {
+    fn from(datagram: Ipv6Datagram) -> Self {
+        IpDatagram::V6(datagram)
+    }
+}
@@ -0,0 +1,238 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::{
+    checksum::InternetChecksum,
+    icmp::{IcmpMessage, ICMP_PROTOCOL},
+    parser::{ParseError, Parser},
+    tcp_segment::{TcpSegment, TCP_PROTOCOL},
+};
+
+pub const IPV4_VERSION: u8 = 4;
+pub const IPV4_HEADER_LENGTH: usize = 20;
+pub const IPV4_MAX_OPTIONS_LENGTH: usize = 40;
+/// The largest datagram the total length field can describe.
+pub const IPV4_MAX_LENGTH: usize = u16::MAX as usize;
+pub const DEFAULT_TTL: u8 = 64;
+/// The MTU every IPv4 link has to support, see RFC 791.
+pub const MIN_IPV4_MTU: usize = 68;
+
+const FLAG_DONT_FRAGMENT: u16 = 0x4000;
+const FLAG_MORE_FRAGMENTS: u16 = 0x2000;
+pub(crate) const FRAGMENT_OFFSET_MASK: u16 = 0x1fff;
+
+/// An IPv4 header as in RFC 791 section 3.1. The version, header length and
+/// total length are derived from the options and payload when serializing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4Header {
+    pub tos: u8,
+    pub id: u16,
+    pub dont_fragment: bool,
+    pub more_fragments: bool,
+    /// Offset of this fragment's payload, in units of 8 bytes.
+    pub fragment_offset: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub checksum: u16,
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub options: Vec<u8>,
+}
+
+impl Default for Ipv4Header {
+    fn default() -> Self {
+        Ipv4Header {
+            tos: 0,
+            id: 0,
+            dont_fragment: false,
+            more_fragments: false,
+            fragment_offset: 0,
+            ttl: DEFAULT_TTL,
+            protocol: 0,
+            checksum: 0,
+            src: Ipv4Addr::UNSPECIFIED,
+            dst: Ipv4Addr::UNSPECIFIED,
+            options: Vec::new(),
+        }
+    }
+}
+
+impl Ipv4Header {
+    pub fn header_length(&self) -> usize {
+        IPV4_HEADER_LENGTH + self.options.len().next_multiple_of(4)
+    }
+
+    /// Recomputes [`Ipv4Header::checksum`] after a field was changed. A header
+    /// that cannot be serialized is left with a zero checksum, serializing the
+    /// datagram reports the error.
+    pub fn compute_checksum(&mut self, payload_length: usize) {
+        self.checksum = 0;
+        let Ok(header) = self.serialize(payload_length) else {
+            return;
+        };
+        let mut checksum = InternetChecksum::new();
+        checksum.add(&header);
+        self.checksum = checksum.value();
+    }
+
+    /// Parses just the header, along with the total length it claims. The
+    /// payload may be cut short, as in the quote of an ICMP error.
+    pub fn parse(data: &[u8]) -> Result<(Ipv4Header, usize), ParseError> {
+        let mut parser = Parser::new(data);
+        let version_ihl = parser.u8()?;
+        let tos = parser.u8()?;
+        let total_length = parser.u16()? as usize;
+        let id = parser.u16()?;
+        let flags = parser.u16()?;
+        let ttl = parser.u8()?;
+        let protocol = parser.u8()?;
+        let checksum = parser.u16()?;
+        let src = Ipv4Addr::from(parser.u32()?);
+        let dst = Ipv4Addr::from(parser.u32()?);
+
+        if version_ihl >> 4 != IPV4_VERSION {
+            return Err(ParseError::BadVersion);
+        }
+        let header_length = (version_ihl & 0x0f) as usize * 4;
+        if header_length < IPV4_HEADER_LENGTH {
+            return Err(ParseError::HeaderTooShort);
+        }
+        let options = parser.bytes(header_length - IPV4_HEADER_LENGTH)?;
+        if total_length < header_length {
+            return Err(ParseError::BadLength);
+        }
+        let mut sum = InternetChecksum::new();
+        sum.add(&data[..header_length]);
+        if sum.value() != 0 {
+            return Err(ParseError::BadChecksum);
+        }
+
+        let header = Ipv4Header {
+            tos,
+            id,
+            dont_fragment: flags & FLAG_DONT_FRAGMENT != 0,
+            more_fragments: flags & FLAG_MORE_FRAGMENTS != 0,
+            fragment_offset: flags & FRAGMENT_OFFSET_MASK,
+            ttl,
+            protocol,
+            checksum,
+            src,
+            dst,
+            options: options.to_vec(),
+        };
+        Ok((header, total_length))
+    }
+
+    fn serialize(&self, payload_length: usize) -> Result<Vec<u8>, ParseError> {
+        if self.options.len() > IPV4_MAX_OPTIONS_LENGTH {
+            return Err(ParseError::BadLength);
+        }
+        let header_length = self.header_length();
+        let total_length =
+            u16::try_from(header_length + payload_length).map_err(|_| ParseError::BadLength)?;
+        let flags = (self.dont_fragment as u16 * FLAG_DONT_FRAGMENT)
+            | (self.more_fragments as u16 * FLAG_MORE_FRAGMENTS)
+            | (self.fragment_offset & FRAGMENT_OFFSET_MASK);
+
+        let mut out = Vec::with_capacity(header_length);
+        out.push((IPV4_VERSION << 4) | (header_length / 4) as u8);
+        out.push(self.tos);
+        out.extend_from_slice(&total_length.to_be_bytes());
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&flags.to_be_bytes());
+        out.push(self.ttl);
+        out.push(self.protocol);
+        out.extend_from_slice(&self.checksum.to_be_bytes());
+        out.extend_from_slice(&self.src.octets());
+        out.extend_from_slice(&self.dst.octets());
+        out.extend_from_slice(&self.options);
+        out.resize(header_length, 0);
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InternetDatagram {
+    pub header: Ipv4Header,
+    pub payload: Vec<u8>,
+}
+
+impl InternetDatagram {
+    pub fn new(header: Ipv4Header, payload: Vec<u8>) -> Self {
+        let mut datagram = InternetDatagram { header, payload };
+        datagram.compute_checksum();
+        datagram
+    }
+
+    pub fn total_length(&self) -> usize {
+        self.header.header_length() + self.payload.len()
+    }
+
+    pub fn compute_checksum(&mut self) {
+        self.header.compute_checksum(self.payload.len());
+    }
+
+    /// Serializes the datagram with a freshly computed header checksum, or
+    /// fails with [`ParseError::BadLength`] when it is longer than
+    /// [`IPV4_MAX_LENGTH`] or its options do not fit the header.
+    pub fn serialize(&self) -> Result<Vec<u8>, ParseError> {
+        let mut header = self.header.clone();
+        header.compute_checksum(self.payload.len());
+        let mut out = header.serialize(self.payload.len())?;
+        out.extend_from_slice(&self.payload);
+        Ok(out)
+    }
+
+    /// Parses a datagram, ignoring anything past its total length such as
+    /// link-layer padding.
+    pub fn parse(data: &[u8]) -> Result<InternetDatagram, ParseError> {
+        let (header, total_length) = Ipv4Header::parse(data)?;
+        let header_length = header.header_length();
+        if total_length > data.len() {
+            return Err(ParseError::BadLength);
+        }
+        Ok(InternetDatagram {
+            header,
+            payload: data[header_length..total_length].to_vec(),
+        })
+    }
+
+    /// Wraps a TCP segment, computing its checksum for the given addresses.
+    pub fn from_tcp(segment: &TcpSegment, src: Ipv4Addr, dst: Ipv4Addr) -> InternetDatagram {
+        let header = Ipv4Header {
+            protocol: TCP_PROTOCOL,
+            src,
+            dst,
+            ..Default::default()
+        };
+        let payload = segment.serialize(IpAddr::V4(src), IpAddr::V4(dst));
+        InternetDatagram::new(header, payload)
+    }
+
+    pub fn from_icmp(message: &IcmpMessage, src: Ipv4Addr, dst: Ipv4Addr) -> InternetDatagram {
+        let header = Ipv4Header {
+            protocol: ICMP_PROTOCOL,
+            src,
+            dst,
+            ..Default::default()
+        };
+        InternetDatagram::new(header, message.serialize())
+    }
+
+    pub fn to_icmp(&self) -> Result<IcmpMessage, ParseError> {
+        if self.header.protocol != ICMP_PROTOCOL {
+            return Err(ParseError::WrongProtocol);
+        }
+        IcmpMessage::parse(&self.payload)
+    }
+
+    pub fn to_tcp(&self) -> Result<TcpSegment, ParseError> {
+        if self.header.protocol != TCP_PROTOCOL {
+            return Err(ParseError::WrongProtocol);
+        }
+        TcpSegment::parse(
+            &self.payload,
+            IpAddr::V4(self.header.src),
+            IpAddr::V4(self.header.dst),
+        )
+    }
+}
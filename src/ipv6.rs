@@ -0,0 +1,233 @@
+use std::net::{IpAddr, Ipv6Addr};
+
+use crate::{
+    parser::{ParseError, Parser},
+    tcp_segment::{TcpSegment, TCP_PROTOCOL},
+};
+
+pub const IPV6_VERSION: u8 = 6;
+pub const IPV6_HEADER_LENGTH: usize = 40;
+pub const DEFAULT_HOP_LIMIT: u8 = 64;
+
+pub const HOP_BY_HOP: u8 = 0;
+pub const ROUTING: u8 = 43;
+pub const FRAGMENT: u8 = 44;
+pub const AUTHENTICATION: u8 = 51;
+pub const NO_NEXT_HEADER: u8 = 59;
+pub const DESTINATION_OPTIONS: u8 = 60;
+
+const FRAGMENT_HEADER_LENGTH: usize = 8;
+
+/// The fixed IPv6 header from RFC 8200 section 3. The payload length and
+/// next header are derived from the datagram when serializing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Header {
+    pub traffic_class: u8,
+    /// Only the low 20 bits are used.
+    pub flow_label: u32,
+    pub hop_limit: u8,
+    pub src: Ipv6Addr,
+    pub dst: Ipv6Addr,
+}
+
+impl Default for Ipv6Header {
+    fn default() -> Self {
+        Ipv6Header {
+            traffic_class: 0,
+            flow_label: 0,
+            hop_limit: DEFAULT_HOP_LIMIT,
+            src: Ipv6Addr::UNSPECIFIED,
+            dst: Ipv6Addr::UNSPECIFIED,
+        }
+    }
+}
+
+/// One extension header, `data` holds everything after its next header and
+/// length bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionHeader {
+    kind: u8,
+    data: Vec<u8>,
+}
+
+impl ExtensionHeader {
+    /// Fails with [`ParseError::BadLength`] unless the header comes out a
+    /// length its Hdr Ext Len field can express, padding included.
+    pub fn new(kind: u8, data: Vec<u8>) -> Result<ExtensionHeader, ParseError> {
+        if !Self::is_extension(kind) {
+            return Err(ParseError::Unsupported);
+        }
+        let length = data.len() + 2;
+        let valid = match kind {
+            FRAGMENT => length == FRAGMENT_HEADER_LENGTH,
+            AUTHENTICATION => {
+                length.is_multiple_of(4) && (8..=(u8::MAX as usize + 2) * 4).contains(&length)
+            }
+            _ => length.is_multiple_of(8) && (8..=(u8::MAX as usize + 1) * 8).contains(&length),
+        };
+        match valid {
+            true => Ok(ExtensionHeader { kind, data }),
+            false => Err(ParseError::BadLength),
+        }
+    }
+
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn is_extension(kind: u8) -> bool {
+        matches!(
+            kind,
+            HOP_BY_HOP | ROUTING | FRAGMENT | AUTHENTICATION | DESTINATION_OPTIONS
+        )
+    }
+
+    fn length(&self) -> usize {
+        self.data.len() + 2
+    }
+
+    fn serialize(&self, next_header: u8, out: &mut Vec<u8>) {
+        let length = match self.kind {
+            FRAGMENT => 0,
+            AUTHENTICATION => self.length() / 4 - 2,
+            _ => self.length() / 8 - 1,
+        };
+        out.push(next_header);
+        out.push(length as u8);
+        out.extend_from_slice(&self.data);
+    }
+
+    fn parse(kind: u8, parser: &mut Parser) -> Result<(ExtensionHeader, u8), ParseError> {
+        let next_header = parser.u8()?;
+        let length = parser.u8()? as usize;
+        let length = match kind {
+            FRAGMENT => FRAGMENT_HEADER_LENGTH,
+            AUTHENTICATION => (length + 2) * 4,
+            _ => (length + 1) * 8,
+        };
+        let data = parser.bytes(length - 2)?.to_vec();
+        Ok((ExtensionHeader { kind, data }, next_header))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ipv6Datagram {
+    pub header: Ipv6Header,
+    pub extensions: Vec<ExtensionHeader>,
+    /// The upper-layer protocol following the last extension header.
+    pub protocol: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Ipv6Datagram {
+    pub fn payload_length(&self) -> usize {
+        self.extensions
+            .iter()
+            .map(ExtensionHeader::length)
+            .sum::<usize>()
+            + self.payload.len()
+    }
+
+    /// Fails with [`ParseError::BadLength`] when the extension headers and
+    /// payload do not fit the 16-bit payload length, as jumbograms are not
+    /// supported.
+    pub fn serialize(&self) -> Result<Vec<u8>, ParseError> {
+        let payload_length =
+            u16::try_from(self.payload_length()).map_err(|_| ParseError::BadLength)?;
+        let next_headers = self
+            .extensions
+            .iter()
+            .map(|extension| extension.kind)
+            .chain([self.protocol]);
+        let first_header = next_headers.clone().next().unwrap_or(self.protocol);
+        let version_class_flow = (IPV6_VERSION as u32) << 28
+            | (self.header.traffic_class as u32) << 20
+            | (self.header.flow_label & 0xfffff);
+
+        let mut out = Vec::with_capacity(IPV6_HEADER_LENGTH + self.payload_length());
+        out.extend_from_slice(&version_class_flow.to_be_bytes());
+        out.extend_from_slice(&payload_length.to_be_bytes());
+        out.push(first_header);
+        out.push(self.header.hop_limit);
+        out.extend_from_slice(&self.header.src.octets());
+        out.extend_from_slice(&self.header.dst.octets());
+        for (extension, next_header) in self.extensions.iter().zip(next_headers.skip(1)) {
+            extension.serialize(next_header, &mut out);
+        }
+        out.extend_from_slice(&self.payload);
+        Ok(out)
+    }
+
+    /// Parses a datagram, walking the extension header chain until the
+    /// upper-layer protocol.
+    pub fn parse(data: &[u8]) -> Result<Ipv6Datagram, ParseError> {
+        let mut parser = Parser::new(data);
+        let version_class_flow = parser.u32()?;
+        let payload_length = parser.u16()? as usize;
+        let mut next_header = parser.u8()?;
+        let hop_limit = parser.u8()?;
+        let src = Ipv6Addr::from(parser.u128()?);
+        let dst = Ipv6Addr::from(parser.u128()?);
+
+        if (version_class_flow >> 28) as u8 != IPV6_VERSION {
+            return Err(ParseError::BadVersion);
+        }
+        if payload_length > parser.remaining().len() {
+            return Err(ParseError::BadLength);
+        }
+
+        let mut parser = Parser::new(&parser.remaining()[..payload_length]);
+        let mut extensions = Vec::new();
+        while ExtensionHeader::is_extension(next_header) {
+            let (extension, next) = ExtensionHeader::parse(next_header, &mut parser)?;
+            extensions.push(extension);
+            next_header = next;
+        }
+
+        let payload = match next_header {
+            NO_NEXT_HEADER => Vec::new(),
+            _ => parser.remaining().to_vec(),
+        };
+        let header = Ipv6Header {
+            traffic_class: (version_class_flow >> 20) as u8,
+            flow_label: version_class_flow & 0xfffff,
+            hop_limit,
+            src,
+            dst,
+        };
+        Ok(Ipv6Datagram {
+            header,
+            extensions,
+            protocol: next_header,
+            payload,
+        })
+    }
+
+    pub fn from_tcp(segment: &TcpSegment, src: Ipv6Addr, dst: Ipv6Addr) -> Ipv6Datagram {
+        Ipv6Datagram {
+            header: Ipv6Header {
+                src,
+                dst,
+                ..Default::default()
+            },
+            extensions: Vec::new(),
+            protocol: TCP_PROTOCOL,
+            payload: segment.serialize(IpAddr::V6(src), IpAddr::V6(dst)),
+        }
+    }
+
+    pub fn to_tcp(&self) -> Result<TcpSegment, ParseError> {
+        if self.protocol != TCP_PROTOCOL {
+            return Err(ParseError::WrongProtocol);
+        }
+        TcpSegment::parse(
+            &self.payload,
+            IpAddr::V6(self.header.src),
+            IpAddr::V6(self.header.dst),
+        )
+    }
+}
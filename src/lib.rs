@@ -1,13 +1,39 @@
 use byte_stream::ByteStream;
+use congestion::CongestionControl;
 use rand::Rng;
+use rtt::{DEFAULT_MAX_RTO, DEFAULT_MIN_RTO};
 use sequence::RelativeSequence;
+use tcp_connection::TcpConnection;
+use tcp_receiver::{window_scale, DEFAULT_ACK_TIMEOUT};
+use tcp_segment::ipv4_mss_for_mtu;
 use tcp_sender::TcpSender;
 
+pub mod arp;
+pub mod bbr;
 pub mod byte_stream;
+pub mod checksum;
+pub mod congestion;
+pub mod cubic;
+pub mod ethernet;
+pub mod fragment;
+pub mod icmp;
+pub mod ip;
+pub mod ipv4;
+pub mod ipv6;
+pub mod network_interface;
+pub mod parser;
+pub mod plpmtud;
 pub mod reassembler;
+pub mod reno;
+pub mod router;
+pub mod rtt;
 pub mod sequence;
+pub mod tcp_adapter;
+pub mod tcp_connection;
 pub mod tcp_receiver;
+pub mod tcp_segment;
 pub mod tcp_sender;
+pub mod tcp_state;
 
 #[derive(Default, Clone, Debug)]
 pub struct TcpSenderMessage {
@@ -15,6 +41,16 @@ pub struct TcpSenderMessage {
     pub syn: bool,
     pub payload: Vec<u8>,
     pub fin: bool,
+    pub rst: bool,
+    /// Offers to receive SACK blocks, only meaningful on a SYN (RFC 2018).
+    pub sack_permitted: bool,
+    /// The shift this end applies to the windows it advertises, offered on a
+    /// SYN (RFC 7323).
+    pub window_scale: Option<u8>,
+    /// When the segment left, in the sender's clock (TSval of RFC 7323).
+    pub timestamp: Option<u32>,
+    /// The largest payload this end accepts, announced on a SYN.
+    pub mss: Option<u16>,
 }
 
 impl TcpSenderMessage {
@@ -24,6 +60,11 @@ impl TcpSenderMessage {
             syn: false,
             payload: Default::default(),
             fin: false,
+            rst: false,
+            sack_permitted: false,
+            window_scale: None,
+            timestamp: None,
+            mss: None,
         }
     }
 
@@ -42,6 +83,31 @@ impl TcpSenderMessage {
         self
     }
 
+    pub fn with_rst(mut self) -> TcpSenderMessage {
+        self.rst = true;
+        self
+    }
+
+    pub fn with_sack_permitted(mut self) -> TcpSenderMessage {
+        self.sack_permitted = true;
+        self
+    }
+
+    pub fn with_window_scale(mut self, shift: u8) -> TcpSenderMessage {
+        self.window_scale = Some(shift);
+        self
+    }
+
+    pub fn with_mss(mut self, mss: u16) -> TcpSenderMessage {
+        self.mss = Some(mss);
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: u32) -> TcpSenderMessage {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
     pub fn with_payload(mut self, payload: &[u8]) -> TcpSenderMessage {
         self.payload = payload.to_vec();
         self
@@ -56,10 +122,25 @@ impl TcpSenderMessage {
     }
 }
 
-#[derive(Default)]
+/// A block of data the receiver holds beyond the acknowledgment number,
+/// from `left` up to but excluding `right`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SackBlock {
+    pub left: RelativeSequence,
+    pub right: RelativeSequence,
+}
+
+#[derive(Default, Clone, Debug)]
 pub struct TcpReceiverMessage {
     pub ack_no: Option<RelativeSequence>,
+    /// As on the wire, scaled down by the negotiated window scale.
     pub window_size: u16,
+    pub rst: bool,
+    /// Out-of-order data held by the receiver, the most recently received
+    /// first (RFC 2018).
+    pub sack: Vec<SackBlock>,
+    /// The peer's timestamp being echoed (TSecr of RFC 7323).
+    pub timestamp_echo: Option<u32>,
 }
 
 impl TcpReceiverMessage {
@@ -67,6 +148,9 @@ impl TcpReceiverMessage {
         TcpReceiverMessage {
             ack_no: None,
             window_size: 0,
+            rst: false,
+            sack: Vec::new(),
+            timestamp_echo: None,
         }
     }
 
@@ -79,6 +163,38 @@ impl TcpReceiverMessage {
         self.window_size = size;
         self
     }
+
+    pub fn with_rst(mut self) -> TcpReceiverMessage {
+        self.rst = true;
+        self
+    }
+
+    pub fn with_timestamp_echo(mut self, timestamp: u32) -> TcpReceiverMessage {
+        self.timestamp_echo = Some(timestamp);
+        self
+    }
+
+    pub fn with_sack(mut self, left: u32, right: u32) -> TcpReceiverMessage {
+        self.sack.push(SackBlock {
+            left: RelativeSequence(left),
+            right: RelativeSequence(right),
+        });
+        self
+    }
+}
+
+/// A full segment as seen by a [`TcpConnection`]: the sending half of one
+/// endpoint combined with the acknowledgment and window of its receiving half.
+#[derive(Default, Clone, Debug)]
+pub struct TcpMessage {
+    pub sender: TcpSenderMessage,
+    pub receiver: TcpReceiverMessage,
+}
+
+impl TcpMessage {
+    pub fn new(sender: TcpSenderMessage, receiver: TcpReceiverMessage) -> TcpMessage {
+        TcpMessage { sender, receiver }
+    }
 }
 
 pub const DEFAULT_CAPACITY: usize = 64000;
@@ -91,6 +207,19 @@ pub struct TcpConfig {
     recv_capacity: usize,
     send_capacity: usize,
     fixed_isn: Option<RelativeSequence>,
+    adaptive_rto: bool,
+    min_rto: u64,
+    max_rto: u64,
+    congestion: Option<Box<dyn CongestionControl>>,
+    sack: bool,
+    timestamps: bool,
+    nodelay: bool,
+    delayed_ack: bool,
+    ack_timeout: u64,
+    sws_avoidance: bool,
+    mss: usize,
+    mtu: Option<usize>,
+    mtu_probing: bool,
 }
 
 impl Default for TcpConfig {
@@ -106,6 +235,19 @@ impl TcpConfig {
             recv_capacity: DEFAULT_CAPACITY,
             send_capacity: DEFAULT_CAPACITY,
             fixed_isn: None,
+            adaptive_rto: false,
+            min_rto: DEFAULT_MIN_RTO,
+            max_rto: DEFAULT_MAX_RTO,
+            congestion: None,
+            sack: false,
+            timestamps: false,
+            nodelay: false,
+            delayed_ack: false,
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            sws_avoidance: false,
+            mss: MAX_PAYLOAD_SIZE,
+            mtu: None,
+            mtu_probing: false,
         }
     }
 
@@ -119,6 +261,94 @@ impl TcpConfig {
         self
     }
 
+    /// Adapts the RTO to measured round-trip times as in RFC 6298, with
+    /// [`TcpConfig::rt_timeout`] only used until the first measurement.
+    pub fn adaptive_rto(mut self, adaptive: bool) -> Self {
+        self.adaptive_rto = adaptive;
+        self
+    }
+
+    pub fn min_rto(mut self, rto: u64) -> Self {
+        self.min_rto = rto;
+        self
+    }
+
+    pub fn max_rto(mut self, rto: u64) -> Self {
+        self.max_rto = rto;
+        self
+    }
+
+    /// Picks the congestion control algorithm, by default only the
+    /// receiver's window limits the sender.
+    pub fn congestion_control(mut self, congestion: impl CongestionControl + 'static) -> Self {
+        self.congestion = Some(Box::new(congestion));
+        self
+    }
+
+    /// Negotiates selective acknowledgments (RFC 2018) with the peer.
+    pub fn sack(mut self, enabled: bool) -> Self {
+        self.sack = enabled;
+        self
+    }
+
+    /// Negotiates the timestamps option (RFC 7323), which gives an RTT
+    /// sample with every acknowledgment and guards against old duplicates.
+    pub fn timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Sends every write straight away. By default Nagle's algorithm holds
+    /// small segments while earlier data is unacknowledged, so
+    /// byte-at-a-time writes do not flood the network.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Acknowledges every second full-sized segment instead of every one,
+    /// waiting at most [`TcpConfig::ack_timeout`] for the second.
+    pub fn delayed_ack(mut self, enabled: bool) -> Self {
+        self.delayed_ack = enabled;
+        self
+    }
+
+    pub fn ack_timeout(mut self, timeout: u64) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// Avoids the silly window syndrome (RFC 1122) on both ends: the receiver
+    /// only reopens its window in sizeable steps and the sender does not
+    /// fill a small window while data is unacknowledged.
+    pub fn sws_avoidance(mut self, enabled: bool) -> Self {
+        self.sws_avoidance = enabled;
+        self
+    }
+
+    /// The largest payload to send and to accept, announced to the peer in
+    /// the SYN. Segments are no larger than what the peer announced either.
+    pub fn mss(mut self, mss: usize) -> Self {
+        self.mss = mss;
+        self
+    }
+
+    /// The MTU of the local link, which caps the MSS so that segments fit
+    /// into a single IPv4 datagram.
+    pub fn mtu(mut self, mtu: usize) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    /// Probes for the path MTU as in RFC 4821 instead of relying on ICMP
+    /// alone, starting from segments small enough for any path.
+    pub fn mtu_probing(mut self, enabled: bool) -> Self {
+        self.mtu_probing = enabled;
+        self
+    }
+
+    /// Windows above 64 KiB are advertised with the window scale option,
+    /// which allows up to a gigabyte when the peer supports it.
     pub fn recv_capacity(mut self, capacity: usize) -> Self {
         self.recv_capacity = capacity;
         self
@@ -137,7 +367,45 @@ impl TcpConfig {
             RelativeSequence(isn)
         };
         let byte_stream = ByteStream::new(self.send_capacity);
-        let sender = TcpSender::new(isn, self.rt_timeout);
+        let mut sender = TcpSender::new(isn, self.rt_timeout);
+        if self.adaptive_rto {
+            sender = sender.with_adaptive_rto(self.min_rto, self.max_rto);
+        }
+        if let Some(congestion) = self.congestion {
+            sender = sender.with_congestion_control(congestion);
+        }
+        if self.sack {
+            sender = sender.with_sack();
+        }
+        if self.timestamps {
+            sender = sender.with_timestamps();
+        }
+        if !self.nodelay {
+            sender = sender.with_nagle();
+        }
+        if self.sws_avoidance {
+            sender = sender.with_sws_avoidance();
+        }
+        sender = sender.with_window_scale(window_scale(self.recv_capacity));
+        let mss = self
+            .mtu
+            .map_or(self.mss, |mtu| self.mss.min(ipv4_mss_for_mtu(mtu)));
+        sender = sender.with_mss(mss);
+        if self.mtu_probing {
+            sender = sender.with_mtu_probing();
+        }
         (sender, byte_stream)
     }
+
+    pub fn generate_connection(self) -> TcpConnection {
+        let rt_timeout = self.rt_timeout;
+        let recv_capacity = self.recv_capacity;
+        let delayed_ack = self.delayed_ack.then_some(self.ack_timeout);
+        let (sender, outbound) = self.generate_parts();
+        let connection = TcpConnection::new(sender, outbound, recv_capacity, rt_timeout);
+        match delayed_ack {
+            Some(timeout) => connection.with_delayed_ack(timeout),
+            None => connection,
+        }
+    }
 }
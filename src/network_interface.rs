@@ -0,0 +1,169 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::Ipv4Addr,
+};
+
+use crate::{
+    arp::{ArpMessage, ArpOpcode},
+    ethernet::{EthernetAddress, EthernetFrame, ETHERNET_MTU, ETHERTYPE_ARP, ETHERTYPE_IPV4},
+    ipv4::{InternetDatagram, IPV4_MAX_LENGTH, MIN_IPV4_MTU},
+};
+
+/// How long a learned IP to Ethernet mapping is trusted.
+pub const ARP_CACHE_TTL: u64 = 30_000;
+/// How long to wait for a reply before asking for the same address again.
+pub const ARP_REQUEST_INTERVAL: u64 = 5_000;
+
+/// Connects the IP layer to an Ethernet link, translating next-hop IP
+/// addresses into Ethernet addresses with ARP.
+#[derive(Debug)]
+pub struct NetworkInterface {
+    ethernet_address: EthernetAddress,
+    ip_address: Ipv4Addr,
+    mtu: usize,
+
+    /// Learned mappings along with how long ago they were learned.
+    arp_cache: HashMap<Ipv4Addr, (EthernetAddress, u64)>,
+    /// Outstanding requests along with how long ago they were sent.
+    arp_requests: HashMap<Ipv4Addr, u64>,
+    /// Datagrams waiting for their next hop to be resolved.
+    waiting: HashMap<Ipv4Addr, Vec<InternetDatagram>>,
+
+    frames_out: VecDeque<EthernetFrame>,
+    datagrams_in: VecDeque<InternetDatagram>,
+}
+
+impl NetworkInterface {
+    pub fn new(ethernet_address: EthernetAddress, ip_address: Ipv4Addr) -> Self {
+        NetworkInterface {
+            ethernet_address,
+            ip_address,
+            mtu: ETHERNET_MTU,
+            arp_cache: HashMap::new(),
+            arp_requests: HashMap::new(),
+            waiting: HashMap::new(),
+            frames_out: VecDeque::new(),
+            datagrams_in: VecDeque::new(),
+        }
+    }
+
+    pub fn ethernet_address(&self) -> EthernetAddress {
+        self.ethernet_address
+    }
+
+    pub fn ip_address(&self) -> Ipv4Addr {
+        self.ip_address
+    }
+
+    /// Limits datagrams on the link to `mtu` bytes, instead of what
+    /// Ethernet allows. Routers fragment to fit, or report that they could
+    /// not with ICMP. No link carries less than [`MIN_IPV4_MTU`].
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu.max(MIN_IPV4_MTU);
+        self
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Sends `datagram` towards `next_hop`, queuing it behind an ARP request
+    /// when the next hop's Ethernet address is not known yet. Datagrams longer
+    /// than [`IPV4_MAX_LENGTH`] cannot be framed and are dropped.
+    pub fn send_datagram(&mut self, datagram: InternetDatagram, next_hop: Ipv4Addr) {
+        if datagram.total_length() > IPV4_MAX_LENGTH {
+            return;
+        }
+        if let Some((ethernet_address, _)) = self.arp_cache.get(&next_hop) {
+            if let Ok(frame) =
+                EthernetFrame::from_datagram(&datagram, self.ethernet_address, *ethernet_address)
+            {
+                self.frames_out.push_back(frame);
+            }
+            return;
+        }
+
+        self.waiting.entry(next_hop).or_default().push(datagram);
+        if self.arp_requests.contains_key(&next_hop) {
+            return;
+        }
+        self.arp_requests.insert(next_hop, 0);
+        let request = ArpMessage::request(self.ethernet_address, self.ip_address, next_hop);
+        self.frames_out.push_back(EthernetFrame::from_arp(&request));
+    }
+
+    /// Takes a frame off the link. IPv4 datagrams addressed to us become
+    /// available from [`NetworkInterface::try_receive`], ARP traffic is
+    /// handled here.
+    pub fn recv_frame(&mut self, frame: EthernetFrame) {
+        let dst = frame.header.dst;
+        if dst != self.ethernet_address && !dst.is_broadcast() {
+            return;
+        }
+
+        match frame.header.ethertype {
+            ETHERTYPE_IPV4 => {
+                if let Ok(datagram) = frame.to_datagram() {
+                    self.datagrams_in.push_back(datagram);
+                }
+            }
+            ETHERTYPE_ARP => {
+                if let Ok(message) = frame.to_arp() {
+                    self.receive_arp(message);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn receive_arp(&mut self, message: ArpMessage) {
+        let sender_ip = message.sender_ip_address;
+        let sender_ethernet = message.sender_ethernet_address;
+        self.arp_cache.insert(sender_ip, (sender_ethernet, 0));
+        self.arp_requests.remove(&sender_ip);
+        for datagram in self.waiting.remove(&sender_ip).unwrap_or_default() {
+            if let Ok(frame) =
+                EthernetFrame::from_datagram(&datagram, self.ethernet_address, sender_ethernet)
+            {
+                self.frames_out.push_back(frame);
+            }
+        }
+
+        if message.opcode == ArpOpcode::Request && message.target_ip_address == self.ip_address {
+            let reply = ArpMessage::reply(
+                self.ethernet_address,
+                self.ip_address,
+                sender_ethernet,
+                sender_ip,
+            );
+            self.frames_out.push_back(EthernetFrame::from_arp(&reply));
+        }
+    }
+
+    /// Expires cached mappings, and gives up on requests that went
+    /// unanswered, dropping the datagrams that were waiting on them.
+    pub fn tick(&mut self, ms_since: u64) {
+        self.arp_cache.retain(|_, (_, age)| {
+            *age += ms_since;
+            *age < ARP_CACHE_TTL
+        });
+
+        let waiting = &mut self.waiting;
+        self.arp_requests.retain(|ip, age| {
+            *age += ms_since;
+            if *age < ARP_REQUEST_INTERVAL {
+                return true;
+            }
+            waiting.remove(ip);
+            false
+        });
+    }
+
+    pub fn try_send(&mut self) -> Option<EthernetFrame> {
+        self.frames_out.pop_front()
+    }
+
+    pub fn try_receive(&mut self) -> Option<InternetDatagram> {
+        self.datagrams_in.pop_front()
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The buffer ended before the header or payload did.
+    Truncated,
+    /// A header length field points inside the fixed part of the header.
+    HeaderTooShort,
+    /// A length field disagrees with the size of the buffer.
+    BadLength,
+    BadChecksum,
+    BadVersion,
+    BadOption,
+    /// The payload is not the protocol the caller asked for.
+    WrongProtocol,
+    /// A well-formed field holds a value this crate does not handle.
+    Unsupported,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            ParseError::Truncated => "buffer is truncated",
+            ParseError::HeaderTooShort => "header length is too short",
+            ParseError::BadLength => "length field does not match the buffer",
+            ParseError::BadChecksum => "checksum mismatch",
+            ParseError::BadVersion => "unexpected version",
+            ParseError::BadOption => "malformed option",
+            ParseError::WrongProtocol => "unexpected protocol",
+            ParseError::Unsupported => "unsupported field value",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Reads big-endian fields off the front of a buffer.
+pub(crate) struct Parser<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Parser { buffer }
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], ParseError> {
+        if self.buffer.len() < count {
+            return Err(ParseError::Truncated);
+        }
+        let (bytes, rest) = self.buffer.split_at(count);
+        self.buffer = rest;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, ParseError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, ParseError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn u128(&mut self) -> Result<u128, ParseError> {
+        let bytes = self.bytes(16)?;
+        Ok(u128::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buffer
+    }
+}
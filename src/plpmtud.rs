@@ -0,0 +1,82 @@
+/// The segment size a search starts from, small enough for any IPv4 path
+/// (RFC 4821 section 7.2).
+pub const BASE_MSS: usize = 536;
+/// Losses of one probe size before the search gives up on it, as
+/// MAX_PROBES in RFC 4821 section 7.6.3.
+pub const MAX_PROBES: u32 = 3;
+/// The search stops once the largest size known to work is this close to
+/// the smallest size not ruled out.
+pub const SEARCH_PRECISION: usize = 16;
+
+/// The search for the largest segment the path carries, as packetization
+/// layer path MTU discovery does in RFC 4821. It goes by probes: single
+/// segments larger than the current size, which become the new size once
+/// acknowledged. Unlike ICMP based discovery it keeps working on paths that
+/// filter ICMP.
+///
+/// Sizes are payload bytes, the ceiling also comes from whatever else
+/// limits segments such as the peer's MSS.
+#[derive(Debug, Clone)]
+pub struct MtuSearch {
+    /// The largest size known to get through.
+    low: usize,
+    /// The largest size not ruled out yet.
+    high: usize,
+    /// Whether a probe was lost, after which the search bisects.
+    failed: bool,
+    /// Losses of the size probed last.
+    losses: u32,
+}
+
+impl Default for MtuSearch {
+    fn default() -> Self {
+        Self::new(BASE_MSS)
+    }
+}
+
+impl MtuSearch {
+    pub fn new(base: usize) -> Self {
+        MtuSearch {
+            low: base,
+            high: usize::MAX,
+            failed: false,
+            losses: 0,
+        }
+    }
+
+    /// The size to use for regular segments.
+    pub fn mss(&self, ceiling: usize) -> usize {
+        self.low.min(ceiling)
+    }
+
+    /// The size of the next probe, if there is still something to find out.
+    /// The first probe tries the ceiling, after a loss the search bisects.
+    pub fn next_probe(&self, ceiling: usize) -> Option<usize> {
+        let high = self.high.min(ceiling);
+        if high <= self.low || (self.failed && high - self.low < SEARCH_PRECISION) {
+            return None;
+        }
+        match self.failed {
+            false => Some(high),
+            true => Some(self.low + (high - self.low).div_ceil(2)),
+        }
+    }
+
+    /// A probe of `size` was acknowledged, so segments can be that large.
+    pub fn probe_acked(&mut self, size: usize) {
+        self.low = self.low.max(size);
+        self.losses = 0;
+    }
+
+    /// A probe of `size` was lost, after [`MAX_PROBES`] in a row the size
+    /// is taken to be too large for the path.
+    pub fn probe_lost(&mut self, size: usize) {
+        self.losses += 1;
+        if self.losses < MAX_PROBES {
+            return;
+        }
+        self.losses = 0;
+        self.failed = true;
+        self.high = self.high.min(size - 1);
+    }
+}
@@ -1,23 +1,40 @@
+use std::{collections::BTreeMap, ops::Range};
+
 use crate::byte_stream::ByteStream;
 
+#[derive(Debug)]
 pub struct Reassembler {
-    buffer: Vec<Option<u8>>,
+    /// Bytes held back, keyed by stream index. Pieces never overlap or touch,
+    /// so each one is a range waiting for the gap before it to be filled.
+    pieces: BTreeMap<usize, Vec<u8>>,
+    pending: usize,
+    capacity: usize,
     current_index: usize,
+    end_index: Option<usize>,
 }
 
 impl Reassembler {
     pub fn new(capacity: usize) -> Reassembler {
         Reassembler {
-            buffer: vec![None; capacity],
+            pieces: BTreeMap::new(),
+            pending: 0,
+            capacity,
             current_index: 0,
+            end_index: None,
         }
     }
 
     pub fn push(&mut self, first_index: usize, data: &[u8], last: bool, writer: &mut ByteStream) {
         if last {
+            self.end_index = Some(first_index + data.len());
+        }
+        self.push_inner(first_index, data, writer);
+        if self.end_index == Some(self.current_index) {
             writer.close();
         }
+    }
 
+    fn push_inner(&mut self, first_index: usize, data: &[u8], writer: &mut ByteStream) {
         let (data, first_index) = if first_index <= self.current_index {
             let diff = self.current_index - first_index;
             if data.len() < diff {
@@ -33,34 +50,58 @@ impl Reassembler {
             return;
         }
 
-        if first_index >= self.buffer.len() {
+        if first_index >= self.capacity {
             return;
         }
 
-        let last_index = data.len().min(writer.avalible_capacity()) + first_index;
+        let last_index =
+            (data.len().min(writer.avalible_capacity()) + first_index).min(self.capacity);
+        self.insert(
+            self.current_index + first_index,
+            &data[..last_index - first_index],
+        );
 
-        self.buffer[first_index..last_index]
-            .iter_mut()
-            .zip(data.iter())
-            .for_each(|(buf, d)| *buf = Some(*d));
-
-        let buf = self
-            .buffer
-            .iter_mut()
-            .map_while(|x| x.take())
-            .collect::<Vec<_>>();
+        let Some(buf) = self.pieces.remove(&self.current_index) else {
+            return;
+        };
+        self.pending -= buf.len();
+        self.current_index += writer.push(&buf);
+    }
 
-        for i in 0..(self.buffer.len() - buf.len()) {
-            self.buffer[i] = self.buffer[i + buf.len()]
-        }
-        for i in (self.buffer.len() - buf.len())..self.buffer.len() {
-            self.buffer[i] = None
+    /// Stores `data` at stream index `start`, merging it with the pieces it
+    /// overlaps or touches. Newer bytes win where they overlap.
+    fn insert(&mut self, start: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
         }
+        let end = start + data.len();
+        let before = self
+            .pieces
+            .range(..=start)
+            .next_back()
+            .filter(|(index, piece)| *index + piece.len() >= start)
+            .map(|(index, _)| *index);
+        let (piece_start, mut piece) = match before {
+            Some(index) => (index, self.pieces.remove(&index).unwrap_or_default()),
+            None => (start, Vec::new()),
+        };
+        self.pending -= piece.len();
 
-        if buf.is_empty() {
-            return;
+        let offset = start - piece_start;
+        let overlap = piece.len().saturating_sub(offset).min(data.len());
+        piece[offset..offset + overlap].copy_from_slice(&data[..overlap]);
+        piece.extend_from_slice(&data[overlap..]);
+
+        while let Some((&index, _)) = self.pieces.range(piece_start + 1..=end).next() {
+            let next = self.pieces.remove(&index).unwrap_or_default();
+            self.pending -= next.len();
+            let next_end = index + next.len();
+            if next_end > end {
+                piece.extend_from_slice(&next[end - index..]);
+            }
         }
-        self.current_index += writer.push(&buf);
+        self.pending += piece.len();
+        self.pieces.insert(piece_start, piece);
     }
 
     pub fn push_str(
@@ -74,6 +115,23 @@ impl Reassembler {
     }
 
     pub fn pending(&self) -> usize {
-        self.buffer.iter().filter(|x| x.is_some()).count()
+        self.pending
+    }
+
+    /// The contiguous ranges of stream indices held back waiting for a gap
+    /// to be filled, in order.
+    pub fn buffered(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.pieces
+            .iter()
+            .map(|(index, piece)| *index..*index + piece.len())
+    }
+
+    /// The buffered range holding stream index `index`, if any.
+    pub fn buffered_range(&self, index: usize) -> Option<Range<usize>> {
+        self.pieces
+            .range(..=index)
+            .next_back()
+            .map(|(start, piece)| *start..*start + piece.len())
+            .filter(|range| range.contains(&index))
     }
 }
@@ -0,0 +1,153 @@
+use crate::{
+    congestion::{rescale_window, AckEvent, CongestionControl},
+    MAX_PAYLOAD_SIZE,
+};
+
+/// Duplicate acknowledgments the sender waits for before fast retransmit,
+/// each of which is a segment that left the network.
+const DUPLICATE_THRESHOLD: u64 = 3;
+
+/// Progress through one fast recovery, counted from the loss.
+#[derive(Debug, Clone, Copy)]
+struct Recovery {
+    /// What was in flight when the loss was detected, all of which must be
+    /// acknowledged to leave recovery.
+    flight: u64,
+    acked: u64,
+}
+
+/// Reno congestion control as in RFC 5681, by default with the NewReno
+/// modification to fast recovery from RFC 6582.
+#[derive(Debug, Clone)]
+pub struct Reno {
+    mss: u64,
+    cwnd: u64,
+    ssthresh: u64,
+    /// Bytes acknowledged towards the next increase in congestion avoidance.
+    bytes_acked: u64,
+    recovery: Option<Recovery>,
+    new_reno: bool,
+}
+
+impl Default for Reno {
+    fn default() -> Self {
+        Self::new(MAX_PAYLOAD_SIZE as u64)
+    }
+}
+
+impl Reno {
+    /// NewReno, which stays in fast recovery until everything in flight at
+    /// the time of the loss was acknowledged.
+    pub fn new(mss: u64) -> Self {
+        Reno {
+            mss,
+            cwnd: Self::initial_window(mss),
+            ssthresh: u64::MAX,
+            bytes_acked: 0,
+            recovery: None,
+            new_reno: true,
+        }
+    }
+
+    /// Classic Reno, which leaves fast recovery on the first new
+    /// acknowledgment.
+    pub fn classic(mss: u64) -> Self {
+        Reno {
+            new_reno: false,
+            ..Self::new(mss)
+        }
+    }
+
+    /// The initial window from RFC 5681 section 3.1.
+    pub fn initial_window(mss: u64) -> u64 {
+        match mss {
+            0..=1095 => 4 * mss,
+            1096..=2190 => 3 * mss,
+            _ => 2 * mss,
+        }
+    }
+
+    pub fn mss(&self) -> u64 {
+        self.mss
+    }
+
+    fn reduce(&mut self, in_flight: u64) {
+        self.ssthresh = (in_flight / 2).max(2 * self.mss);
+        self.bytes_acked = 0;
+    }
+}
+
+impl CongestionControl for Reno {
+    fn on_ack(&mut self, event: &AckEvent) {
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.acked += event.acked;
+            if self.new_reno && recovery.acked < recovery.flight {
+                // A partial acknowledgment: deflate by what left the network
+                // and make room for the retransmission of the next hole.
+                self.cwnd = self.cwnd.saturating_sub(event.acked);
+                if event.acked >= self.mss {
+                    self.cwnd += self.mss;
+                }
+                self.cwnd = self.cwnd.max(self.mss);
+                return;
+            }
+            self.recovery = None;
+            self.cwnd = self.ssthresh.min(event.in_flight.max(self.mss) + self.mss);
+            return;
+        }
+
+        if self.cwnd < self.ssthresh {
+            self.cwnd += event.acked.min(self.mss);
+            return;
+        }
+        self.bytes_acked += event.acked;
+        if self.bytes_acked >= self.cwnd {
+            self.bytes_acked -= self.cwnd;
+            self.cwnd += self.mss;
+        }
+    }
+
+    fn on_loss(&mut self, in_flight: u64) {
+        if self.recovery.is_some() {
+            return;
+        }
+        self.reduce(in_flight);
+        self.cwnd = self.ssthresh + DUPLICATE_THRESHOLD * self.mss;
+        self.recovery = Some(Recovery {
+            flight: in_flight,
+            acked: 0,
+        });
+    }
+
+    fn on_timeout(&mut self, in_flight: u64) {
+        self.reduce(in_flight);
+        self.cwnd = self.mss;
+        self.recovery = None;
+    }
+
+    fn on_duplicate_ack(&mut self) {
+        if self.recovery.is_some() {
+            self.cwnd += self.mss;
+        }
+    }
+
+    fn set_mss(&mut self, mss: u64) {
+        let mss = mss.max(1);
+        self.cwnd = rescale_window(self.cwnd, self.mss, mss).max(mss);
+        self.ssthresh = rescale_window(self.ssthresh, self.mss, mss).max(2 * mss);
+        self.bytes_acked = rescale_window(self.bytes_acked, self.mss, mss);
+        self.mss = mss;
+    }
+
+    fn cwnd(&self) -> u64 {
+        self.cwnd
+    }
+
+    fn ssthresh(&self) -> u64 {
+        self.ssthresh
+    }
+
+    fn in_recovery(&self) -> bool {
+        self.recovery.is_some()
+    }
+}
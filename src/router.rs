@@ -0,0 +1,169 @@
+use std::net::Ipv4Addr;
+
+use crate::{
+    fragment::FragmentError, icmp::IcmpMessage, ipv4::InternetDatagram,
+    network_interface::NetworkInterface,
+};
+
+/// One routing table entry. Datagrams whose destination starts with the
+/// first `prefix_length` bits of `prefix` leave through `interface`, either
+/// to `next_hop` or, for directly attached networks, to the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    pub prefix: Ipv4Addr,
+    pub prefix_length: u8,
+    pub next_hop: Option<Ipv4Addr>,
+    pub interface: usize,
+}
+
+impl Route {
+    fn mask(&self) -> u32 {
+        u32::MAX
+            .checked_shl(32 - self.prefix_length as u32)
+            .unwrap_or(0)
+    }
+
+    pub fn matches(&self, address: Ipv4Addr) -> bool {
+        (u32::from(address) ^ u32::from(self.prefix)) & self.mask() == 0
+    }
+}
+
+/// Forwards datagrams between its interfaces by longest prefix match.
+#[derive(Debug, Default)]
+pub struct Router {
+    interfaces: Vec<NetworkInterface>,
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches an interface and returns the index routes refer to it by.
+    pub fn add_interface(&mut self, interface: NetworkInterface) -> usize {
+        self.interfaces.push(interface);
+        self.interfaces.len() - 1
+    }
+
+    pub fn interface(&self, index: usize) -> &NetworkInterface {
+        &self.interfaces[index]
+    }
+
+    pub fn interface_mut(&mut self, index: usize) -> &mut NetworkInterface {
+        &mut self.interfaces[index]
+    }
+
+    pub fn add_route(
+        &mut self,
+        prefix: Ipv4Addr,
+        prefix_length: u8,
+        next_hop: Option<Ipv4Addr>,
+        interface: usize,
+    ) {
+        assert!(prefix_length <= 32, "prefix length {prefix_length} over 32");
+        assert!(
+            interface < self.interfaces.len(),
+            "no interface with index {interface}"
+        );
+        self.routes.push(Route {
+            prefix,
+            prefix_length,
+            next_hop,
+            interface,
+        });
+    }
+
+    /// Removes the routes for exactly this prefix, returning whether any
+    /// existed.
+    pub fn remove_route(&mut self, prefix: Ipv4Addr, prefix_length: u8) -> bool {
+        let before = self.routes.len();
+        self.routes
+            .retain(|route| route.prefix != prefix || route.prefix_length != prefix_length);
+        self.routes.len() != before
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// The most specific route towards `destination`, if any.
+    pub fn lookup(&self, destination: Ipv4Addr) -> Option<&Route> {
+        self.routes
+            .iter()
+            .filter(|route| route.matches(destination))
+            .max_by_key(|route| route.prefix_length)
+    }
+
+    /// Forwards every datagram the interfaces have received. Datagrams with
+    /// no route or whose TTL runs out are dropped. Datagrams too large for
+    /// the outgoing interface are fragmented, or with DF set, dropped and
+    /// answered with ICMP fragmentation needed.
+    pub fn route(&mut self) {
+        for index in 0..self.interfaces.len() {
+            while let Some(datagram) = self.interfaces[index].try_receive() {
+                self.forward(datagram);
+            }
+        }
+    }
+
+    fn forward(&mut self, mut datagram: InternetDatagram) {
+        if datagram.header.ttl <= 1 {
+            return;
+        }
+        let destination = datagram.header.dst;
+        let Some(route) = self.lookup(destination).copied() else {
+            return;
+        };
+        datagram.header.ttl -= 1;
+        datagram.compute_checksum();
+        let next_hop = route.next_hop.unwrap_or(destination);
+        let interface = &mut self.interfaces[route.interface];
+        let mtu = interface.mtu();
+        if datagram.total_length() <= mtu {
+            interface.send_datagram(datagram, next_hop);
+            return;
+        }
+        match datagram.fragment(mtu) {
+            Ok(fragments) => {
+                for fragment in fragments {
+                    interface.send_datagram(fragment, next_hop);
+                }
+            }
+            Err(FragmentError::DontFragment) => self.fragmentation_needed(&datagram, mtu),
+            // Interfaces carry at least MIN_IPV4_MTU bytes, room for the
+            // largest header and eight bytes of payload, so this never
+            // happens and ICMP would have no message for it anyway.
+            Err(FragmentError::MtuTooSmall) => {}
+            // A fragment near the end of a maximum-sized datagram can leave
+            // no room to describe the pieces it splits into.
+            Err(FragmentError::OffsetTooLarge) => {}
+        }
+    }
+
+    /// Tells the source of `datagram` the MTU it has to fit (RFC 1191),
+    /// unless the datagram is an ICMP error itself.
+    fn fragmentation_needed(&mut self, datagram: &InternetDatagram, mtu: usize) {
+        if datagram.to_icmp().is_ok_and(|message| message.is_error()) {
+            return;
+        }
+        let source = datagram.header.src;
+        let Some(route) = self.lookup(source).copied() else {
+            return;
+        };
+        let Ok(message) =
+            IcmpMessage::fragmentation_needed(datagram, mtu.min(u16::MAX as usize) as u16)
+        else {
+            return;
+        };
+        let interface = &mut self.interfaces[route.interface];
+        let reply = InternetDatagram::from_icmp(&message, interface.ip_address(), source);
+        interface.send_datagram(reply, route.next_hop.unwrap_or(source));
+    }
+
+    pub fn tick(&mut self, ms_since: u64) {
+        for interface in self.interfaces.iter_mut() {
+            interface.tick(ms_since);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+/// Lower bound on an adaptive RTO. RFC 6298 asks for a second, but like
+/// most stacks we allow going lower on fast links.
+pub const DEFAULT_MIN_RTO: u64 = 200;
+/// Upper bound on an adaptive RTO, backoff included.
+pub const DEFAULT_MAX_RTO: u64 = 60_000;
+
+/// Resolution of the clock driving `tick`, in milliseconds.
+const CLOCK_GRANULARITY: u64 = 1;
+
+/// Smoothed round-trip time and its variation, as computed by Jacobson and
+/// Karels and specified in RFC 6298 section 2.
+#[derive(Debug, Clone)]
+pub struct RttEstimator {
+    srtt: Option<u64>,
+    rttvar: u64,
+    min_rto: u64,
+    max_rto: u64,
+}
+
+impl Default for RttEstimator {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_RTO, DEFAULT_MAX_RTO)
+    }
+}
+
+impl RttEstimator {
+    pub fn new(min_rto: u64, max_rto: u64) -> Self {
+        assert!(min_rto <= max_rto, "minimum RTO above the maximum");
+        RttEstimator {
+            srtt: None,
+            rttvar: 0,
+            min_rto,
+            max_rto,
+        }
+    }
+
+    pub fn srtt(&self) -> Option<u64> {
+        self.srtt
+    }
+
+    /// The round-trip time variation, only meaningful once a sample arrived.
+    pub fn rttvar(&self) -> Option<u64> {
+        self.srtt.map(|_| self.rttvar)
+    }
+
+    pub fn min_rto(&self) -> u64 {
+        self.min_rto
+    }
+
+    pub fn max_rto(&self) -> u64 {
+        self.max_rto
+    }
+
+    /// Feeds one measurement. Callers must leave out segments that were
+    /// retransmitted, whose acknowledgment is ambiguous (Karn's algorithm).
+    pub fn sample(&mut self, rtt: u64) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                self.rttvar = (3 * self.rttvar + srtt.abs_diff(rtt)) / 4;
+                self.srtt = Some((7 * srtt + rtt) / 8);
+            }
+        }
+    }
+
+    /// The RTO derived from the estimate, `None` until the first sample.
+    pub fn rto(&self) -> Option<u64> {
+        let srtt = self.srtt?;
+        let rto = srtt + CLOCK_GRANULARITY.max(4 * self.rttvar);
+        Some(self.clamp(rto))
+    }
+
+    pub fn clamp(&self, rto: u64) -> u64 {
+        rto.clamp(self.min_rto, self.max_rto)
+    }
+}
@@ -0,0 +1,137 @@
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    icmp::ICMP_PROTOCOL,
+    ip::{FamilyMismatch, IpDatagram},
+    ipv4::{InternetDatagram, MIN_IPV4_MTU},
+    parser::Parser,
+    tcp_connection::TcpConnection,
+    tcp_segment::{ipv4_mss_for_mtu, TcpSegment, TCP_PROTOCOL},
+};
+
+/// Puts a [`TcpConnection`] on top of IP. The adapter does not care about
+/// the address family: it speaks IPv4 or IPv6 depending on its local address.
+///
+/// IPv4 datagrams leave with DF set, and ICMP "fragmentation needed" about
+/// them lowers the connection's MSS (RFC 1191). Over IPv6, or where ICMP is
+/// filtered, [`TcpConfig::mtu_probing`](crate::TcpConfig::mtu_probing) finds
+/// the path MTU instead.
+#[derive(Debug)]
+pub struct TcpAdapter {
+    connection: TcpConnection,
+    local: SocketAddr,
+    remote: Option<SocketAddr>,
+    next_id: u16,
+}
+
+impl TcpAdapter {
+    /// Creates an adapter that accepts the first SYN sent to `local`.
+    pub fn new(connection: TcpConnection, local: SocketAddr) -> Self {
+        TcpAdapter {
+            connection,
+            local,
+            remote: None,
+            next_id: 0,
+        }
+    }
+
+    pub fn connect(&mut self, remote: SocketAddr) -> Result<(), FamilyMismatch> {
+        if remote.is_ipv4() != self.local.is_ipv4() {
+            return Err(FamilyMismatch);
+        }
+        self.remote = Some(remote);
+        self.connection.connect();
+        Ok(())
+    }
+
+    pub fn local(&self) -> SocketAddr {
+        self.local
+    }
+
+    pub fn remote(&self) -> Option<SocketAddr> {
+        self.remote
+    }
+
+    pub fn connection(&self) -> &TcpConnection {
+        &self.connection
+    }
+
+    pub fn connection_mut(&mut self) -> &mut TcpConnection {
+        &mut self.connection
+    }
+
+    /// Hands a datagram to the connection if it belongs to it, anything else
+    /// (wrong addresses, ports, protocol or a bad checksum) is dropped.
+    pub fn receive(&mut self, datagram: &IpDatagram) {
+        if datagram.dst() != self.local.ip() {
+            return;
+        }
+        if let IpDatagram::V4(datagram) = datagram {
+            if datagram.header.protocol == ICMP_PROTOCOL {
+                self.receive_icmp(datagram);
+                return;
+            }
+        }
+        let Ok(segment) = datagram.to_tcp() else {
+            return;
+        };
+        if segment.dst_port != self.local.port() {
+            return;
+        }
+        let src = SocketAddr::new(datagram.src(), segment.src_port);
+        match self.remote {
+            Some(remote) if remote != src => return,
+            Some(_) => {}
+            None if segment.syn => self.remote = Some(src),
+            None => return,
+        }
+        self.connection.receive(segment.to_message());
+    }
+
+    /// MTUs below the IPv4 minimum are ignored so that a forged message
+    /// cannot shrink segments to nothing.
+    fn receive_icmp(&mut self, datagram: &InternetDatagram) {
+        let Some(remote) = self.remote else {
+            return;
+        };
+        let Ok(message) = datagram.to_icmp() else {
+            return;
+        };
+        let Some(mtu) = message.next_hop_mtu().map(usize::from) else {
+            return;
+        };
+        let Ok((header, quoted)) = message.original() else {
+            return;
+        };
+        let mut parser = Parser::new(quoted);
+        let (Ok(src_port), Ok(dst_port)) = (parser.u16(), parser.u16()) else {
+            return;
+        };
+        let ours = IpAddr::V4(header.src) == self.local.ip()
+            && IpAddr::V4(header.dst) == remote.ip()
+            && header.protocol == TCP_PROTOCOL
+            && src_port == self.local.port()
+            && dst_port == remote.port();
+        if ours && mtu >= MIN_IPV4_MTU {
+            self.connection.reduce_mss(ipv4_mss_for_mtu(mtu));
+        }
+    }
+
+    pub fn try_send(&mut self) -> Option<IpDatagram> {
+        let remote = self.remote?;
+        let message = self.connection.try_send()?;
+        let segment = TcpSegment::from_message(&message, self.local.port(), remote.port());
+        let mut datagram = IpDatagram::from_tcp(&segment, self.local.ip(), remote.ip()).ok()?;
+        if let IpDatagram::V4(ref mut datagram) = datagram {
+            datagram.header.id = self.next_id;
+            datagram.header.dont_fragment = true;
+            datagram.compute_checksum();
+            self.next_id = self.next_id.wrapping_add(1);
+        }
+        Some(datagram)
+    }
+
+    pub fn tick(&mut self, ms_since: u64) {
+        self.connection.tick(ms_since);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{
+    byte_stream::ByteStream,
+    reassembler::Reassembler,
+    tcp_receiver::TcpReceiver,
+    tcp_sender::TcpSender,
+    tcp_state::{TcpEvent, TcpState},
+    TcpMessage, TcpReceiverMessage, TcpSenderMessage,
+};
+
+/// How many retransmission timeouts an endpoint stays in TIME_WAIT, in case
+/// the peer did not get our last acknowledgment.
+pub const LINGER_FACTOR: u64 = 10;
+
+#[derive(Debug)]
+pub struct TcpConnection {
+    sender: TcpSender,
+    receiver: TcpReceiver,
+    reassembler: Reassembler,
+
+    outbound: ByteStream,
+    inbound: ByteStream,
+
+    rt_timeout: u64,
+    time_since_last_segment_received: u64,
+
+    need_ack: bool,
+    state: TcpState,
+}
+
+impl TcpConnection {
+    /// Creates a new [`TcpConnection`] from an already configured sender and
+    /// the stream it reads from, see [`crate::TcpConfig::generate_connection`].
+    ///
+    /// The connection starts passively open in [`TcpState::Listen`], sending
+    /// or calling [`TcpConnection::connect`] turns it into an active open.
+    pub fn new(
+        sender: TcpSender,
+        outbound: ByteStream,
+        recv_capacity: usize,
+        rt_timeout: u64,
+    ) -> Self {
+        Self {
+            receiver: TcpReceiver::new()
+                .with_sack(sender.sack_offered())
+                .with_window_scale(sender.window_scale())
+                .with_timestamps(sender.timestamps_offered())
+                .with_sws_avoidance(sender.sws_avoidance())
+                .with_mss(sender.advertised_mss()),
+            sender,
+            reassembler: Reassembler::new(recv_capacity),
+            outbound,
+            inbound: ByteStream::new(recv_capacity),
+            rt_timeout,
+            time_since_last_segment_received: 0,
+            need_ack: false,
+            state: TcpState::Listen,
+        }
+    }
+
+    /// Delays acknowledgments by up to `timeout` ms, see
+    /// [`TcpReceiver::with_delayed_ack`].
+    pub fn with_delayed_ack(mut self, timeout: u64) -> Self {
+        self.receiver = self.receiver.with_delayed_ack(Some(timeout));
+        self
+    }
+
+    pub fn active(&self) -> bool {
+        self.state != TcpState::Closed
+    }
+
+    pub fn state(&self) -> TcpState {
+        self.state
+    }
+
+    pub fn inbound(&self) -> &ByteStream {
+        &self.inbound
+    }
+
+    pub fn inbound_mut(&mut self) -> &mut ByteStream {
+        &mut self.inbound
+    }
+
+    pub fn outbound(&self) -> &ByteStream {
+        &self.outbound
+    }
+
+    /// Bytes written here are picked up by the next call to
+    /// [`TcpConnection::push`], `receive` or `tick`.
+    pub fn outbound_mut(&mut self) -> &mut ByteStream {
+        &mut self.outbound
+    }
+
+    pub fn sender(&self) -> &TcpSender {
+        &self.sender
+    }
+
+    pub fn receiver(&self) -> &TcpReceiver {
+        &self.receiver
+    }
+
+    pub fn bytes_in_flight(&self) -> u64 {
+        self.sender.seq_in_flight().0
+    }
+
+    pub fn unassembled_bytes(&self) -> usize {
+        self.reassembler.pending()
+    }
+
+    pub fn time_since_last_segment_received(&self) -> u64 {
+        self.time_since_last_segment_received
+    }
+
+    /// Starts an active open by sending SYN.
+    pub fn connect(&mut self) {
+        self.push();
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        let written = self.outbound.push(data);
+        self.push();
+        written
+    }
+
+    pub fn end_input_stream(&mut self) {
+        self.outbound.close();
+        self.push();
+    }
+
+    pub fn push(&mut self) {
+        if !self.active() {
+            return;
+        }
+        let (syn_sent, fin_sent) = (self.sender.syn_sent(), self.sender.fin_sent());
+        self.sender.push(&mut self.outbound);
+        if !syn_sent && self.sender.syn_sent() {
+            self.transition(TcpEvent::ActiveOpen);
+        }
+        if !fin_sent && self.sender.fin_sent() {
+            self.transition(TcpEvent::Close);
+        }
+    }
+
+    /// Sends no payload larger than `mss` from now on, because the path
+    /// reported it takes no more, see [`TcpSender::reduce_mss`].
+    pub fn reduce_mss(&mut self, mss: usize) {
+        if !self.active() {
+            return;
+        }
+        self.sender.reduce_mss(mss);
+        self.push();
+    }
+
+    /// Tears the connection down immediately, dropping anything in flight
+    /// and telling the peer with a RST.
+    pub fn abort(&mut self) {
+        self.sender.reset();
+        self.inbound.set_error();
+        self.outbound.set_error();
+        self.transition(TcpEvent::Abort);
+    }
+
+    pub fn receive(&mut self, message: TcpMessage) {
+        if !self.active() {
+            return;
+        }
+        self.time_since_last_segment_received = 0;
+
+        let TcpMessage { sender, receiver } = message;
+        if self.state == TcpState::Listen && !sender.syn {
+            return;
+        }
+        if sender.rst {
+            self.receive_rst(sender, receiver);
+            return;
+        }
+
+        let syn = sender.syn;
+        let window_scale = sender.window_scale;
+        let mss = sender.mss;
+        let timestamps = sender.timestamp.is_some();
+        let fin_received = self.inbound.closed();
+        let occupies_sequence = sender.sequence_length() > 0;
+        let keep_alive = self.is_keep_alive(&sender);
+        self.receiver
+            .receive(sender, &mut self.reassembler, &mut self.inbound);
+        // An old duplicate's acknowledgment and window are as stale as its
+        // data.
+        if self.receiver.take_paws_reject() {
+            return;
+        }
+        if syn {
+            self.sender.permit_sack(self.receiver.sack_permitted());
+        }
+        self.sender.receive_segment(receiver, !occupies_sequence);
+        // The window in the SYN itself is never scaled, only later ones.
+        if syn {
+            self.sender.permit_window_scale(window_scale);
+            self.sender.permit_timestamps(timestamps);
+            self.sender.permit_mss(mss);
+        }
+
+        if syn {
+            if self.state == TcpState::SynSent && self.sender.syn_acked() {
+                self.transition(TcpEvent::ReceiveSynAck);
+            } else {
+                self.transition(TcpEvent::ReceiveSyn);
+            }
+        }
+        if self.sender.syn_acked() {
+            self.transition(TcpEvent::ReceiveAckOfSyn);
+        }
+        if self.sender.fin_acked() {
+            self.transition(TcpEvent::ReceiveAckOfFin);
+        }
+        if !fin_received && self.inbound.closed() {
+            self.transition(TcpEvent::ReceiveFin);
+        }
+
+        self.push();
+        if keep_alive || (occupies_sequence && !self.receiver.delays_acks()) {
+            self.need_ack = true;
+        }
+    }
+
+    pub fn try_send(&mut self) -> Option<TcpMessage> {
+        if !self.active() && !self.sender.has_error() {
+            return None;
+        }
+        let segment = match self.sender.try_send() {
+            Some(segment) => segment,
+            None if (self.need_ack || self.receiver.ack_due()) && self.active() => {
+                self.sender.send_empty_message()
+            }
+            None => return None,
+        };
+        self.need_ack = false;
+        let mut receiver = self.receiver.send(&mut self.inbound);
+        if segment.syn {
+            // Window scaling only applies once both SYNs went through.
+            let window = self.inbound.avalible_capacity().min(u16::MAX as usize);
+            receiver.window_size = window as u16;
+        }
+        Some(TcpMessage::new(segment, receiver))
+    }
+
+    pub fn tick(&mut self, ms_since: u64) {
+        if !self.active() {
+            return;
+        }
+        self.time_since_last_segment_received += ms_since;
+        self.sender.tick(ms_since);
+        self.receiver.tick(ms_since);
+        if self.sender.has_error() {
+            self.inbound.set_error();
+            self.outbound.set_error();
+            self.transition(TcpEvent::RetransmissionLimit);
+            return;
+        }
+        if self.state == TcpState::TimeWait
+            && self.time_since_last_segment_received >= LINGER_FACTOR * self.rt_timeout
+        {
+            self.transition(TcpEvent::TimeWaitExpired);
+            return;
+        }
+        self.push();
+    }
+
+    fn receive_rst(&mut self, sender: TcpSenderMessage, receiver: TcpReceiverMessage) {
+        let accepted = match self.state {
+            TcpState::SynSent => receiver.ack_no == Some(self.sender.next_relative_seq()),
+            _ => {
+                self.receiver
+                    .receive(sender, &mut self.reassembler, &mut self.inbound);
+                if self.receiver.take_challenge_ack() {
+                    self.need_ack = true;
+                }
+                self.inbound.has_error()
+            }
+        };
+        if !accepted {
+            return;
+        }
+
+        self.inbound.set_error();
+        self.outbound.set_error();
+        self.sender.receive(TcpReceiverMessage::new().with_rst());
+        self.transition(TcpEvent::ReceiveRst);
+    }
+
+    fn transition(&mut self, event: TcpEvent) {
+        if let Some(next) = self.state.next(event) {
+            self.state = next;
+        }
+    }
+
+    fn is_keep_alive(&self, segment: &TcpSenderMessage) -> bool {
+        match self.receiver.ack_no(&self.inbound) {
+            Some(ack_no) => {
+                segment.sequence_length() == 0 && segment.seq_no.0 == ack_no.0.wrapping_sub(1)
+            }
+            None => false,
+        }
+    }
+}
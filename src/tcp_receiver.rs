@@ -1,18 +1,176 @@
+use std::ops::Range;
+
 use crate::{
     byte_stream::ByteStream,
     reassembler::Reassembler,
     sequence::{AbsoluteSequence, RelativeSequence},
-    TcpReceiverMessage, TcpSenderMessage,
+    tcp_segment::MAX_SACK_BLOCKS,
+    SackBlock, TcpReceiverMessage, TcpSenderMessage, MAX_PAYLOAD_SIZE,
 };
 
-#[derive(Default)]
+/// How long an acknowledgment may be delayed by default, well below the
+/// 500 ms RFC 1122 allows.
+pub const DEFAULT_ACK_TIMEOUT: u64 = 200;
+
+/// Full-sized segments that are acknowledged together at most (RFC 5681).
+pub const ACK_EVERY_SEGMENTS: u32 = 2;
+
+/// The largest window scale RFC 7323 allows, for windows up to a gigabyte.
+pub const MAX_WINDOW_SCALE: u8 = 14;
+
+/// The smallest shift that lets a window of `capacity` bytes be advertised.
+pub fn window_scale(capacity: usize) -> u8 {
+    let mut shift = 0;
+    while shift < MAX_WINDOW_SCALE && capacity >> shift > u16::MAX as usize {
+        shift += 1;
+    }
+    shift
+}
+
+#[derive(Default, Debug)]
 pub struct TcpReceiver {
     isn: Option<RelativeSequence>,
+    challenge_ack: bool,
+    paws_reject: bool,
+    sack: bool,
+    sack_permitted: bool,
+    /// Stream indices of the blocks to report, the most recent first.
+    sack_ranges: Vec<Range<usize>>,
+    window_scale: Option<u8>,
+    peer_window_scale: bool,
+    timestamps: bool,
+    peer_timestamps: bool,
+    /// TS.Recent of RFC 7323.
+    ts_recent: Option<u32>,
+    ack_timeout: Option<u64>,
+    ack_timer: Option<u64>,
+    unacked_segments: u32,
+    ack_now: bool,
+    mss: usize,
+    sws_avoidance: bool,
+    right_edge: Option<u64>,
 }
 
 impl TcpReceiver {
     pub fn new() -> Self {
-        TcpReceiver { isn: None }
+        TcpReceiver {
+            isn: None,
+            challenge_ack: false,
+            paws_reject: false,
+            sack: false,
+            sack_permitted: false,
+            sack_ranges: Vec::new(),
+            window_scale: None,
+            peer_window_scale: false,
+            timestamps: false,
+            peer_timestamps: false,
+            ts_recent: None,
+            ack_timeout: None,
+            ack_timer: None,
+            unacked_segments: 0,
+            ack_now: false,
+            mss: MAX_PAYLOAD_SIZE,
+            sws_avoidance: false,
+            right_edge: None,
+        }
+    }
+
+    /// Delays acknowledgments by up to `timeout` ms, so that one covers every
+    /// second full-sized segment. Out-of-order data, a filled gap, SYN and
+    /// FIN are still acknowledged at once. Without it, every segment that
+    /// occupies sequence space is acknowledged by the caller.
+    pub fn with_delayed_ack(mut self, timeout: Option<u64>) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// The MSS this end announced, which tells full-sized segments apart
+    /// for delayed acknowledgments and SWS avoidance.
+    pub fn with_mss(mut self, mss: usize) -> Self {
+        self.mss = mss;
+        self
+    }
+
+    /// Holds back small window updates, see [`TcpReceiver::send`].
+    pub fn with_sws_avoidance(mut self, enabled: bool) -> Self {
+        self.sws_avoidance = enabled;
+        self
+    }
+
+    pub fn delays_acks(&self) -> bool {
+        self.ack_timeout.is_some()
+    }
+
+    /// Whether an acknowledgment should be sent now, even without data to
+    /// carry it.
+    pub fn ack_due(&self) -> bool {
+        self.ack_now
+    }
+
+    /// Sends the delayed acknowledgment once it waited long enough.
+    pub fn tick(&mut self, ms_since: u64) {
+        let (Some(timer), Some(timeout)) = (self.ack_timer.as_mut(), self.ack_timeout) else {
+            return;
+        };
+        *timer += ms_since;
+        if *timer >= timeout {
+            self.ack_now = true;
+        }
+    }
+
+    /// Owes the peer an acknowledgment, right away or after a while.
+    fn schedule_ack(&mut self, immediate: bool) {
+        if !self.delays_acks() {
+            return;
+        }
+        self.ack_timer.get_or_insert(0);
+        self.ack_now |= immediate;
+    }
+
+    /// Echoes the peer's timestamps and rejects segments stamped before the
+    /// most recent one (PAWS), provided the peer's SYN carried timestamps.
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Whether both ends agreed on timestamps.
+    pub fn timestamps_enabled(&self) -> bool {
+        self.timestamps && self.peer_timestamps
+    }
+
+    /// The timestamp echoed in acknowledgments.
+    pub fn ts_recent(&self) -> Option<u32> {
+        self.ts_recent.filter(|_| self.timestamps_enabled())
+    }
+
+    /// Scales advertised windows down by `shift`, provided the peer's SYN
+    /// offered window scaling too. It should match what the SYN this end
+    /// sends offers.
+    pub fn with_window_scale(mut self, shift: Option<u8>) -> Self {
+        self.window_scale = shift.map(|shift| shift.min(MAX_WINDOW_SCALE));
+        self
+    }
+
+    /// The shift applied to advertised windows, zero unless both ends
+    /// agreed on window scaling.
+    pub fn window_shift(&self) -> u8 {
+        match self.window_scale {
+            Some(shift) if self.peer_window_scale => shift,
+            _ => 0,
+        }
+    }
+
+    /// Reports out-of-order data in SACK blocks, provided the peer's SYN
+    /// said it understands them.
+    pub fn with_sack(mut self, enabled: bool) -> Self {
+        self.sack = enabled;
+        self
+    }
+
+    /// Whether both ends agreed on SACK.
+    pub fn sack_permitted(&self) -> bool {
+        self.sack && self.sack_permitted
     }
 
     pub fn receive(
@@ -21,41 +179,194 @@ impl TcpReceiver {
         reassembler: &mut Reassembler,
         writer: &mut ByteStream,
     ) {
+        if writer.has_error() {
+            return;
+        }
+
         let isn = match self.isn {
             Some(isn) => isn,
             None => {
-                if !message.syn {
+                if !message.syn || message.rst {
                     return;
                 }
 
                 self.isn = Some(message.seq_no);
+                self.sack_permitted = message.sack_permitted;
+                self.peer_window_scale = message.window_scale.is_some();
+                self.peer_timestamps = message.timestamp.is_some();
                 message.seq_no
             }
         };
 
         let checkpoint = writer.pushed() as u64 + 1;
         let abs_seq = message.seq_no.unwrap(isn, AbsoluteSequence(checkpoint));
-        let stream_index = abs_seq.0 - 1 + message.syn as u64;
+        if message.rst {
+            self.receive_rst(abs_seq, writer);
+            return;
+        }
+        let occupies_sequence = message.sequence_length() > 0;
+        if (abs_seq.0 == 0 && !message.syn) || !self.check_timestamp(&message, abs_seq, checkpoint)
+        {
+            // Unacceptable segments are answered with an acknowledgment.
+            if occupies_sequence {
+                self.schedule_ack(true);
+            }
+            return;
+        }
+        let stream_index = abs_seq.0 + message.syn as u64 - 1;
 
-        dbg!(checkpoint, abs_seq, stream_index);
+        let length = message.payload.len();
+        let (syn, fin) = (message.syn, message.fin);
+        let pushed = writer.pushed();
         reassembler.push(stream_index as usize, &message.payload, message.fin, writer);
+        if self.sack_permitted() {
+            self.update_sack(stream_index as usize, length, reassembler);
+        }
+
+        if occupies_sequence {
+            // Anything but the next in-order data tells the peer something it
+            // should hear at once: a hole, a filled hole or a duplicate.
+            let in_order = stream_index as usize == pushed && writer.pushed() == pushed + length;
+            if length >= self.mss {
+                self.unacked_segments += 1;
+            }
+            let immediate = syn
+                || fin
+                || !in_order
+                || reassembler.pending() > 0
+                || self.unacked_segments >= ACK_EVERY_SEGMENTS;
+            self.schedule_ack(immediate);
+        }
     }
 
-    pub fn send(&mut self, inbound: &mut ByteStream) -> TcpReceiverMessage {
-        let window = if inbound.avalible_capacity() > u16::MAX as usize {
-            u16::MAX
-        } else {
-            inbound.avalible_capacity() as u16
+    /// Orders the blocks as RFC 2018 section 4 asks.
+    fn update_sack(&mut self, index: usize, length: usize, reassembler: &Reassembler) {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let latest = (length > 0)
+            .then(|| reassembler.buffered_range(index))
+            .flatten();
+        let previous = self
+            .sack_ranges
+            .iter()
+            .filter_map(|range| reassembler.buffered_range(range.start));
+        for range in latest
+            .into_iter()
+            .chain(previous)
+            .chain(reassembler.buffered())
+        {
+            if ranges.len() == MAX_SACK_BLOCKS {
+                break;
+            }
+            if !ranges.contains(&range) {
+                ranges.push(range);
+            }
+        }
+        self.sack_ranges = ranges;
+    }
+
+    /// PAWS as in RFC 7323 section 5.3.
+    fn check_timestamp(
+        &mut self,
+        message: &TcpSenderMessage,
+        abs_seq: AbsoluteSequence,
+        expected: u64,
+    ) -> bool {
+        let Some(value) = message.timestamp.filter(|_| self.timestamps_enabled()) else {
+            return true;
         };
-        match self.isn {
-            Some(isn) => {
-                let abs_ackno = inbound.pushed() + inbound.closed() as usize + 1;
+        if let Some(recent) = self.ts_recent {
+            if (value.wrapping_sub(recent) as i32) < 0 {
+                self.paws_reject = true;
+                return false;
+            }
+        }
+        if abs_seq.0 <= expected {
+            self.ts_recent = Some(value);
+        }
+        true
+    }
+
+    /// Validates a reset as in RFC 5961 section 3.2.
+    fn receive_rst(&mut self, abs_seq: AbsoluteSequence, writer: &mut ByteStream) {
+        let expected = (writer.pushed() + writer.closed() as usize + 1) as u64;
+        if abs_seq.0 == expected {
+            writer.set_error();
+        } else if abs_seq.0 > expected && abs_seq.0 < expected + writer.avalible_capacity() as u64 {
+            self.challenge_ack = true;
+        }
+    }
+
+    /// Whether an in-window but inexact RST arrived since the last call, in
+    /// which case the caller should answer with a plain ACK.
+    pub fn take_challenge_ack(&mut self) -> bool {
+        std::mem::take(&mut self.challenge_ack)
+    }
+
+    /// Whether PAWS dropped a segment since the last call, in which case the
+    /// caller should ignore its acknowledgment and window as well.
+    pub fn take_paws_reject(&mut self) -> bool {
+        std::mem::take(&mut self.paws_reject)
+    }
 
-                TcpReceiverMessage::new()
-                    .with_ack(isn.0 + abs_ackno as u32)
-                    .with_window_size(window)
+    /// With SWS avoidance (RFC 1122 section 4.2.3.3) the right edge only
+    /// moves by a full segment or half the buffer, whichever is smaller.
+    fn window(&mut self, inbound: &ByteStream) -> usize {
+        let available = inbound.avalible_capacity();
+        if !self.sws_avoidance {
+            return available;
+        }
+        let acked = inbound.pushed() as u64;
+        let window = match self.right_edge {
+            Some(edge) => {
+                let offered = edge.saturating_sub(acked) as usize;
+                let threshold = self.mss.min((available + inbound.len()) / 2);
+                if available >= offered + threshold {
+                    available
+                } else {
+                    offered.min(available)
+                }
             }
+            None => available,
+        };
+        self.right_edge = Some(acked + window as u64);
+        window
+    }
+
+    /// The next sequence number the receiver expects, once SYN has arrived.
+    pub fn ack_no(&self, inbound: &ByteStream) -> Option<RelativeSequence> {
+        let isn = self.isn?;
+        let abs_ackno = inbound.pushed() + inbound.closed() as usize + 1;
+        Some(AbsoluteSequence(abs_ackno as u64).wrap(isn))
+    }
+
+    /// The acknowledgment and window to send, which settles any
+    /// acknowledgment owed. With SWS avoidance the window only grows in
+    /// worthwhile steps.
+    pub fn send(&mut self, inbound: &mut ByteStream) -> TcpReceiverMessage {
+        self.ack_timer = None;
+        self.unacked_segments = 0;
+        self.ack_now = false;
+        let window = (self.window(inbound) >> self.window_shift()).min(u16::MAX as usize);
+        let window = window as u16;
+        let mut message = match self.ack_no(inbound) {
+            Some(ack_no) => TcpReceiverMessage::new()
+                .with_ack(ack_no.0)
+                .with_window_size(window),
             None => TcpReceiverMessage::new().with_window_size(window),
+        };
+        message.rst = inbound.has_error();
+        message.timestamp_echo = self.ts_recent();
+        if let Some(isn) = self.isn {
+            let wrap = |index: usize| AbsoluteSequence(index as u64 + 1).wrap(isn);
+            message.sack = self
+                .sack_ranges
+                .iter()
+                .map(|range| SackBlock {
+                    left: wrap(range.start),
+                    right: wrap(range.end),
+                })
+                .collect();
         }
+        message
     }
 }
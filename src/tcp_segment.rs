@@ -0,0 +1,393 @@
+use std::net::IpAddr;
+
+use crate::{
+    checksum::InternetChecksum,
+    ipv4::IPV4_HEADER_LENGTH,
+    ipv6::IPV6_HEADER_LENGTH,
+    parser::{ParseError, Parser},
+    sequence::RelativeSequence,
+    SackBlock, TcpMessage, TcpReceiverMessage, TcpSenderMessage,
+};
+
+pub const TCP_PROTOCOL: u8 = 6;
+pub const TCP_HEADER_LENGTH: usize = 20;
+pub const TCP_MAX_OPTIONS_LENGTH: usize = 40;
+
+/// The MSS that keeps segments within `mtu` bytes, IPv4 and TCP headers
+/// without options included.
+pub fn ipv4_mss_for_mtu(mtu: usize) -> usize {
+    mtu.saturating_sub(IPV4_HEADER_LENGTH + TCP_HEADER_LENGTH)
+}
+
+/// As [`ipv4_mss_for_mtu`], for the fixed IPv6 header without extension
+/// headers.
+pub fn ipv6_mss_for_mtu(mtu: usize) -> usize {
+    mtu.saturating_sub(IPV6_HEADER_LENGTH + TCP_HEADER_LENGTH)
+}
+
+const FLAG_FIN: u8 = 0x01;
+const FLAG_SYN: u8 = 0x02;
+const FLAG_RST: u8 = 0x04;
+const FLAG_PSH: u8 = 0x08;
+const FLAG_ACK: u8 = 0x10;
+const FLAG_URG: u8 = 0x20;
+
+const OPTION_END: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_MSS: u8 = 2;
+const OPTION_WINDOW_SCALE: u8 = 3;
+const OPTION_SACK_PERMITTED: u8 = 4;
+const OPTION_SACK: u8 = 5;
+const OPTION_TIMESTAMPS: u8 = 8;
+
+/// At most this many SACK blocks fit into the option space, one less
+/// alongside timestamps.
+pub const MAX_SACK_BLOCKS: usize = 4;
+
+/// Option space the timestamps option takes on every segment once agreed,
+/// padding included.
+pub const TIMESTAMPS_OPTION_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TcpOption {
+    Nop,
+    Mss(u16),
+    WindowScale(u8),
+    SackPermitted,
+    Sack(Vec<SackBlock>),
+    Timestamps { value: u32, echo: u32 },
+    Unknown { kind: u8, data: Vec<u8> },
+}
+
+impl TcpOption {
+    /// Encoded length, including kind and length bytes.
+    fn len(&self) -> usize {
+        match self {
+            TcpOption::Nop => 1,
+            TcpOption::Mss(_) => 4,
+            TcpOption::WindowScale(_) => 3,
+            TcpOption::SackPermitted => 2,
+            TcpOption::Sack(blocks) => blocks.len() * 8 + 2,
+            TcpOption::Timestamps { .. } => 10,
+            TcpOption::Unknown { data, .. } => data.len() + 2,
+        }
+    }
+
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            TcpOption::Nop => out.push(OPTION_NOP),
+            TcpOption::Mss(mss) => {
+                out.extend_from_slice(&[OPTION_MSS, 4]);
+                out.extend_from_slice(&mss.to_be_bytes());
+            }
+            TcpOption::WindowScale(shift) => {
+                out.extend_from_slice(&[OPTION_WINDOW_SCALE, 3, *shift])
+            }
+            TcpOption::SackPermitted => out.extend_from_slice(&[OPTION_SACK_PERMITTED, 2]),
+            TcpOption::Sack(blocks) => {
+                out.push(OPTION_SACK);
+                out.push(blocks.len() as u8 * 8 + 2);
+                for block in blocks {
+                    out.extend_from_slice(&block.left.0.to_be_bytes());
+                    out.extend_from_slice(&block.right.0.to_be_bytes());
+                }
+            }
+            TcpOption::Timestamps { value, echo } => {
+                out.extend_from_slice(&[OPTION_TIMESTAMPS, 10]);
+                out.extend_from_slice(&value.to_be_bytes());
+                out.extend_from_slice(&echo.to_be_bytes());
+            }
+            TcpOption::Unknown { kind, data } => {
+                out.push(*kind);
+                out.push(data.len() as u8 + 2);
+                out.extend_from_slice(data);
+            }
+        }
+    }
+
+    fn parse_all(mut parser: Parser) -> Result<Vec<TcpOption>, ParseError> {
+        let mut options = Vec::new();
+        while !parser.remaining().is_empty() {
+            let kind = parser.u8()?;
+            let option = match kind {
+                OPTION_END => break,
+                OPTION_NOP => TcpOption::Nop,
+                _ => {
+                    let length = parser.u8().map_err(|_| ParseError::BadOption)? as usize;
+                    if length < 2 {
+                        return Err(ParseError::BadOption);
+                    }
+                    let data = parser
+                        .bytes(length - 2)
+                        .map_err(|_| ParseError::BadOption)?;
+                    TcpOption::parse_one(kind, data)?
+                }
+            };
+            options.push(option);
+        }
+        Ok(options)
+    }
+
+    fn parse_one(kind: u8, data: &[u8]) -> Result<TcpOption, ParseError> {
+        let option = match kind {
+            OPTION_MSS if data.len() == 2 => TcpOption::Mss(u16::from_be_bytes([data[0], data[1]])),
+            OPTION_WINDOW_SCALE if data.len() == 1 => TcpOption::WindowScale(data[0]),
+            OPTION_SACK_PERMITTED if data.is_empty() => TcpOption::SackPermitted,
+            OPTION_SACK if !data.is_empty() && data.len().is_multiple_of(8) => {
+                let mut parser = Parser::new(data);
+                let mut blocks = Vec::new();
+                while !parser.remaining().is_empty() {
+                    blocks.push(SackBlock {
+                        left: RelativeSequence(parser.u32()?),
+                        right: RelativeSequence(parser.u32()?),
+                    });
+                }
+                TcpOption::Sack(blocks)
+            }
+            OPTION_TIMESTAMPS if data.len() == 8 => {
+                let mut parser = Parser::new(data);
+                TcpOption::Timestamps {
+                    value: parser.u32()?,
+                    echo: parser.u32()?,
+                }
+            }
+            OPTION_MSS
+            | OPTION_WINDOW_SCALE
+            | OPTION_SACK_PERMITTED
+            | OPTION_SACK
+            | OPTION_TIMESTAMPS => return Err(ParseError::BadOption),
+            _ => TcpOption::Unknown {
+                kind,
+                data: data.to_vec(),
+            },
+        };
+        Ok(option)
+    }
+}
+
+/// A TCP segment as it appears on the wire, see RFC 793 section 3.1.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcpSegment {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub seq_no: RelativeSequence,
+    pub ack_no: RelativeSequence,
+
+    pub urg: bool,
+    pub ack: bool,
+    pub psh: bool,
+    pub rst: bool,
+    pub syn: bool,
+    pub fin: bool,
+
+    pub window_size: u16,
+    pub checksum: u16,
+    pub urgent_pointer: u16,
+    pub options: Vec<TcpOption>,
+    pub payload: Vec<u8>,
+}
+
+impl TcpSegment {
+    pub fn from_message(message: &TcpMessage, src_port: u16, dst_port: u16) -> TcpSegment {
+        let TcpMessage { sender, receiver } = message;
+        let mut options = Vec::new();
+        if let Some(mss) = sender.mss.filter(|_| sender.syn) {
+            options.push(TcpOption::Mss(mss));
+        }
+        if let Some(shift) = sender.window_scale.filter(|_| sender.syn) {
+            options.push(TcpOption::WindowScale(shift));
+        }
+        if sender.syn && sender.sack_permitted {
+            options.push(TcpOption::SackPermitted);
+        }
+        if let Some(value) = sender.timestamp {
+            let echo = receiver.timestamp_echo.unwrap_or(0);
+            options.push(TcpOption::Timestamps { value, echo });
+        }
+        if !receiver.sack.is_empty() {
+            let mut used = Vec::new();
+            options
+                .iter()
+                .for_each(|option| option.serialize(&mut used));
+            let room = (TCP_MAX_OPTIONS_LENGTH - used.len() - 2) / 8;
+            let blocks = receiver.sack.len().min(MAX_SACK_BLOCKS).min(room);
+            options.push(TcpOption::Sack(receiver.sack[..blocks].to_vec()));
+        }
+        TcpSegment {
+            src_port,
+            dst_port,
+            seq_no: sender.seq_no,
+            ack_no: receiver.ack_no.unwrap_or_default(),
+            ack: receiver.ack_no.is_some(),
+            rst: sender.rst || receiver.rst,
+            syn: sender.syn,
+            fin: sender.fin,
+            window_size: receiver.window_size,
+            options,
+            payload: sender.payload.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_message(&self) -> TcpMessage {
+        let sender = TcpSenderMessage {
+            seq_no: self.seq_no,
+            syn: self.syn,
+            payload: self.payload.clone(),
+            fin: self.fin,
+            rst: self.rst,
+            sack_permitted: self.syn && self.options.contains(&TcpOption::SackPermitted),
+            window_scale: self.options.iter().find_map(|option| match option {
+                TcpOption::WindowScale(shift) if self.syn => Some(*shift),
+                _ => None,
+            }),
+            timestamp: self.timestamps().map(|(value, _)| value),
+            mss: self.options.iter().find_map(|option| match option {
+                TcpOption::Mss(mss) if self.syn => Some(*mss),
+                _ => None,
+            }),
+        };
+        let sack = self
+            .options
+            .iter()
+            .find_map(|option| match option {
+                TcpOption::Sack(blocks) if self.ack => Some(blocks.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let receiver = TcpReceiverMessage {
+            ack_no: self.ack.then_some(self.ack_no),
+            window_size: self.window_size,
+            rst: self.rst,
+            sack,
+            // An echo of zero is what a peer sends before it has anything to
+            // echo, never a timestamp (RFC 7323 section 4.3).
+            timestamp_echo: self
+                .timestamps()
+                .and_then(|(_, echo)| self.ack.then_some(echo))
+                .filter(|&echo| echo != 0),
+        };
+        TcpMessage::new(sender, receiver)
+    }
+
+    /// TSval and TSecr, if the segment carries timestamps.
+    fn timestamps(&self) -> Option<(u32, u32)> {
+        self.options.iter().find_map(|option| match option {
+            TcpOption::Timestamps { value, echo } => Some((*value, *echo)),
+            _ => None,
+        })
+    }
+
+    fn flags(&self) -> u8 {
+        [
+            (self.fin, FLAG_FIN),
+            (self.syn, FLAG_SYN),
+            (self.rst, FLAG_RST),
+            (self.psh, FLAG_PSH),
+            (self.ack, FLAG_ACK),
+            (self.urg, FLAG_URG),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+
+    /// Serializes the segment, filling in the checksum over the pseudo-header
+    /// built from `src` and `dst` instead of using [`TcpSegment::checksum`].
+    /// Options that no longer fit into the header are left out.
+    pub fn serialize(&self, src: IpAddr, dst: IpAddr) -> Vec<u8> {
+        let mut options = Vec::new();
+        for option in &self.options {
+            if options.len() + option.len() <= TCP_MAX_OPTIONS_LENGTH {
+                option.serialize(&mut options);
+            }
+        }
+        options.resize(options.len().next_multiple_of(4), OPTION_END);
+        let data_offset = (TCP_HEADER_LENGTH + options.len()) / 4;
+
+        let mut out = Vec::with_capacity(data_offset * 4 + self.payload.len());
+        out.extend_from_slice(&self.src_port.to_be_bytes());
+        out.extend_from_slice(&self.dst_port.to_be_bytes());
+        out.extend_from_slice(&self.seq_no.0.to_be_bytes());
+        out.extend_from_slice(&self.ack_no.0.to_be_bytes());
+        out.push((data_offset as u8) << 4);
+        out.push(self.flags());
+        out.extend_from_slice(&self.window_size.to_be_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&self.urgent_pointer.to_be_bytes());
+        out.extend_from_slice(&options);
+        out.extend_from_slice(&self.payload);
+
+        let checksum = tcp_checksum(src, dst, &out);
+        out[16..18].copy_from_slice(&checksum.to_be_bytes());
+        out
+    }
+
+    /// Parses a segment and verifies its checksum against the pseudo-header
+    /// built from `src` and `dst`.
+    pub fn parse(data: &[u8], src: IpAddr, dst: IpAddr) -> Result<TcpSegment, ParseError> {
+        let mut parser = Parser::new(data);
+        let src_port = parser.u16()?;
+        let dst_port = parser.u16()?;
+        let seq_no = RelativeSequence(parser.u32()?);
+        let ack_no = RelativeSequence(parser.u32()?);
+        let data_offset = (parser.u8()? >> 4) as usize * 4;
+        let flags = parser.u8()?;
+        let window_size = parser.u16()?;
+        let checksum = parser.u16()?;
+        let urgent_pointer = parser.u16()?;
+
+        if data_offset < TCP_HEADER_LENGTH {
+            return Err(ParseError::HeaderTooShort);
+        }
+        let options = parser.bytes(data_offset - TCP_HEADER_LENGTH)?;
+        if tcp_checksum(src, dst, data) != 0 {
+            return Err(ParseError::BadChecksum);
+        }
+
+        Ok(TcpSegment {
+            src_port,
+            dst_port,
+            seq_no,
+            ack_no,
+            urg: flags & FLAG_URG != 0,
+            ack: flags & FLAG_ACK != 0,
+            psh: flags & FLAG_PSH != 0,
+            rst: flags & FLAG_RST != 0,
+            syn: flags & FLAG_SYN != 0,
+            fin: flags & FLAG_FIN != 0,
+            window_size,
+            checksum,
+            urgent_pointer,
+            options: TcpOption::parse_all(Parser::new(options))?,
+            payload: parser.remaining().to_vec(),
+        })
+    }
+}
+
+/// Checksum of `segment` together with the IPv4 (RFC 793) or IPv6 (RFC 8200
+/// section 8.1) pseudo-header. Mixed families are summed as IPv6, with the
+/// IPv4 address in its mapped form.
+pub fn tcp_checksum(src: IpAddr, dst: IpAddr, segment: &[u8]) -> u16 {
+    let mut checksum = InternetChecksum::new();
+    match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            checksum.add(&src.octets());
+            checksum.add(&dst.octets());
+            checksum.add(&[0, TCP_PROTOCOL]);
+            checksum.add(&(segment.len() as u16).to_be_bytes());
+        }
+        _ => {
+            let to_v6 = |addr: IpAddr| match addr {
+                IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+                IpAddr::V6(addr) => addr,
+            };
+            checksum.add(&to_v6(src).octets());
+            checksum.add(&to_v6(dst).octets());
+            checksum.add(&(segment.len() as u32).to_be_bytes());
+            checksum.add(&[0, 0, 0, TCP_PROTOCOL]);
+        }
+    }
+    checksum.add(segment);
+    checksum.value()
+}
@@ -1,29 +1,153 @@
-
+use std::{cmp::Ordering, collections::VecDeque, mem};
 
 use crate::{
     byte_stream::ByteStream,
+    congestion::{AckEvent, CongestionControl, RateSample, Unlimited},
+    plpmtud::MtuSearch,
+    rtt::RttEstimator,
     sequence::{AbsoluteSequence, RelativeSequence},
-    TcpReceiverMessage, TcpSenderMessage, MAX_PAYLOAD_SIZE,
+    tcp_receiver::MAX_WINDOW_SCALE,
+    tcp_segment::TIMESTAMPS_OPTION_LENGTH,
+    SackBlock, TcpReceiverMessage, TcpSenderMessage, MAX_PAYLOAD_SIZE, MAX_RETRY_ATTEMPT,
 };
 
+/// The MSS assumed for a peer whose SYN carried no MSS option, see RFC 9293
+/// section 3.7.1.
+pub const DEFAULT_PEER_MSS: usize = 536;
+
+/// Duplicate acknowledgments that signal a lost segment, see RFC 5681.
+pub const DUPLICATE_ACK_THRESHOLD: u32 = 3;
+
+/// A segment waiting to be acknowledged.
+#[derive(Debug)]
+struct Outstanding {
+    abs_seq: AbsoluteSequence,
+    message: TcpSenderMessage,
+    sent_at: u64,
+    /// Whether the segment left through `try_send` yet, pacing may hold it.
+    released: bool,
+    retransmitted: bool,
+    sacked: bool,
+    lost: bool,
+    delivered: u64,
+    delivered_time: u64,
+    probe: bool,
+}
+
+impl Outstanding {
+    /// The "pipe" of RFC 6675.
+    fn in_pipe(&self) -> bool {
+        !self.sacked && (!self.lost || self.retransmitted)
+    }
+
+    fn split(self, mss: usize) -> Vec<Outstanding> {
+        let mut abs_seq = self.abs_seq;
+        split(self.message, mss)
+            .into_iter()
+            .map(|message| {
+                let part = Outstanding {
+                    abs_seq,
+                    message,
+                    sent_at: self.sent_at,
+                    released: self.released,
+                    retransmitted: self.retransmitted,
+                    sacked: self.sacked,
+                    lost: self.lost,
+                    delivered: self.delivered,
+                    delivered_time: self.delivered_time,
+                    probe: false,
+                };
+                abs_seq += part.message.sequence_length() as u64;
+                part
+            })
+            .collect()
+    }
+}
+
+/// The SYN and its options go with the first part, the FIN with the last.
+fn split(message: TcpSenderMessage, mss: usize) -> Vec<TcpSenderMessage> {
+    if message.payload.len() <= mss {
+        return vec![message];
+    }
+    let last = (message.payload.len() - 1) / mss;
+    let mut seq_no = message.seq_no.0;
+    message
+        .payload
+        .chunks(mss)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut part = match index {
+                0 => TcpSenderMessage {
+                    payload: Vec::new(),
+                    ..message.clone()
+                },
+                _ => TcpSenderMessage::new().with_seq(seq_no),
+            };
+            part.payload = chunk.to_vec();
+            part.fin = message.fin && index == last;
+            seq_no = seq_no.wrapping_add(part.sequence_length() as u32);
+            part
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct Persist {
+    timer: u64,
+    timeout: u64,
+    /// Whether a new byte may be sent as a window probe.
+    probe_due: bool,
+    unanswered: u64,
+}
+
 #[derive(Debug)]
 pub struct TcpSender {
     isn: RelativeSequence,
     initial_rto: u64,
     rto_timeout: u64,
     timer: u64,
+    clock: u64,
+
+    rtt: RttEstimator,
+    adaptive_rto: bool,
+    congestion: Box<dyn CongestionControl>,
+    delivered: u64,
+    delivered_time: u64,
+    next_release: f64,
+    nagle: bool,
+    sws_avoidance: bool,
 
     syn: bool,
     fin: bool,
 
     next_abs_seq: AbsoluteSequence,
 
-    windows: u16,
+    /// Already scaled.
+    windows: u64,
+    max_window: u64,
+    window_scale: Option<u8>,
+    peer_window_shift: u8,
+    mss: Option<usize>,
+    peer_mss: Option<usize>,
+    path_mss: Option<usize>,
+    mtu_search: Option<MtuSearch>,
     outstanding_seq: AbsoluteSequence,
-    outstanding_segment: Vec<(AbsoluteSequence, TcpSenderMessage)>,
-    segment_out: Vec<TcpSenderMessage>,
+    outstanding_segment: Vec<Outstanding>,
+    segment_out: VecDeque<TcpSenderMessage>,
 
     retries_times: u64,
+    persist: Option<Persist>,
+    window_probes: u64,
+    duplicate_acks: u32,
+    fast_retransmits: u64,
+
+    sack: bool,
+    sack_permitted: bool,
+    timestamps: bool,
+    peer_timestamps: bool,
+    recovery_point: Option<AbsoluteSequence>,
+
+    error: bool,
 }
 
 impl TcpSender {
@@ -32,16 +156,359 @@ impl TcpSender {
         Self {
             isn,
             initial_rto,
-            rto_timeout: 0,
+            rto_timeout: initial_rto,
             timer: 0,
+            clock: 0,
+            rtt: RttEstimator::default(),
+            adaptive_rto: false,
+            congestion: Box::new(Unlimited),
+            delivered: 0,
+            delivered_time: 0,
+            next_release: 0.0,
+            nagle: false,
+            sws_avoidance: false,
             syn: false,
             fin: false,
             next_abs_seq: AbsoluteSequence(0),
             windows: 1,
+            max_window: 0,
+            window_scale: None,
+            peer_window_shift: 0,
+            mss: None,
+            peer_mss: None,
+            path_mss: None,
+            mtu_search: None,
             outstanding_seq: AbsoluteSequence(0),
             outstanding_segment: Vec::new(),
-            segment_out: Vec::new(),
+            segment_out: VecDeque::new(),
             retries_times: 0,
+            persist: None,
+            window_probes: 0,
+            duplicate_acks: 0,
+            fast_retransmits: 0,
+            sack: false,
+            sack_permitted: false,
+            timestamps: false,
+            peer_timestamps: false,
+            recovery_point: None,
+            error: false,
+        }
+    }
+
+    /// Derives the RTO from measured round-trip times, clamped to
+    /// `min_rto..=max_rto`, instead of always starting from the initial RTO.
+    /// The initial RTO still applies until the first measurement.
+    pub fn with_adaptive_rto(mut self, min_rto: u64, max_rto: u64) -> Self {
+        self.rtt = RttEstimator::new(min_rto, max_rto);
+        self.adaptive_rto = true;
+        self
+    }
+
+    /// Limits what is in flight with `congestion` on top of the receiver's
+    /// window.
+    pub fn with_congestion_control(mut self, congestion: Box<dyn CongestionControl>) -> Self {
+        self.congestion = congestion;
+        self.sync_mss();
+        self
+    }
+
+    /// Coalesces small writes with Nagle's algorithm: while data is
+    /// unacknowledged, only full segments leave.
+    pub fn with_nagle(mut self) -> Self {
+        self.nagle = true;
+        self
+    }
+
+    /// Avoids the silly window syndrome: while data is unacknowledged, a
+    /// segment the peer's window cuts short waits until the window is at
+    /// least half the largest one offered, or a full segment.
+    pub fn with_sws_avoidance(mut self) -> Self {
+        self.sws_avoidance = true;
+        self
+    }
+
+    pub fn sws_avoidance(&self) -> bool {
+        self.sws_avoidance
+    }
+
+    /// Offers SACK in the SYN, see [`TcpSender::permit_sack`].
+    pub fn with_sack(mut self) -> Self {
+        self.sack = true;
+        self
+    }
+
+    /// Records whether the peer's SYN permitted SACK. If both ends did, SACK
+    /// blocks from the peer feed a scoreboard that steers retransmissions
+    /// during loss recovery. A SYN not sent yet only offers it if the peer
+    /// did (RFC 2018 section 2).
+    pub fn permit_sack(&mut self, permitted: bool) {
+        self.sack_permitted = permitted;
+        if !permitted && !self.syn {
+            self.sack = false;
+        }
+    }
+
+    /// Offers window scaling in the SYN, announcing that windows this end
+    /// advertises are shifted by `shift`.
+    pub fn with_window_scale(mut self, shift: u8) -> Self {
+        self.window_scale = Some(shift.min(MAX_WINDOW_SCALE));
+        self
+    }
+
+    pub fn window_scale(&self) -> Option<u8> {
+        self.window_scale
+    }
+
+    /// Records the window scale the peer's SYN offered, if any. Windows in
+    /// later acknowledgments are shifted by it if this sender offered scaling
+    /// too, and a SYN not sent yet only offers it if the peer did (RFC 7323
+    /// section 2.2).
+    pub fn permit_window_scale(&mut self, shift: Option<u8>) {
+        match shift {
+            Some(shift) if self.window_scale.is_some() => {
+                self.peer_window_shift = shift.min(MAX_WINDOW_SCALE)
+            }
+            Some(_) => {}
+            None if !self.syn => self.window_scale = None,
+            None => {}
+        }
+    }
+
+    /// Announces `mss` in the SYN as the largest payload this end takes, and
+    /// sends no larger segments either.
+    pub fn with_mss(mut self, mss: usize) -> Self {
+        self.mss = Some(mss.clamp(1, u16::MAX as usize));
+        self.sync_mss();
+        self
+    }
+
+    /// The largest payload this end takes.
+    pub fn advertised_mss(&self) -> usize {
+        self.mss.unwrap_or(MAX_PAYLOAD_SIZE)
+    }
+
+    /// Records the MSS the peer's SYN announced, [`DEFAULT_PEER_MSS`] if it
+    /// announced none.
+    pub fn permit_mss(&mut self, mss: Option<u16>) {
+        let mss = mss.map_or(DEFAULT_PEER_MSS, usize::from);
+        self.peer_mss = Some(mss.max(1));
+        self.sync_mss();
+    }
+
+    fn sync_mss(&mut self) {
+        let mss = self.mss() as u64;
+        self.congestion.set_mss(mss);
+    }
+
+    /// The largest payload sent, within both ends' MSS and what is known of
+    /// the path.
+    pub fn mss(&self) -> usize {
+        let ceiling = self.mss_ceiling();
+        match &self.mtu_search {
+            Some(search) => search.mss(ceiling),
+            None => ceiling,
+        }
+    }
+
+    fn mss_ceiling(&self) -> usize {
+        let peer_mss = self.peer_mss.unwrap_or(usize::MAX);
+        // Options on every segment leave less room for the payload.
+        let overhead = match self.timestamps_enabled() {
+            true => TIMESTAMPS_OPTION_LENGTH,
+            false => 0,
+        };
+        let path_mss = self
+            .path_mss
+            .map_or(usize::MAX, |mss| mss.saturating_sub(overhead).max(1));
+        self.advertised_mss().min(peer_mss).min(path_mss)
+    }
+
+    /// Lowers the MSS to what the path takes, as an ICMP "fragmentation
+    /// needed" message reported it (RFC 1191). Outstanding segments are split
+    /// to fit, and those already sent are resent right away since the path
+    /// dropped them. Nothing was lost to congestion, so the congestion
+    /// window stays as it is.
+    pub fn reduce_mss(&mut self, mss: usize) {
+        if self.error || self.path_mss.is_some_and(|path_mss| path_mss <= mss) {
+            return;
+        }
+        self.path_mss = Some(mss.max(1));
+        let mss = self.mss();
+
+        let mut dropped = Vec::new();
+        let mut resend = Vec::new();
+        let mut outstanding = Vec::new();
+        for segment in mem::take(&mut self.outstanding_segment) {
+            let oversized = segment.released && segment.message.payload.len() > mss;
+            if oversized {
+                dropped.push(segment.message.seq_no);
+            }
+            for mut part in segment.split(mss) {
+                if oversized {
+                    part.retransmitted = true;
+                    resend.push(part.message.clone());
+                }
+                outstanding.push(part);
+            }
+        }
+        self.outstanding_segment = outstanding;
+
+        let queued = mem::take(&mut self.segment_out)
+            .into_iter()
+            .filter(|message| !dropped.contains(&message.seq_no))
+            .flat_map(|message| split(message, mss));
+        self.segment_out = resend.into_iter().chain(queued).collect();
+        self.sync_mss();
+    }
+
+    /// Searches for the largest segment the path carries with probes, as
+    /// packetization layer path MTU discovery does (RFC 4821). Until a probe
+    /// gets through, segments are no larger than
+    /// [`BASE_MSS`](crate::plpmtud::BASE_MSS).
+    pub fn with_mtu_probing(mut self) -> Self {
+        self.mtu_search = Some(MtuSearch::default());
+        self.sync_mss();
+        self
+    }
+
+    fn next_probe(&self) -> Option<usize> {
+        let search = self.mtu_search.as_ref()?;
+        let in_flight = self.outstanding_segment.iter().any(|segment| segment.probe);
+        if in_flight || self.persist.is_some() {
+            return None;
+        }
+        search.next_probe(self.mss_ceiling())
+    }
+
+    fn probe_acked(&mut self, size: usize) {
+        if let Some(search) = self.mtu_search.as_mut() {
+            search.probe_acked(size);
+        }
+        self.sync_mss();
+    }
+
+    fn lost_probe(&self) -> Option<usize> {
+        self.outstanding_segment
+            .iter()
+            .enumerate()
+            .position(|(index, segment)| {
+                let first_lost = index == 0 && self.duplicate_acks >= DUPLICATE_ACK_THRESHOLD;
+                segment.probe && segment.released && (segment.lost || first_lost)
+            })
+    }
+
+    /// A lost probe is no congestion signal, so there is no backoff either.
+    fn lose_probe(&mut self, index: usize) {
+        let segment = self.outstanding_segment.remove(index);
+        if let Some(search) = self.mtu_search.as_mut() {
+            search.probe_lost(segment.message.payload.len());
+        }
+        let mut parts = segment.split(self.mss());
+        for part in &mut parts {
+            part.retransmitted = true;
+            part.lost = false;
+        }
+        for part in parts.iter().rev() {
+            self.segment_out.push_front(part.message.clone());
+        }
+        self.outstanding_segment.splice(index..index, parts);
+        self.timer = 0;
+        self.duplicate_acks = 0;
+        self.sync_mss();
+    }
+
+    /// The peer's receive window in bytes.
+    pub fn window(&self) -> u64 {
+        self.windows
+    }
+
+    /// Offers timestamps in the SYN, see [`TcpSender::permit_timestamps`].
+    pub fn with_timestamps(mut self) -> Self {
+        self.timestamps = true;
+        self
+    }
+
+    /// Records whether the peer's SYN carried timestamps. If both ends did,
+    /// every segment is stamped and every echo that acknowledges new data is
+    /// an RTT sample, retransmissions included. A SYN not sent yet only
+    /// offers them if the peer did (RFC 7323 section 3.2).
+    pub fn permit_timestamps(&mut self, permitted: bool) {
+        self.peer_timestamps = permitted;
+        if !permitted && !self.syn {
+            self.timestamps = false;
+        }
+    }
+
+    pub fn timestamps_offered(&self) -> bool {
+        self.timestamps
+    }
+
+    /// Whether both ends agreed on timestamps.
+    pub fn timestamps_enabled(&self) -> bool {
+        self.timestamps && self.peer_timestamps
+    }
+
+    fn timestamp(&self, syn: bool) -> Option<u32> {
+        let stamped = self.timestamps_enabled() || (syn && self.timestamps);
+        stamped.then_some(self.clock as u32)
+    }
+
+    pub fn sack_offered(&self) -> bool {
+        self.sack
+    }
+
+    /// Whether both ends agreed on SACK.
+    pub fn sack_permitted(&self) -> bool {
+        self.sack && self.sack_permitted
+    }
+
+    /// Sequence space reported by SACK blocks and not yet cumulatively
+    /// acknowledged.
+    pub fn sacked_bytes(&self) -> u64 {
+        self.outstanding_segment
+            .iter()
+            .filter(|segment| segment.sacked)
+            .map(|segment| segment.message.sequence_length() as u64)
+            .sum()
+    }
+
+    pub fn congestion_control(&self) -> &dyn CongestionControl {
+        self.congestion.as_ref()
+    }
+
+    pub fn srtt(&self) -> Option<u64> {
+        self.rtt.srtt()
+    }
+
+    pub fn rttvar(&self) -> Option<u64> {
+        self.rtt.rttvar()
+    }
+
+    /// The retransmission timeout currently in effect, backoff included.
+    pub fn rto(&self) -> u64 {
+        self.rto_timeout
+    }
+
+    fn released_in_flight(&self) -> u64 {
+        self.outstanding_segment
+            .iter()
+            .filter(|segment| segment.released && segment.in_pipe())
+            .map(|segment| segment.message.sequence_length() as u64)
+            .sum()
+    }
+
+    fn out_of_pipe(&self) -> u64 {
+        self.outstanding_segment
+            .iter()
+            .filter(|segment| !segment.in_pipe())
+            .map(|segment| segment.message.sequence_length() as u64)
+            .sum()
+    }
+
+    fn current_rto(&self) -> u64 {
+        match self.rtt.rto() {
+            Some(rto) if self.adaptive_rto => rto,
+            _ => self.initial_rto,
         }
     }
 
@@ -57,20 +524,140 @@ impl TcpSender {
         self.outstanding_seq
     }
 
+    pub fn syn_sent(&self) -> bool {
+        self.syn
+    }
+
+    pub fn syn_acked(&self) -> bool {
+        self.syn && self.next_abs_seq.0 > self.outstanding_seq.0
+    }
+
+    pub fn fin_sent(&self) -> bool {
+        self.fin
+    }
+
+    pub fn fin_acked(&self) -> bool {
+        self.fin && self.outstanding_seq.0 == 0
+    }
+
     pub fn consecutive_retransmissions(&self) -> u64 {
         self.retries_times
     }
 
+    /// The interval until the next window probe, while the peer advertises
+    /// a zero window.
+    pub fn persist_timeout(&self) -> Option<u64> {
+        self.persist.as_ref().map(|persist| persist.timeout)
+    }
+
+    /// Window probes sent so far.
+    pub fn window_probes(&self) -> u64 {
+        self.window_probes
+    }
+
+    /// Duplicate acknowledgments received since data was last acknowledged.
+    pub fn duplicate_acks(&self) -> u32 {
+        self.duplicate_acks
+    }
+
+    /// Segments retransmitted without waiting for the RTO, either on duplicate
+    /// acknowledgments or on a partial acknowledgment during recovery.
+    pub fn fast_retransmits(&self) -> u64 {
+        self.fast_retransmits
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.error
+    }
+
+    /// Abandons everything in flight and queues a RST for the peer.
+    pub fn reset(&mut self) {
+        self.abandon();
+        let message = self.send_empty_message();
+        self.segment_out.push_back(message);
+    }
+
+    fn abandon(&mut self) {
+        self.error = true;
+        self.outstanding_segment.clear();
+        self.outstanding_seq = AbsoluteSequence(0);
+        self.segment_out.clear();
+    }
+
+    /// Releases the next segment, unless the pacing rate asks to wait for
+    /// more time to pass.
     pub fn try_send(&mut self) -> Option<TcpSenderMessage> {
         if !self.syn {
             return None;
         }
 
-        self.segment_out.pop()
+        let front = self.segment_out.front()?;
+        let length = front.sequence_length();
+        // Retransmissions repair the pipe rather than add to it, so they
+        // leave right away.
+        let retransmission = self
+            .outstanding_index(front)
+            .is_some_and(|index| self.outstanding_segment[index].retransmitted);
+        let pacing_rate = self.congestion.pacing_rate().filter(|rate| *rate > 0.0);
+        if let Some(rate) = pacing_rate.filter(|_| length > 0 && !retransmission && !self.error) {
+            let now = self.clock as f64;
+            if now < self.next_release {
+                return None;
+            }
+            self.next_release = self.next_release.max(now) + length as f64 / rate;
+        }
+
+        let mut message = self.segment_out.pop_front()?;
+        self.stamp(&message);
+        message.timestamp = self.timestamp(message.syn);
+        Some(message)
+    }
+
+    fn outstanding_index(&self, message: &TcpSenderMessage) -> Option<usize> {
+        if message.sequence_length() == 0 {
+            return None;
+        }
+        let abs_seq = message.seq_no.unwrap(self.isn, self.next_abs_seq);
+        self.outstanding_segment
+            .iter()
+            .position(|segment| segment.abs_seq == abs_seq)
+    }
+
+    fn stamp(&mut self, message: &TcpSenderMessage) {
+        let Some(index) = self.outstanding_index(message) else {
+            return;
+        };
+        // Leaving an empty pipe, the delivery rate interval starts now and
+        // not at whatever acknowledgment came last.
+        if index == 0 && !self.outstanding_segment[0].retransmitted {
+            self.delivered_time = self.clock;
+        }
+        let segment = &mut self.outstanding_segment[index];
+        segment.released = true;
+        segment.sent_at = self.clock;
+        segment.delivered = self.delivered;
+        segment.delivered_time = self.delivered_time;
     }
 
     pub fn push(&mut self, reader: &mut ByteStream) {
-        let window_size = self.windows.max(1) as usize;
+        if reader.has_error() && !self.error {
+            self.reset();
+        }
+        if self.error {
+            reader.set_error();
+            return;
+        }
+
+        let cwnd = self.congestion.cwnd().saturating_add(self.out_of_pipe());
+        // A zero window only lets a one byte probe through, when the persist
+        // timer says so.
+        let window = match &self.persist {
+            Some(persist) => persist.probe_due as u64,
+            None => self.windows.max(1),
+        };
+        let window_size = window.min(cwnd) as usize;
+        let mss = self.mss();
+        let mut probe = self.next_probe();
 
         while window_size > self.outstanding_seq.0 as usize {
             let outstanding_seq = self.outstanding_seq.0 as usize;
@@ -79,10 +666,22 @@ impl TcpSender {
             if !self.syn {
                 self.syn = true;
                 message.syn = true;
+                message.sack_permitted = self.sack;
+                message.window_scale = self.window_scale;
+                message.mss = self.mss.map(|mss| mss as u16);
             }
 
-            let payload_size = MAX_PAYLOAD_SIZE
-                .min(window_size - outstanding_seq - message.syn as usize);
+            let room = window_size - outstanding_seq - message.syn as usize;
+            // A probe only goes out when there is data enough to fill it.
+            let probe_size =
+                probe.filter(|&size| !message.syn && size <= room && reader.len() >= size);
+            if probe_size.is_some() {
+                probe = None;
+            }
+            let payload_size = probe_size.unwrap_or(mss.min(room));
+            if self.holds_small_segment(reader, payload_size) {
+                break;
+            }
             let payload = reader.read(payload_size);
             let size = payload.len() + outstanding_seq + message.syn as usize;
 
@@ -97,16 +696,30 @@ impl TcpSender {
             }
 
             if self.outstanding_segment.is_empty() {
-                self.rto_timeout = self.initial_rto;
+                self.rto_timeout = self.current_rto();
                 self.timer = 0
             }
 
             self.outstanding_seq += message.sequence_length() as u64;
-            self.outstanding_segment
-                .push((self.next_abs_seq(), message.clone()));
+            self.outstanding_segment.push(Outstanding {
+                abs_seq: self.next_abs_seq(),
+                message: message.clone(),
+                sent_at: self.clock,
+                released: false,
+                retransmitted: false,
+                sacked: false,
+                lost: false,
+                delivered: self.delivered,
+                delivered_time: self.delivered_time,
+                probe: probe_size.is_some(),
+            });
             self.next_abs_seq += message.sequence_length() as u64;
             let fin = message.fin;
-            self.segment_out.push(message);
+            self.segment_out.push_back(message);
+            if let Some(persist) = self.persist.as_mut() {
+                persist.probe_due = false;
+                self.window_probes += 1;
+            }
 
             if fin {
                 break;
@@ -114,57 +727,332 @@ impl TcpSender {
         }
     }
 
+    /// Nagle's algorithm (RFC 896) and SWS avoidance (RFC 1122 section
+    /// 4.2.3.4).
+    fn holds_small_segment(&self, reader: &ByteStream, payload_size: usize) -> bool {
+        let ready = reader.len().min(payload_size);
+        if !self.syn || self.outstanding_seq.0 == 0 || ready >= self.mss() {
+            return false;
+        }
+        let last = reader.closed() && reader.len() <= payload_size;
+        // Only the peer's window counts here, not the congestion window.
+        let room = self.windows.saturating_sub(self.outstanding_seq.0);
+        let window_limited = (reader.len() as u64) > room && room < self.max_window / 2;
+        (self.nagle && !last) || (self.sws_avoidance && window_limited)
+    }
+
+    fn fast_retransmit(&mut self) {
+        let Some(segment) = self.outstanding_segment.first_mut() else {
+            return;
+        };
+        if !segment.released {
+            return;
+        }
+        segment.retransmitted = true;
+        let message = segment.message.clone();
+        self.fast_retransmits += 1;
+        self.segment_out.push_front(message);
+    }
+
+    fn duplicate_ack(&mut self, window_size: u64) {
+        if self.outstanding_segment.is_empty() || window_size == 0 || window_size != self.windows {
+            self.duplicate_acks = 0;
+            return;
+        }
+        self.duplicate_acks += 1;
+        if let Some(index) = self.lost_probe() {
+            self.lose_probe(index);
+            return;
+        }
+        if self.sack_permitted() {
+            self.sack_recovery();
+            return;
+        }
+        match self.duplicate_acks.cmp(&DUPLICATE_ACK_THRESHOLD) {
+            Ordering::Less => {}
+            Ordering::Equal => {
+                self.congestion.on_loss(self.released_in_flight());
+                self.fast_retransmit();
+            }
+            Ordering::Greater => self.congestion.on_duplicate_ack(),
+        }
+    }
+
+    fn update_scoreboard(&mut self, blocks: &[SackBlock]) {
+        if !self.sack_permitted() || blocks.is_empty() {
+            return;
+        }
+        for block in blocks {
+            let left = block.left.unwrap(self.isn, self.next_abs_seq);
+            let right = block.right.unwrap(self.isn, self.next_abs_seq);
+            for segment in &mut self.outstanding_segment {
+                let end = segment.abs_seq.0 + segment.message.sequence_length() as u64;
+                if segment.abs_seq >= left && end <= right.0 {
+                    segment.sacked = true;
+                }
+            }
+        }
+        let mut sacked_after = 0;
+        for segment in self.outstanding_segment.iter_mut().rev() {
+            if segment.sacked {
+                sacked_after += 1;
+            } else if sacked_after >= DUPLICATE_ACK_THRESHOLD {
+                segment.lost = true;
+            }
+        }
+        // A SACKed probe got through just as well as an acknowledged one.
+        let probe = self
+            .outstanding_segment
+            .iter_mut()
+            .find(|segment| segment.probe && segment.sacked);
+        if let Some(segment) = probe {
+            segment.probe = false;
+            let size = segment.message.payload.len();
+            self.probe_acked(size);
+        }
+    }
+
+    /// Loss recovery as in RFC 6675.
+    fn sack_recovery(&mut self) {
+        if self.recovery_point.is_none() {
+            let first_lost = self
+                .outstanding_segment
+                .first()
+                .is_some_and(|segment| segment.lost);
+            if self.duplicate_acks < DUPLICATE_ACK_THRESHOLD && !first_lost {
+                return;
+            }
+            self.recovery_point = Some(self.next_abs_seq);
+            self.congestion.on_loss(self.released_in_flight());
+            self.fast_retransmit();
+        }
+        self.retransmit_lost();
+    }
+
+    fn retransmit_lost(&mut self) {
+        let cwnd = self.congestion.cwnd();
+        let mut pipe = self.outstanding_seq.0 - self.out_of_pipe();
+        let mut resend = Vec::new();
+        for segment in &mut self.outstanding_segment {
+            if !segment.lost || segment.sacked || segment.retransmitted || !segment.released {
+                continue;
+            }
+            let length = segment.message.sequence_length() as u64;
+            if pipe + length > cwnd {
+                break;
+            }
+            pipe += length;
+            segment.retransmitted = true;
+            resend.push(segment.message.clone());
+        }
+        self.fast_retransmits += resend.len() as u64;
+        for message in resend.into_iter().rev() {
+            self.segment_out.push_front(message);
+        }
+    }
+
     pub fn send_empty_message(&mut self) -> TcpSenderMessage {
-        TcpSenderMessage::new().with_seq(self.next_relative_seq().0)
+        let mut message = TcpSenderMessage::new().with_seq(self.next_relative_seq().0);
+        message.rst = self.error;
+        message.timestamp = self.timestamp(false);
+        message
     }
 
     pub fn receive(&mut self, message: TcpReceiverMessage) {
+        self.receive_segment(message, true);
+    }
+
+    /// Like [`TcpSender::receive`], where `pure_ack` tells whether the
+    /// acknowledgment came on a segment without data, SYN or FIN. Only those
+    /// count as duplicate acknowledgments (RFC 5681 section 2).
+    pub fn receive_segment(&mut self, message: TcpReceiverMessage, pure_ack: bool) {
+        if message.rst {
+            self.abandon();
+            return;
+        }
+        if self.error {
+            return;
+        }
+        let window_size = (message.window_size as u64) << self.peer_window_shift;
         if let Some(ref ack_no) = message.ack_no {
             let recv_abs_seq = ack_no.unwrap(self.isn, self.next_abs_seq);
             if recv_abs_seq > self.next_abs_seq() {
                 return;
             }
+            self.update_scoreboard(&message.sack);
 
-            let outdated_pos = self
-                .outstanding_segment
-                .iter()
-                .position(|(abs_seq, segment)| {
-                    abs_seq.0 + segment.sequence_length() as u64 > recv_abs_seq.0
-                });
+            let outdated_pos = self.outstanding_segment.iter().position(|segment| {
+                segment.abs_seq.0 + segment.message.sequence_length() as u64 > recv_abs_seq.0
+            });
 
             let pos = match outdated_pos {
                 Some(pos) => pos,
                 None => self.outstanding_segment.len(),
             };
             if pos != 0 {
-                self.outstanding_seq -= self
-                    .outstanding_segment
-                    .drain(..pos)
-                    .map(|(_, x)| x.sequence_length() as u64)
-                    .sum::<u64>();
-                self.rto_timeout = self.initial_rto;
+                // Karn's algorithm: an acknowledgment covering a retransmitted
+                // segment is no measurement, and an adaptive RTO keeps its
+                // backoff. An echoed timestamp tells which transmission
+                // arrived though.
+                let retransmitted = self.outstanding_segment[..pos]
+                    .iter()
+                    .any(|segment| segment.retransmitted);
+                let acked = &self.outstanding_segment[pos - 1];
+                let echo = message.timestamp_echo.filter(|_| self.timestamps_enabled());
+                let rtt = match echo {
+                    Some(echo) => Some((self.clock as u32).wrapping_sub(echo) as u64),
+                    None => (!retransmitted).then(|| self.clock - acked.sent_at),
+                };
+                if let Some(rtt) = rtt {
+                    self.rtt.sample(rtt);
+                }
+                let (prior_delivered, prior_time) = (acked.delivered, acked.delivered_time);
+                // Neither the SYN nor a retransmission says anything about
+                // the bandwidth, their interval spans the handshake or a loss.
+                let rate_sampled = !retransmitted && !self.outstanding_segment[0].message.syn;
+                let recovering = self.congestion.in_recovery();
+                let mut acked = 0;
+                let mut probe = None;
+                for segment in self.outstanding_segment.drain(..pos) {
+                    acked += segment.message.sequence_length() as u64;
+                    if segment.probe {
+                        probe = Some(segment.message.payload.len());
+                    }
+                }
+                if let Some(size) = probe {
+                    self.probe_acked(size);
+                }
+                self.outstanding_seq -= acked;
+                self.delivered += acked;
+                self.delivered_time = self.clock;
+                let rate_sample = (rate_sampled && self.clock > prior_time).then(|| RateSample {
+                    delivered: self.delivered - prior_delivered,
+                    interval: self.clock - prior_time,
+                    prior_delivered,
+                });
+                self.congestion.on_ack(&AckEvent {
+                    acked,
+                    in_flight: self.released_in_flight(),
+                    rtt,
+                    now: self.clock,
+                    rate_sample,
+                });
+                if rtt.is_some() || !self.adaptive_rto {
+                    self.rto_timeout = self.current_rto();
+                }
                 if !self.outstanding_segment.is_empty() {
                     self.timer = 0
                 }
+                self.duplicate_acks = 0;
+                if self.sack_permitted() {
+                    match self.recovery_point {
+                        Some(point) if recv_abs_seq >= point => self.recovery_point = None,
+                        Some(_) => self.retransmit_lost(),
+                        None => {}
+                    }
+                } else if recovering && self.congestion.in_recovery() {
+                    // A partial acknowledgment during recovery points at the
+                    // next hole, which is resent right away (RFC 6582).
+                    self.fast_retransmit();
+                }
+            } else if pure_ack
+                && self.syn_acked()
+                && recv_abs_seq.0 + self.outstanding_seq.0 == self.next_abs_seq.0
+            {
+                self.duplicate_ack(window_size);
             }
         }
-        self.windows = message.window_size;
+        self.windows = window_size;
+        self.max_window = self.max_window.max(window_size);
         self.retries_times = 0;
+        self.update_persist();
+    }
+
+    fn update_persist(&mut self) {
+        match (self.windows, self.persist.as_mut()) {
+            (0, None) => {
+                self.persist = Some(Persist {
+                    timer: 0,
+                    timeout: self.current_rto(),
+                    probe_due: true,
+                    unanswered: 0,
+                });
+            }
+            (0, Some(persist)) => persist.unanswered = 0,
+            (_, Some(_)) => {
+                self.persist = None;
+                self.rto_timeout = self.current_rto();
+                self.timer = 0;
+            }
+            (_, None) => {}
+        }
+    }
+
+    /// Unlike retransmission timeouts, window probes do not signal congestion.
+    fn tick_persist(&mut self, ms_since: u64) {
+        let max_rto = self.rtt.max_rto();
+        let Some(persist) = self.persist.as_mut() else {
+            return;
+        };
+        persist.timer += ms_since;
+        if persist.timer < persist.timeout {
+            return;
+        }
+        persist.timer = 0;
+        persist.timeout = (persist.timeout * 2).min(max_rto.max(self.initial_rto));
+        let Some(segment) = self.outstanding_segment.first_mut() else {
+            persist.probe_due = true;
+            return;
+        };
+        persist.unanswered += 1;
+        if persist.unanswered > MAX_RETRY_ATTEMPT {
+            self.reset();
+            return;
+        }
+        segment.retransmitted = true;
+        self.segment_out.push_back(segment.message.clone());
+        self.window_probes += 1;
     }
 
     pub fn tick(&mut self, ms_since: u64) {
         self.timer += ms_since;
+        self.clock += ms_since;
+        if self.persist.is_some() {
+            self.tick_persist(ms_since);
+            return;
+        }
 
-        for (_, segment) in self.outstanding_segment.iter() {
-            if self.timer < self.rto_timeout {
-                return;
-            }
-            if self.windows > 0 {
-                self.rto_timeout *= 2;
-            }
-            self.timer = 0;
-            self.retries_times += 1;
-            self.segment_out.push(segment.clone());
+        let Some(segment) = self.outstanding_segment.first_mut() else {
+            return;
+        };
+        if self.timer < self.rto_timeout {
+            return;
+        }
+        if segment.probe {
+            self.lose_probe(0);
+            return;
+        }
+        segment.retransmitted = true;
+        let segment = segment.message.clone();
+        self.rto_timeout *= 2;
+        if self.adaptive_rto {
+            self.rto_timeout = self.rtt.clamp(self.rto_timeout);
+        }
+        self.timer = 0;
+        self.retries_times += 1;
+        self.duplicate_acks = 0;
+        // The receiver may have dropped what it SACKed, so after a timeout
+        // the scoreboard starts over (RFC 2018 section 8).
+        self.recovery_point = None;
+        for segment in &mut self.outstanding_segment {
+            segment.sacked = false;
+            segment.lost = false;
+        }
+        if self.retries_times > MAX_RETRY_ATTEMPT {
+            self.reset();
+            return;
         }
+        self.congestion.on_timeout(self.released_in_flight());
+        self.segment_out.push_back(segment);
     }
 }
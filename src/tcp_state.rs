@@ -0,0 +1,82 @@
+/// Connection states from RFC 793, section 3.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcpState {
+    Closed,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    Closing,
+    TimeWait,
+    CloseWait,
+    LastAck,
+}
+
+/// Everything that can move a connection from one [`TcpState`] to another:
+/// user calls, segment arrivals and timers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcpEvent {
+    PassiveOpen,
+    ActiveOpen,
+    /// The user closed its outbound stream and our FIN went out.
+    Close,
+    Abort,
+
+    ReceiveSyn,
+    ReceiveSynAck,
+    ReceiveAckOfSyn,
+    ReceiveFin,
+    ReceiveAckOfFin,
+    ReceiveRst,
+
+    TimeWaitExpired,
+    RetransmissionLimit,
+}
+
+impl TcpState {
+    /// Looks up the transition table, `None` means the event is not valid in
+    /// the current state and should leave it untouched.
+    pub fn next(self, event: TcpEvent) -> Option<TcpState> {
+        use TcpEvent::*;
+        use TcpState::*;
+
+        let next = match (self, event) {
+            (Closed, _) => match event {
+                PassiveOpen => Listen,
+                ActiveOpen => SynSent,
+                _ => return None,
+            },
+            (_, Abort) | (_, RetransmissionLimit) => Closed,
+            (Listen, ReceiveRst) => return None,
+            (_, ReceiveRst) => Closed,
+
+            (Listen, ReceiveSyn) => SynReceived,
+            (Listen, ActiveOpen) => SynSent,
+            (Listen, Close) => Closed,
+
+            (SynSent, ReceiveSynAck) => Established,
+            (SynSent, ReceiveSyn) => SynReceived,
+            (SynSent, Close) => Closed,
+
+            (SynReceived, ReceiveAckOfSyn) => Established,
+            (SynReceived, Close) => FinWait1,
+
+            (Established, Close) => FinWait1,
+            (Established, ReceiveFin) => CloseWait,
+
+            (FinWait1, ReceiveAckOfFin) => FinWait2,
+            (FinWait1, ReceiveFin) => Closing,
+            (FinWait2, ReceiveFin) => TimeWait,
+            (Closing, ReceiveAckOfFin) => TimeWait,
+            (TimeWait, TimeWaitExpired) => Closed,
+
+            (CloseWait, Close) => LastAck,
+            (LastAck, ReceiveAckOfFin) => Closed,
+
+            _ => return None,
+        };
+        Some(next)
+    }
+}
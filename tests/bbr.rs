@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+
+use cs144::{
+    bbr::{Bbr, BbrMode},
+    byte_stream::ByteStream,
+    congestion::{AckEvent, CongestionControl, RateSample},
+    sequence::RelativeSequence,
+    tcp_sender::TcpSender,
+    TcpConfig, TcpReceiverMessage, TcpSenderMessage,
+};
+
+const MSS: u64 = 1000;
+
+/// A sender behind a bottleneck of `rate` bytes per millisecond, with
+/// `delay` milliseconds of propagation each way and no losses.
+struct Path {
+    sender: TcpSender,
+    stream: ByteStream,
+    rate: f64,
+    delay: u64,
+    now: u64,
+    credit: f64,
+    queue: VecDeque<TcpSenderMessage>,
+    queued: usize,
+    max_queued: usize,
+    forward: VecDeque<(u64, TcpSenderMessage)>,
+    backward: VecDeque<(u64, u32)>,
+    acked: u32,
+    /// Most segments released by the sender within one millisecond.
+    max_burst: usize,
+}
+
+impl Path {
+    fn new(congestion: impl CongestionControl + 'static, rate: f64, delay: u64) -> Path {
+        let (mut sender, mut stream) = TcpConfig::new()
+            .fixed_isn(RelativeSequence(0))
+            .congestion_control(congestion)
+            .generate_parts();
+        sender.push(&mut stream);
+        Path {
+            sender,
+            stream,
+            rate,
+            delay,
+            now: 0,
+            credit: 0.0,
+            queue: VecDeque::new(),
+            queued: 0,
+            max_queued: 0,
+            forward: VecDeque::new(),
+            backward: VecDeque::new(),
+            acked: 0,
+            max_burst: 0,
+        }
+    }
+
+    fn step(&mut self) {
+        self.now += 1;
+        self.sender.tick(1);
+
+        while self.backward.front().is_some_and(|(at, _)| *at <= self.now) {
+            let (_, ack_no) = self.backward.pop_front().unwrap();
+            let ack = TcpReceiverMessage::new()
+                .with_ack(ack_no)
+                .with_window_size(u16::MAX);
+            self.sender.receive(ack);
+        }
+        let room = self.stream.avalible_capacity();
+        self.stream.push(&vec![b'x'; room]);
+        self.sender.push(&mut self.stream);
+
+        let mut burst = 0;
+        while let Some(segment) = self.sender.try_send() {
+            self.queued += segment.sequence_length();
+            self.queue.push_back(segment);
+            burst += 1;
+        }
+        self.max_burst = self.max_burst.max(burst);
+        self.max_queued = self.max_queued.max(self.queued);
+
+        self.credit += self.rate;
+        while let Some(segment) = self.queue.front() {
+            let length = segment.sequence_length();
+            if self.credit < length as f64 {
+                break;
+            }
+            self.credit -= length as f64;
+            self.queued -= length;
+            let segment = self.queue.pop_front().unwrap();
+            self.forward.push_back((self.now + self.delay, segment));
+        }
+        if self.queue.is_empty() {
+            self.credit = 0.0;
+        }
+
+        while self.forward.front().is_some_and(|(at, _)| *at <= self.now) {
+            let (_, segment) = self.forward.pop_front().unwrap();
+            self.acked = segment.seq_no.0 + segment.sequence_length() as u32;
+            self.backward.push_back((self.now + self.delay, self.acked));
+        }
+    }
+
+    fn run(&mut self, ms: u64) -> u32 {
+        let before = self.acked;
+        for _ in 0..ms {
+            self.step();
+        }
+        self.acked - before
+    }
+}
+
+fn ack(bbr: &mut Bbr, now: u64, in_flight: u64, prior_delivered: u64, rate: u64) {
+    bbr.on_ack(&AckEvent {
+        acked: MSS,
+        in_flight,
+        rtt: Some(100),
+        now,
+        rate_sample: Some(RateSample {
+            delivered: rate * 100,
+            interval: 100,
+            prior_delivered,
+        }),
+    });
+}
+
+#[test]
+fn startup_ends_when_bandwidth_stops_growing() {
+    let mut bbr = Bbr::new(MSS);
+    assert_eq!(bbr.mode(), BbrMode::Startup);
+    assert_eq!(bbr.pacing_rate(), None);
+
+    // Every round trip delivers the same 50 bytes per millisecond.
+    let mut delivered = 0;
+    for round in 0..5 {
+        let prior = delivered;
+        for _ in 0..5 {
+            ack(&mut bbr, round * 100, 100 * MSS, prior, 50);
+            delivered += MSS;
+        }
+        if round < 3 {
+            assert_eq!(bbr.mode(), BbrMode::Startup, "round {round}");
+        }
+    }
+    assert_eq!(bbr.round(), 5);
+    assert_eq!(bbr.bottleneck_bandwidth(), 50.0);
+    assert_eq!(bbr.min_rtt(), Some(100));
+    assert_eq!(bbr.mode(), BbrMode::Drain);
+
+    // Drain lasts until no more than a BDP of 5000 bytes is in flight.
+    ack(&mut bbr, 600, 5 * MSS, delivered, 50);
+    assert_eq!(bbr.mode(), BbrMode::ProbeBw);
+    assert_eq!(bbr.pacing_rate(), Some(1.25 * 50.0));
+    assert_eq!(bbr.cwnd(), 10 * MSS);
+}
+
+#[test]
+fn bandwidth_estimate_forgets_old_rounds() {
+    let mut bbr = Bbr::new(MSS);
+    let mut delivered = 0;
+    ack(&mut bbr, 0, 0, delivered, 80);
+    for round in 1..=10 {
+        delivered += MSS;
+        ack(&mut bbr, round * 100, 0, delivered, 20);
+        if round < 10 {
+            assert_eq!(bbr.bottleneck_bandwidth(), 80.0);
+        }
+    }
+    assert_eq!(bbr.bottleneck_bandwidth(), 20.0);
+}
+
+#[test]
+fn probe_rtt_after_min_rtt_expires() {
+    let mut bbr = Bbr::new(MSS);
+    ack(&mut bbr, 0, 10 * MSS, 0, 50);
+    ack(&mut bbr, 10_001, 10 * MSS, MSS, 50);
+    assert_eq!(bbr.mode(), BbrMode::ProbeRtt);
+    assert_eq!(bbr.cwnd(), 4 * MSS);
+
+    // Held for 200 ms and a round trip once in flight is down to 4 segments.
+    ack(&mut bbr, 10_050, 4 * MSS, 2 * MSS, 50);
+    ack(&mut bbr, 10_200, 4 * MSS, 3 * MSS, 50);
+    assert_eq!(bbr.mode(), BbrMode::ProbeRtt);
+    ack(&mut bbr, 10_250, 4 * MSS, 4 * MSS, 50);
+    assert_eq!(bbr.min_rtt(), Some(100));
+    assert!(bbr.cwnd() > 4 * MSS);
+
+    // Each of these acknowledgments was a round without growth, so the pipe
+    // counts as full and probing resumes at the bottleneck rate.
+    assert_eq!(bbr.mode(), BbrMode::ProbeBw);
+}
+
+#[test]
+fn converges_on_bottleneck() {
+    // 100 bytes per millisecond, and 50 ms of round trip for a full segment
+    // including its 10 ms of transmission.
+    let mut path = Path::new(Bbr::new(MSS), 100.0, 20);
+    path.run(2000);
+    let delivered = path.run(5000);
+    assert!(delivered > 470_000, "{delivered}");
+    assert!(path.max_queued < 20 * MSS as usize, "{}", path.max_queued);
+
+    // Cruising, at most a BDP and change sits in the queue.
+    path.max_queued = 0;
+    path.run(2000);
+    assert!(path.max_queued <= 8 * MSS as usize, "{}", path.max_queued);
+}
+
+#[test]
+fn pacing_spreads_segments_over_time() {
+    let mut path = Path::new(Bbr::new(MSS), 100.0, 20);
+    path.run(1000);
+    path.max_burst = 0;
+    path.run(2000);
+    assert!(path.max_burst <= 2, "{}", path.max_burst);
+
+    // Without pacing, every acknowledgment releases a burst.
+    let mut path = Path::new(cs144::reno::Reno::new(MSS), 100.0, 20);
+    path.run(3000);
+    assert!(path.max_burst > 2);
+}
+
+#[test]
+fn lost_syn_does_not_stall_pacing() {
+    let mut path = Path::new(Bbr::new(MSS), 100.0, 20);
+    // The first SYN never makes it across, the retransmission after the
+    // RTO does.
+    assert!(path.sender.try_send().unwrap().syn);
+    path.run(3000);
+    assert!(path.sender.congestion_control().pacing_rate().unwrap() > 50.0);
+    let delivered = path.run(5000);
+    assert!(delivered > 470_000, "{delivered}");
+}
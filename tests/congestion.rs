@@ -0,0 +1,160 @@
+use std::{cell::RefCell, rc::Rc};
+
+use cs144::{
+    byte_stream::ByteStream,
+    congestion::{AckEvent, CongestionControl, RateSample},
+    sequence::RelativeSequence,
+    tcp_sender::TcpSender,
+    TcpConfig, TcpReceiverMessage, DEFAULT_TIMEOUT_RT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Ack(AckEvent),
+    Loss(u64),
+    Timeout(u64),
+}
+
+/// A fixed congestion window that records what the sender tells it.
+#[derive(Debug)]
+struct Recorder {
+    cwnd: u64,
+    events: Rc<RefCell<Vec<Event>>>,
+}
+
+impl CongestionControl for Recorder {
+    fn on_ack(&mut self, event: &AckEvent) {
+        self.events.borrow_mut().push(Event::Ack(*event));
+    }
+
+    fn on_loss(&mut self, in_flight: u64) {
+        self.events.borrow_mut().push(Event::Loss(in_flight));
+    }
+
+    fn on_timeout(&mut self, in_flight: u64) {
+        self.events.borrow_mut().push(Event::Timeout(in_flight));
+    }
+
+    fn cwnd(&self) -> u64 {
+        self.cwnd
+    }
+
+    fn ssthresh(&self) -> u64 {
+        u64::MAX
+    }
+}
+
+fn sender(cwnd: u64) -> (TcpSender, ByteStream, Rc<RefCell<Vec<Event>>>) {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let recorder = Recorder {
+        cwnd,
+        events: events.clone(),
+    };
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .congestion_control(recorder)
+        .nodelay(true)
+        .generate_parts();
+    sender.push(&mut stream);
+    assert!(sender.try_send().unwrap().syn);
+    (sender, stream, events)
+}
+
+fn ack(ack_no: u32, window: u16) -> TcpReceiverMessage {
+    TcpReceiverMessage::new()
+        .with_ack(ack_no)
+        .with_window_size(window)
+}
+
+fn drain(sender: &mut TcpSender) -> usize {
+    std::iter::from_fn(|| sender.try_send()).count()
+}
+
+#[test]
+fn cwnd_limits_bytes_in_flight() {
+    let (mut sender, mut stream, _) = sender(3000);
+    sender.receive(ack(1, 60000));
+    stream.push_str(&"x".repeat(10000));
+    sender.push(&mut stream);
+    assert_eq!(drain(&mut sender), 3);
+    assert_eq!(sender.seq_in_flight().0, 3000);
+
+    sender.receive(ack(1001, 60000));
+    sender.push(&mut stream);
+    assert_eq!(drain(&mut sender), 1);
+    assert_eq!(sender.seq_in_flight().0, 3000);
+}
+
+#[test]
+fn receiver_window_still_applies() {
+    let (mut sender, mut stream, _) = sender(3000);
+    sender.receive(ack(1, 1500));
+    stream.push_str(&"x".repeat(10000));
+    sender.push(&mut stream);
+    assert_eq!(drain(&mut sender), 2);
+    assert_eq!(sender.seq_in_flight().0, 1500);
+}
+
+#[test]
+fn controller_sees_acks_and_timeouts() {
+    let (mut sender, mut stream, events) = sender(3000);
+    sender.tick(40);
+    sender.receive(ack(1, 60000));
+    stream.push_str(&"x".repeat(2000));
+    sender.push(&mut stream);
+    drain(&mut sender);
+
+    sender.tick(DEFAULT_TIMEOUT_RT);
+    assert_eq!(drain(&mut sender), 1);
+    sender.receive(ack(1001, 60000));
+    sender.receive(ack(1001, 60000));
+    sender.receive(ack(2001, 60000));
+
+    assert_eq!(
+        *events.borrow(),
+        vec![
+            Event::Ack(AckEvent {
+                acked: 1,
+                in_flight: 0,
+                rtt: Some(40),
+                now: 40,
+                rate_sample: None,
+            }),
+            Event::Timeout(2000),
+            Event::Ack(AckEvent {
+                acked: 1000,
+                in_flight: 1000,
+                rtt: None,
+                now: 40 + DEFAULT_TIMEOUT_RT,
+                rate_sample: None,
+            }),
+            Event::Ack(AckEvent {
+                acked: 1000,
+                in_flight: 0,
+                rtt: Some(DEFAULT_TIMEOUT_RT),
+                now: 40 + DEFAULT_TIMEOUT_RT,
+                rate_sample: Some(RateSample {
+                    delivered: 2000,
+                    interval: DEFAULT_TIMEOUT_RT,
+                    prior_delivered: 1,
+                }),
+            }),
+        ]
+    );
+    assert_eq!(sender.congestion_control().cwnd(), 3000);
+}
+
+#[test]
+fn zero_window_probe_is_not_congestion() {
+    let (mut sender, mut stream, events) = sender(3000);
+    sender.receive(ack(1, 0));
+    stream.push_str("x");
+    sender.push(&mut stream);
+    assert_eq!(drain(&mut sender), 1);
+    sender.tick(DEFAULT_TIMEOUT_RT);
+    assert_eq!(drain(&mut sender), 1);
+    assert!(!events
+        .borrow()
+        .iter()
+        .any(|event| matches!(event, Event::Timeout(_) | Event::Loss(_))));
+}
@@ -0,0 +1,181 @@
+use cs144::{
+    congestion::{AckEvent, CongestionControl},
+    cubic::Cubic,
+    sequence::RelativeSequence,
+    TcpConfig, TcpReceiverMessage,
+};
+
+const MSS: u64 = 1000;
+
+/// Grows the window to `segments` in slow start.
+fn grown(segments: u64) -> Cubic {
+    let mut cubic = Cubic::new(MSS);
+    while cubic.cwnd() < segments * MSS {
+        cubic.on_ack(&AckEvent {
+            acked: MSS,
+            ..Default::default()
+        });
+    }
+    cubic
+}
+
+/// Acknowledges a full window one segment at a time every `rtt` until
+/// `until`, starting at `now`, and returns the time reached.
+fn run(cc: &mut impl CongestionControl, mut now: u64, rtt: u64, until: u64) -> u64 {
+    while now < until {
+        now += rtt;
+        for _ in 0..cc.cwnd() / MSS {
+            cc.on_ack(&AckEvent {
+                acked: MSS,
+                in_flight: 0,
+                rtt: Some(rtt),
+                now,
+                ..Default::default()
+            });
+        }
+    }
+    now
+}
+
+/// Loses a segment out of a window of 100 at time 0 and recovers from it.
+fn after_loss() -> Cubic {
+    let mut cubic = grown(100);
+    cubic.on_loss(100 * MSS);
+    cubic.on_ack(&AckEvent {
+        acked: 100 * MSS,
+        ..Default::default()
+    });
+    cubic
+}
+
+#[test]
+fn multiplicative_decrease() {
+    let mut cubic = grown(100);
+    assert_eq!(cubic.cwnd(), 100 * MSS);
+    cubic.on_loss(100 * MSS);
+    assert!(cubic.in_recovery());
+    assert_eq!(cubic.cwnd(), 70 * MSS);
+    assert_eq!(cubic.ssthresh(), 70 * MSS);
+    assert_eq!(cubic.w_max(), 100 * MSS);
+
+    // Only one reduction per window.
+    cubic.on_loss(100 * MSS);
+    assert_eq!(cubic.cwnd(), 70 * MSS);
+    cubic.on_ack(&AckEvent {
+        acked: 100 * MSS,
+        ..Default::default()
+    });
+    assert!(!cubic.in_recovery());
+    assert_eq!(cubic.cwnd(), 70 * MSS);
+}
+
+#[test]
+fn window_is_concave_then_convex() {
+    // K = cbrt(30 / 0.4), a little over four seconds.
+    let mut cubic = after_loss();
+    let rtt = 100;
+    let now = run(&mut cubic, 0, rtt, 2000);
+    let early = cubic.cwnd();
+    assert!(early > 85 * MSS && early < 100 * MSS, "{early}");
+
+    // Growth flattens out around the old maximum.
+    let now = run(&mut cubic, now, rtt, 4000);
+    let plateau = cubic.cwnd();
+    assert!(plateau > 98 * MSS && plateau <= 101 * MSS, "{plateau}");
+    let now = run(&mut cubic, now, rtt, 4600);
+    assert!(cubic.cwnd() - plateau < 2 * MSS);
+
+    // And then probes for more bandwidth ever faster.
+    run(&mut cubic, now, rtt, 8000);
+    assert!(cubic.cwnd() > 120 * MSS, "{}", cubic.cwnd());
+}
+
+#[test]
+fn growth_depends_on_time_not_rtt() {
+    let mut short = after_loss();
+    let mut long = after_loss();
+    run(&mut short, 0, 50, 2000);
+    run(&mut long, 0, 200, 2000);
+    let (short, long) = (short.cwnd(), long.cwnd());
+    assert!(short.abs_diff(long) < 4 * MSS, "{short} {long}");
+}
+
+#[test]
+fn tcp_friendly_region_on_short_rtt() {
+    // With a 5 ms RTT, a second holds 200 round trips. The cubic function
+    // alone would still be below 90 segments, but standard TCP with the same
+    // average throughput grows by ALPHA segments per round trip.
+    let mut cubic = after_loss();
+    run(&mut cubic, 0, 5, 1000);
+    let expected = 70.0 + 3.0 * 0.3 / 1.7 * 200.0;
+    let segments = cubic.cwnd() as f64 / MSS as f64;
+    assert!((segments - expected).abs() < 5.0, "{segments}");
+}
+
+#[test]
+fn growth_is_capped_per_rtt() {
+    // Twenty seconds into the epoch the cubic function is far above the
+    // window, yet one acknowledged segment grows it by at most half a segment.
+    let mut cubic = after_loss();
+    cubic.on_ack(&AckEvent {
+        acked: MSS,
+        now: 0,
+        ..Default::default()
+    });
+    let cwnd = cubic.cwnd();
+    cubic.on_ack(&AckEvent {
+        acked: MSS,
+        now: 20_000,
+        ..Default::default()
+    });
+    assert!(cubic.cwnd() <= cwnd + MSS / 2, "{}", cubic.cwnd());
+}
+
+#[test]
+fn fast_convergence_lowers_w_max() {
+    let mut cubic = after_loss();
+    run(&mut cubic, 0, 100, 1000);
+    let cwnd = cubic.cwnd();
+    assert!(cwnd < 100 * MSS);
+    cubic.on_loss(cwnd);
+    let expected = cwnd as f64 * 0.85;
+    assert!((cubic.w_max() as f64 - expected).abs() < 2.0);
+
+    let mut plain = after_loss().fast_convergence(false);
+    run(&mut plain, 0, 100, 1000);
+    let cwnd = plain.cwnd();
+    plain.on_loss(cwnd);
+    assert_eq!(plain.w_max(), cwnd);
+}
+
+#[test]
+fn timeout_collapses_window_once() {
+    let mut cubic = grown(100);
+    // The reduction is based on the window, not on what was in flight.
+    cubic.on_timeout(40 * MSS);
+    assert_eq!(cubic.cwnd(), MSS);
+    assert_eq!(cubic.ssthresh(), 70 * MSS);
+    assert_eq!(cubic.w_max(), 100 * MSS);
+    cubic.on_timeout(100 * MSS);
+    assert_eq!(cubic.ssthresh(), 70 * MSS);
+    assert_eq!(cubic.w_max(), 100 * MSS);
+}
+
+#[test]
+fn selectable_for_sender() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .congestion_control(Cubic::new(MSS))
+        .generate_parts();
+    sender.push(&mut stream);
+    sender.try_send();
+    sender.receive(
+        TcpReceiverMessage::new()
+            .with_ack(1)
+            .with_window_size(60000),
+    );
+    stream.push_str(&"x".repeat(20000));
+    sender.push(&mut stream);
+    assert_eq!(sender.seq_in_flight().0, sender.congestion_control().cwnd());
+}
@@ -0,0 +1,120 @@
+use std::net::Ipv4Addr;
+
+use cs144::{
+    arp::{ArpMessage, ArpOpcode},
+    ethernet::{EthernetAddress, EthernetFrame, EthernetHeader, ETHERTYPE_ARP, ETHERTYPE_IPV4},
+    ipv4::{InternetDatagram, Ipv4Header},
+    parser::ParseError,
+};
+
+const HOST: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0x01]);
+const ROUTER: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0x02]);
+
+// A real ARP request for 192.168.1.1 from 192.168.1.100, as captured off the
+// wire without the Ethernet padding.
+const CAPTURED_REQUEST: [u8; 42] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02, 0, 0, 0, 0, 0x01, 0x08, 0x06, 0x00, 0x01, 0x08, 0x00,
+    0x06, 0x04, 0x00, 0x01, 0x02, 0, 0, 0, 0, 0x01, 192, 168, 1, 100, 0, 0, 0, 0, 0, 0, 192, 168,
+    1, 1,
+];
+
+#[test]
+fn address_display() {
+    assert_eq!(HOST.to_string(), "02:00:00:00:00:01");
+    assert_eq!(EthernetAddress::BROADCAST.to_string(), "ff:ff:ff:ff:ff:ff");
+    assert!(EthernetAddress::BROADCAST.is_broadcast());
+    assert!(!HOST.is_broadcast());
+}
+
+#[test]
+fn frame_round_trip() {
+    let frame = EthernetFrame {
+        header: EthernetHeader {
+            dst: ROUTER,
+            src: HOST,
+            ethertype: 0x1234,
+        },
+        payload: b"payload".to_vec(),
+    };
+    let bytes = frame.serialize();
+    assert_eq!(bytes.len(), 21);
+    assert_eq!(&bytes[12..14], &[0x12, 0x34]);
+    assert_eq!(EthernetFrame::parse(&bytes).unwrap(), frame);
+    assert_eq!(
+        EthernetFrame::parse(&bytes[..13]),
+        Err(ParseError::Truncated)
+    );
+}
+
+#[test]
+fn arp_request_matches_capture() {
+    let request = ArpMessage::request(
+        HOST,
+        Ipv4Addr::new(192, 168, 1, 100),
+        Ipv4Addr::new(192, 168, 1, 1),
+    );
+    let frame = EthernetFrame::from_arp(&request);
+    assert_eq!(frame.serialize(), CAPTURED_REQUEST);
+
+    let parsed = EthernetFrame::parse(&CAPTURED_REQUEST).unwrap();
+    assert_eq!(parsed.header.ethertype, ETHERTYPE_ARP);
+    assert_eq!(parsed.to_arp().unwrap(), request);
+    assert_eq!(parsed.to_datagram(), Err(ParseError::WrongProtocol));
+}
+
+#[test]
+fn arp_reply_is_unicast() {
+    let reply = ArpMessage::reply(
+        ROUTER,
+        Ipv4Addr::new(192, 168, 1, 1),
+        HOST,
+        Ipv4Addr::new(192, 168, 1, 100),
+    );
+    let frame = EthernetFrame::from_arp(&reply);
+    assert_eq!(frame.header.dst, HOST);
+    assert_eq!(frame.header.src, ROUTER);
+
+    let parsed = ArpMessage::parse(&frame.payload).unwrap();
+    assert_eq!(parsed.opcode, ArpOpcode::Reply);
+    assert_eq!(parsed, reply);
+}
+
+#[test]
+fn malformed_arp_is_rejected() {
+    let payload = &CAPTURED_REQUEST[14..];
+    assert_eq!(
+        ArpMessage::parse(&payload[..27]),
+        Err(ParseError::Truncated)
+    );
+
+    let mut opcode = payload.to_vec();
+    opcode[7] = 3;
+    assert_eq!(ArpMessage::parse(&opcode), Err(ParseError::Unsupported));
+
+    let mut hardware = payload.to_vec();
+    hardware[1] = 6;
+    assert_eq!(ArpMessage::parse(&hardware), Err(ParseError::Unsupported));
+
+    let mut protocol = payload.to_vec();
+    protocol[2] = 0x86;
+    assert_eq!(ArpMessage::parse(&protocol), Err(ParseError::Unsupported));
+}
+
+#[test]
+fn frame_carries_datagram() {
+    let header = Ipv4Header {
+        protocol: 17,
+        src: Ipv4Addr::new(10, 0, 0, 1),
+        dst: Ipv4Addr::new(10, 0, 0, 2),
+        ..Default::default()
+    };
+    let datagram = InternetDatagram::new(header, b"udp".to_vec());
+    let frame = EthernetFrame::from_datagram(&datagram, HOST, ROUTER).unwrap();
+    assert_eq!(frame.header.ethertype, ETHERTYPE_IPV4);
+
+    let mut bytes = frame.serialize();
+    bytes.resize(60, 0);
+    let parsed = EthernetFrame::parse(&bytes).unwrap();
+    assert_eq!(parsed.to_datagram().unwrap(), datagram);
+    assert_eq!(parsed.to_arp(), Err(ParseError::WrongProtocol));
+}
@@ -0,0 +1,178 @@
+use std::net::Ipv4Addr;
+
+use cs144::{
+    fragment::{FragmentError, FragmentReassembler},
+    ipv4::{InternetDatagram, Ipv4Header},
+};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+
+fn datagram(id: u16, payload_len: usize) -> InternetDatagram {
+    let header = Ipv4Header {
+        id,
+        protocol: 253,
+        src: Ipv4Addr::new(10, 0, 0, 1),
+        dst: Ipv4Addr::new(10, 0, 0, 2),
+        ..Default::default()
+    };
+    let payload = (0..payload_len).map(|_| thread_rng().gen()).collect();
+    InternetDatagram::new(header, payload)
+}
+
+#[test]
+fn small_datagram_is_not_fragmented() {
+    let original = datagram(1, 100);
+    assert_eq!(original.fragment(1500).unwrap(), vec![original]);
+}
+
+#[test]
+fn fragments_fit_the_mtu() {
+    let original = datagram(1, 3000);
+    let fragments = original.fragment(1500).unwrap();
+    assert_eq!(fragments.len(), 3);
+    for fragment in fragments.iter() {
+        assert!(fragment.total_length() <= 1500);
+        assert_eq!(fragment.header.id, 1);
+        let parsed = InternetDatagram::parse(&fragment.serialize().unwrap()).unwrap();
+        assert_eq!(&parsed, fragment);
+    }
+    assert_eq!(fragments[0].payload.len(), 1480);
+    assert_eq!(fragments[1].header.fragment_offset, 185);
+    assert!(fragments[0].header.more_fragments);
+    assert!(fragments[1].header.more_fragments);
+    assert!(!fragments[2].header.more_fragments);
+    assert_eq!(fragments[2].payload.len(), 40);
+}
+
+#[test]
+fn fragments_can_be_fragmented_again() {
+    let original = datagram(7, 2000);
+    let mut fragments = Vec::new();
+    for fragment in original.fragment(1000).unwrap() {
+        fragments.extend(fragment.fragment(300).unwrap());
+    }
+    assert!(fragments.iter().all(|f| f.total_length() <= 300));
+
+    let mut reassembler = FragmentReassembler::default();
+    let mut result = None;
+    for fragment in fragments {
+        assert!(result.is_none());
+        result = reassembler.push(fragment);
+    }
+    assert_eq!(result.unwrap().payload, original.payload);
+}
+
+#[test]
+fn only_copied_options_are_repeated() {
+    let mut original = datagram(3, 100);
+    original.header.options = vec![0x07, 3, 0, 0x83, 4, 1, 2, 0];
+    let fragments = original.fragment(60).unwrap();
+    assert_eq!(fragments[0].header.options, original.header.options);
+    assert_eq!(fragments[1].header.options, vec![0x83, 4, 1, 2]);
+}
+
+#[test]
+fn dont_fragment_is_respected() {
+    let mut original = datagram(1, 3000);
+    original.header.dont_fragment = true;
+    assert_eq!(original.fragment(1500), Err(FragmentError::DontFragment));
+    assert_eq!(
+        datagram(1, 100).fragment(27),
+        Err(FragmentError::MtuTooSmall)
+    );
+}
+
+#[test]
+fn fragment_offsets_must_fit() {
+    let mut original = datagram(1, 1000);
+    original.header.more_fragments = true;
+    original.header.fragment_offset = 0x1fff - 100;
+    assert_eq!(original.fragment(500), Err(FragmentError::OffsetTooLarge));
+
+    original.header.fragment_offset = 0x1fff - 125;
+    let fragments = original.fragment(500).unwrap();
+    assert_eq!(fragments.last().unwrap().header.fragment_offset, 0x1fff - 5);
+}
+
+#[test]
+fn reassemble_out_of_order_with_duplicates() {
+    let original = datagram(42, 5000);
+    let mut fragments = original.fragment(576).unwrap();
+    fragments.extend(original.fragment(1000).unwrap());
+    fragments.shuffle(&mut thread_rng());
+
+    let mut reassembler = FragmentReassembler::default();
+    let mut result = None;
+    for fragment in fragments {
+        if let Some(datagram) = reassembler.push(fragment) {
+            assert!(result.is_none());
+            result = Some(datagram);
+        }
+    }
+    let result = result.unwrap();
+    assert_eq!(result.payload, original.payload);
+    assert_eq!(result.header.id, 42);
+    assert!(!result.header.more_fragments);
+}
+
+#[test]
+fn fragments_are_keyed_by_id() {
+    let a = datagram(1, 2000).fragment(1000).unwrap();
+    let b = datagram(2, 2000).fragment(1000).unwrap();
+    let mut reassembler = FragmentReassembler::default();
+    assert!(reassembler.push(a[0].clone()).is_none());
+    assert!(reassembler.push(b[1].clone()).is_none());
+    assert!(reassembler.push(b[2].clone()).is_none());
+    assert_eq!(reassembler.pending(), 2);
+    assert!(reassembler.push(a[1].clone()).is_none());
+    assert!(reassembler.push(a[2].clone()).is_some());
+    assert_eq!(reassembler.pending(), 1);
+}
+
+#[test]
+fn incomplete_datagrams_time_out() {
+    let fragments = datagram(1, 2000).fragment(1000).unwrap();
+    let mut reassembler = FragmentReassembler::new(1000, 1 << 20);
+    reassembler.push(fragments[0].clone());
+    reassembler.tick(999);
+    assert_eq!(reassembler.pending(), 1);
+    reassembler.tick(1);
+    assert_eq!(reassembler.pending(), 0);
+    assert_eq!(reassembler.memory_used(), 0);
+
+    reassembler.push(fragments[1].clone());
+    assert!(reassembler.push(fragments[2].clone()).is_none());
+}
+
+#[test]
+fn memory_limit_evicts_oldest() {
+    let a = datagram(1, 2000).fragment(1000).unwrap();
+    let b = datagram(2, 2000).fragment(1000).unwrap();
+    let mut reassembler = FragmentReassembler::new(30_000, 2000);
+    reassembler.push(a[0].clone());
+    reassembler.tick(10);
+    reassembler.push(b[0].clone());
+    assert_eq!(reassembler.memory_used(), 1952);
+
+    reassembler.push(b[1].clone());
+    assert_eq!(reassembler.pending(), 1);
+    assert!(reassembler.memory_used() <= 2000);
+    assert!(reassembler.push(b[2].clone()).is_some());
+    assert_eq!(reassembler.memory_used(), 0);
+}
+
+#[test]
+fn eviction_spares_the_datagram_being_completed() {
+    let a = datagram(1, 2000).fragment(1000).unwrap();
+    let b = datagram(2, 2000).fragment(1000).unwrap();
+    let mut reassembler = FragmentReassembler::new(30_000, 2000);
+    reassembler.push(a[0].clone());
+    reassembler.tick(10);
+    reassembler.push(b[0].clone());
+    reassembler.push(a[2].clone());
+    assert_eq!(reassembler.memory_used(), 2000);
+
+    // The oldest datagram is the one this fragment completes.
+    assert!(reassembler.push(a[1].clone()).is_some());
+    assert_eq!(reassembler.pending(), 0);
+    assert_eq!(reassembler.memory_used(), 0);
+}
@@ -0,0 +1,207 @@
+use std::net::Ipv4Addr;
+
+use cs144::{
+    icmp::IcmpMessage,
+    ipv4::{InternetDatagram, Ipv4Header, DEFAULT_TTL, IPV4_MAX_LENGTH},
+    parser::ParseError,
+    sequence::RelativeSequence,
+    tcp_segment::{TcpSegment, TCP_PROTOCOL},
+};
+
+// The example header from the Wikipedia article on the IPv4 header checksum.
+const REFERENCE_HEADER: [u8; 20] = [
+    0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0xb8, 0x61, 0xc0, 0xa8, 0x00, 0x01,
+    0xc0, 0xa8, 0x00, 0xc7,
+];
+
+fn reference_datagram() -> Vec<u8> {
+    let mut data = REFERENCE_HEADER.to_vec();
+    data.resize(0x73, 0xab);
+    data
+}
+
+#[test]
+fn parse_reference() {
+    let datagram = InternetDatagram::parse(&reference_datagram()).unwrap();
+    let header = &datagram.header;
+    assert_eq!(header.tos, 0);
+    assert_eq!(header.id, 0);
+    assert!(header.dont_fragment);
+    assert!(!header.more_fragments);
+    assert_eq!(header.fragment_offset, 0);
+    assert_eq!(header.ttl, 64);
+    assert_eq!(header.protocol, 17);
+    assert_eq!(header.checksum, 0xb861);
+    assert_eq!(header.src, Ipv4Addr::new(192, 168, 0, 1));
+    assert_eq!(header.dst, Ipv4Addr::new(192, 168, 0, 199));
+    assert_eq!(datagram.payload.len(), 0x73 - 20);
+    assert_eq!(datagram.total_length(), 0x73);
+}
+
+#[test]
+fn serialize_reference() {
+    let header = Ipv4Header {
+        dont_fragment: true,
+        protocol: 17,
+        src: Ipv4Addr::new(192, 168, 0, 1),
+        dst: Ipv4Addr::new(192, 168, 0, 199),
+        ..Default::default()
+    };
+    let datagram = InternetDatagram::new(header, vec![0xab; 0x73 - 20]);
+    assert_eq!(datagram.header.checksum, 0xb861);
+    assert_eq!(datagram.serialize().unwrap(), reference_datagram());
+}
+
+#[test]
+fn options_and_padding() {
+    let header = Ipv4Header {
+        options: vec![1, 1, 1],
+        protocol: 253,
+        ..Default::default()
+    };
+    let datagram = InternetDatagram::new(header, b"payload".to_vec());
+    let bytes = datagram.serialize().unwrap();
+    assert_eq!(bytes[0], 0x46);
+    assert_eq!(bytes.len(), 24 + 7);
+
+    let parsed = InternetDatagram::parse(&bytes).unwrap();
+    assert_eq!(parsed.header.options, vec![1, 1, 1, 0]);
+    assert_eq!(parsed.payload, b"payload");
+}
+
+#[test]
+fn oversized_datagrams_are_rejected() {
+    let header = Ipv4Header::default();
+    let datagram = InternetDatagram::new(header.clone(), vec![0; IPV4_MAX_LENGTH - 20]);
+    assert_eq!(datagram.serialize().unwrap().len(), IPV4_MAX_LENGTH);
+
+    let datagram = InternetDatagram::new(header, vec![0; IPV4_MAX_LENGTH - 19]);
+    assert_eq!(datagram.serialize(), Err(ParseError::BadLength));
+    assert_eq!(
+        IcmpMessage::fragmentation_needed(&datagram, 576),
+        Err(ParseError::BadLength)
+    );
+
+    let header = Ipv4Header {
+        options: vec![1; 41],
+        ..Default::default()
+    };
+    let datagram = InternetDatagram::new(header, b"payload".to_vec());
+    assert_eq!(datagram.header.checksum, 0);
+    assert_eq!(datagram.serialize(), Err(ParseError::BadLength));
+}
+
+#[test]
+fn trailing_bytes_are_ignored() {
+    let mut data = reference_datagram();
+    data.extend_from_slice(&[0; 6]);
+    let datagram = InternetDatagram::parse(&data).unwrap();
+    assert_eq!(datagram.payload.len(), 0x73 - 20);
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    let data = reference_datagram();
+    assert_eq!(
+        InternetDatagram::parse(&data[..19]),
+        Err(ParseError::Truncated)
+    );
+
+    let mut bad_version = data.clone();
+    bad_version[0] = 0x65;
+    assert_eq!(
+        InternetDatagram::parse(&bad_version),
+        Err(ParseError::BadVersion)
+    );
+
+    let mut short_ihl = data.clone();
+    short_ihl[0] = 0x44;
+    assert_eq!(
+        InternetDatagram::parse(&short_ihl),
+        Err(ParseError::HeaderTooShort)
+    );
+
+    let mut long_ihl = data[..20].to_vec();
+    long_ihl[0] = 0x4f;
+    assert_eq!(
+        InternetDatagram::parse(&long_ihl),
+        Err(ParseError::Truncated)
+    );
+
+    assert_eq!(
+        InternetDatagram::parse(&data[..0x72]),
+        Err(ParseError::BadLength)
+    );
+
+    let mut short_total = data.clone();
+    short_total[3] = 19;
+    assert_eq!(
+        InternetDatagram::parse(&short_total),
+        Err(ParseError::BadLength)
+    );
+
+    let mut bad_checksum = data;
+    bad_checksum[8] = 63;
+    assert_eq!(
+        InternetDatagram::parse(&bad_checksum),
+        Err(ParseError::BadChecksum)
+    );
+}
+
+#[test]
+fn tcp_round_trip() {
+    let src = Ipv4Addr::new(10, 0, 0, 1);
+    let dst = Ipv4Addr::new(10, 0, 0, 2);
+    let segment = TcpSegment {
+        src_port: 4321,
+        dst_port: 80,
+        seq_no: RelativeSequence(1),
+        syn: true,
+        window_size: 65535,
+        payload: b"GET / HTTP/1.1".to_vec(),
+        ..Default::default()
+    };
+
+    let datagram = InternetDatagram::from_tcp(&segment, src, dst);
+    assert_eq!(datagram.header.protocol, TCP_PROTOCOL);
+    assert_eq!(datagram.header.ttl, DEFAULT_TTL);
+
+    let parsed = InternetDatagram::parse(&datagram.serialize().unwrap()).unwrap();
+    let parsed_segment = parsed.to_tcp().unwrap();
+    assert_eq!(parsed_segment.payload, segment.payload);
+    assert_eq!(parsed_segment.src_port, 4321);
+    assert!(parsed_segment.syn);
+
+    let mut spoofed = parsed.clone();
+    spoofed.header.src = Ipv4Addr::new(10, 0, 0, 9);
+    assert_eq!(spoofed.to_tcp(), Err(ParseError::BadChecksum));
+
+    let mut udp = parsed;
+    udp.header.protocol = 17;
+    assert_eq!(udp.to_tcp(), Err(ParseError::WrongProtocol));
+}
+
+#[test]
+fn icmp_fragmentation_needed_round_trip() {
+    let original = InternetDatagram::parse(&reference_datagram()).unwrap();
+    let message = IcmpMessage::fragmentation_needed(&original, 576).unwrap();
+    assert!(message.is_error());
+    assert_eq!(message.next_hop_mtu(), Some(576));
+
+    let src = Ipv4Addr::new(10, 0, 0, 1);
+    let datagram = InternetDatagram::from_icmp(&message, src, original.header.src);
+    let parsed = InternetDatagram::parse(&datagram.serialize().unwrap()).unwrap();
+    assert_eq!(parsed.to_icmp().unwrap(), message);
+    assert_eq!(parsed.to_tcp().unwrap_err(), ParseError::WrongProtocol);
+
+    let (header, quoted) = message.original().unwrap();
+    assert_eq!(header, original.header);
+    assert_eq!(quoted, &original.payload[..8]);
+
+    let mut corrupted = datagram.payload.clone();
+    corrupted[4] ^= 1;
+    assert_eq!(
+        IcmpMessage::parse(&corrupted).unwrap_err(),
+        ParseError::BadChecksum
+    );
+}
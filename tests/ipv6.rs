@@ -0,0 +1,204 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use cs144::{
+    ip::{FamilyMismatch, IpDatagram},
+    ipv6::{
+        ExtensionHeader, Ipv6Datagram, Ipv6Header, AUTHENTICATION, DESTINATION_OPTIONS, FRAGMENT,
+        HOP_BY_HOP, NO_NEXT_HEADER, ROUTING,
+    },
+    parser::ParseError,
+    sequence::RelativeSequence,
+    tcp_segment::{TcpSegment, TCP_PROTOCOL},
+};
+
+const SRC: Ipv6Addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+const DST: Ipv6Addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+
+fn header() -> Ipv6Header {
+    Ipv6Header {
+        traffic_class: 0x2e,
+        flow_label: 0x12345,
+        hop_limit: 17,
+        src: SRC,
+        dst: DST,
+    }
+}
+
+#[test]
+fn fixed_header_layout() {
+    let datagram = Ipv6Datagram {
+        header: header(),
+        extensions: Vec::new(),
+        protocol: 17,
+        payload: b"abc".to_vec(),
+    };
+    let bytes = datagram.serialize().unwrap();
+    assert_eq!(bytes.len(), 43);
+    assert_eq!(&bytes[..8], &[0x62, 0xe1, 0x23, 0x45, 0, 3, 17, 17]);
+    assert_eq!(&bytes[8..24], &SRC.octets());
+    assert_eq!(&bytes[24..40], &DST.octets());
+    assert_eq!(Ipv6Datagram::parse(&bytes).unwrap(), datagram);
+
+    let jumbogram = Ipv6Datagram {
+        payload: vec![0; 1 << 16],
+        ..datagram
+    };
+    assert_eq!(jumbogram.serialize(), Err(ParseError::BadLength));
+}
+
+#[test]
+fn extension_headers_are_walked() {
+    let datagram = Ipv6Datagram {
+        header: header(),
+        extensions: vec![
+            ExtensionHeader::new(HOP_BY_HOP, vec![1, 4, 0, 0, 0, 0]).unwrap(),
+            ExtensionHeader::new(ROUTING, vec![0; 14]).unwrap(),
+            ExtensionHeader::new(FRAGMENT, vec![0, 0, 0, 0, 0, 1]).unwrap(),
+            ExtensionHeader::new(AUTHENTICATION, vec![7; 10]).unwrap(),
+            ExtensionHeader::new(DESTINATION_OPTIONS, vec![1, 4, 0, 0, 0, 0]).unwrap(),
+        ],
+        protocol: TCP_PROTOCOL,
+        payload: b"segment".to_vec(),
+    };
+    let bytes = datagram.serialize().unwrap();
+    assert_eq!(bytes[6], HOP_BY_HOP);
+    assert_eq!(&bytes[40..42], &[ROUTING, 0]);
+    assert_eq!(&bytes[48..50], &[FRAGMENT, 1]);
+    assert_eq!(&bytes[64..66], &[AUTHENTICATION, 0]);
+    assert_eq!(&bytes[72..74], &[DESTINATION_OPTIONS, 1]);
+    assert_eq!(&bytes[84..86], &[TCP_PROTOCOL, 0]);
+    assert_eq!(datagram.payload_length(), bytes.len() - 40);
+
+    let parsed = Ipv6Datagram::parse(&bytes).unwrap();
+    assert_eq!(parsed, datagram);
+}
+
+#[test]
+fn no_next_header_has_no_payload() {
+    let mut bytes = Ipv6Datagram {
+        header: header(),
+        extensions: Vec::new(),
+        protocol: NO_NEXT_HEADER,
+        payload: Vec::new(),
+    }
+    .serialize()
+    .unwrap();
+    bytes[5] = 4;
+    bytes.extend_from_slice(&[1, 2, 3, 4]);
+    let parsed = Ipv6Datagram::parse(&bytes).unwrap();
+    assert_eq!(parsed.protocol, NO_NEXT_HEADER);
+    assert!(parsed.payload.is_empty());
+}
+
+#[test]
+fn malformed_datagrams_are_rejected() {
+    let bytes = Ipv6Datagram {
+        header: header(),
+        extensions: vec![
+            ExtensionHeader::new(DESTINATION_OPTIONS, vec![1, 4, 0, 0, 0, 0]).unwrap(),
+        ],
+        protocol: 17,
+        payload: b"abc".to_vec(),
+    }
+    .serialize()
+    .unwrap();
+
+    assert_eq!(
+        Ipv6Datagram::parse(&bytes[..39]),
+        Err(ParseError::Truncated)
+    );
+    assert_eq!(
+        Ipv6Datagram::parse(&bytes[..bytes.len() - 1]),
+        Err(ParseError::BadLength)
+    );
+
+    let mut bad_version = bytes.clone();
+    bad_version[0] = 0x42;
+    assert_eq!(
+        Ipv6Datagram::parse(&bad_version),
+        Err(ParseError::BadVersion)
+    );
+
+    let mut long_extension = bytes;
+    long_extension[41] = 3;
+    assert_eq!(
+        Ipv6Datagram::parse(&long_extension),
+        Err(ParseError::Truncated)
+    );
+}
+
+#[test]
+fn tcp_round_trip() {
+    let segment = TcpSegment {
+        src_port: 1000,
+        dst_port: 2000,
+        seq_no: RelativeSequence(5),
+        syn: true,
+        payload: b"hi".to_vec(),
+        ..Default::default()
+    };
+    let datagram = Ipv6Datagram::from_tcp(&segment, SRC, DST);
+    let parsed = Ipv6Datagram::parse(&datagram.serialize().unwrap()).unwrap();
+    assert_eq!(parsed.to_tcp().unwrap().payload, b"hi");
+
+    let mut wrong_dst = parsed.clone();
+    wrong_dst.header.dst = SRC;
+    assert_eq!(wrong_dst.to_tcp(), Err(ParseError::BadChecksum));
+
+    let mut udp = parsed;
+    udp.protocol = 17;
+    assert_eq!(udp.to_tcp(), Err(ParseError::WrongProtocol));
+}
+
+#[test]
+fn ip_datagram_dispatches_on_version() {
+    let segment = TcpSegment::default();
+    let v4_src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let v4_dst = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    let v4 = IpDatagram::from_tcp(&segment, v4_src, v4_dst).unwrap();
+    assert!(matches!(v4, IpDatagram::V4(_)));
+    let parsed = IpDatagram::parse(&v4.serialize().unwrap()).unwrap();
+    assert_eq!(parsed.src(), v4_src);
+    assert_eq!(parsed.dst(), v4_dst);
+    assert_eq!(parsed.protocol(), TCP_PROTOCOL);
+
+    let v6 = IpDatagram::from_tcp(&segment, IpAddr::V6(SRC), IpAddr::V6(DST)).unwrap();
+    assert!(matches!(v6, IpDatagram::V6(_)));
+    let parsed = IpDatagram::parse(&v6.serialize().unwrap()).unwrap();
+    assert_eq!(parsed.src(), IpAddr::V6(SRC));
+    assert!(parsed.to_tcp().is_ok());
+
+    assert_eq!(IpDatagram::parse(&[0x50]), Err(ParseError::BadVersion));
+    assert_eq!(IpDatagram::parse(&[]), Err(ParseError::Truncated));
+
+    assert_eq!(
+        IpDatagram::from_tcp(&segment, v4_src, IpAddr::V6(DST)),
+        Err(FamilyMismatch)
+    );
+}
+
+#[test]
+fn extension_header_lengths_are_validated() {
+    for (kind, length) in [
+        (HOP_BY_HOP, 0),
+        (HOP_BY_HOP, 5),
+        (ROUTING, 7),
+        (FRAGMENT, 14),
+        (AUTHENTICATION, 2),
+        (AUTHENTICATION, 8),
+        (DESTINATION_OPTIONS, 2048),
+    ] {
+        assert_eq!(
+            ExtensionHeader::new(kind, vec![0; length]),
+            Err(ParseError::BadLength),
+            "{kind} with {length} bytes"
+        );
+    }
+    assert_eq!(
+        ExtensionHeader::new(TCP_PROTOCOL, vec![0; 6]),
+        Err(ParseError::Unsupported)
+    );
+    let largest = ExtensionHeader::new(DESTINATION_OPTIONS, vec![0; 2046]).unwrap();
+    assert_eq!(largest.data().len(), 2046);
+}
@@ -0,0 +1,137 @@
+use std::net::Ipv4Addr;
+
+use cs144::{
+    arp::{ArpMessage, ArpOpcode},
+    ethernet::{EthernetAddress, EthernetFrame},
+    ipv4::{InternetDatagram, Ipv4Header},
+    network_interface::{NetworkInterface, ARP_CACHE_TTL, ARP_REQUEST_INTERVAL},
+};
+
+const LOCAL: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0x01]);
+const REMOTE: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0x02]);
+const LOCAL_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+const REMOTE_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+
+fn datagram(payload: &[u8]) -> InternetDatagram {
+    let header = Ipv4Header {
+        protocol: 17,
+        src: LOCAL_IP,
+        dst: Ipv4Addr::new(192, 168, 0, 1),
+        ..Default::default()
+    };
+    InternetDatagram::new(header, payload.to_vec())
+}
+
+fn expect_request(interface: &mut NetworkInterface, target: Ipv4Addr) {
+    let frame = interface.try_send().expect("expected an ARP request");
+    assert!(frame.header.dst.is_broadcast());
+    let request = frame.to_arp().unwrap();
+    assert_eq!(request.opcode, ArpOpcode::Request);
+    assert_eq!(request.sender_ethernet_address, LOCAL);
+    assert_eq!(request.sender_ip_address, LOCAL_IP);
+    assert_eq!(request.target_ip_address, target);
+}
+
+fn expect_datagram(interface: &mut NetworkInterface, dst: EthernetAddress, payload: &[u8]) {
+    let frame = interface.try_send().expect("expected a datagram");
+    assert_eq!(frame.header.src, LOCAL);
+    assert_eq!(frame.header.dst, dst);
+    assert_eq!(frame.to_datagram().unwrap(), datagram(payload));
+}
+
+fn reply_from_remote() -> EthernetFrame {
+    EthernetFrame::from_arp(&ArpMessage::reply(REMOTE, REMOTE_IP, LOCAL, LOCAL_IP))
+}
+
+#[test]
+fn resolves_then_sends() {
+    let mut interface = NetworkInterface::new(LOCAL, LOCAL_IP);
+    interface.send_datagram(datagram(b"first"), REMOTE_IP);
+    expect_request(&mut interface, REMOTE_IP);
+    assert!(interface.try_send().is_none());
+
+    interface.recv_frame(reply_from_remote());
+    expect_datagram(&mut interface, REMOTE, b"first");
+    assert!(interface.try_send().is_none());
+
+    interface.send_datagram(datagram(b"second"), REMOTE_IP);
+    expect_datagram(&mut interface, REMOTE, b"second");
+}
+
+#[test]
+fn queues_while_pending() {
+    let mut interface = NetworkInterface::new(LOCAL, LOCAL_IP);
+    interface.send_datagram(datagram(b"one"), REMOTE_IP);
+    interface.tick(ARP_REQUEST_INTERVAL - 1);
+    interface.send_datagram(datagram(b"two"), REMOTE_IP);
+    expect_request(&mut interface, REMOTE_IP);
+    assert!(interface.try_send().is_none());
+
+    interface.recv_frame(reply_from_remote());
+    expect_datagram(&mut interface, REMOTE, b"one");
+    expect_datagram(&mut interface, REMOTE, b"two");
+    assert!(interface.try_send().is_none());
+}
+
+#[test]
+fn unanswered_request_is_retried_after_interval() {
+    let mut interface = NetworkInterface::new(LOCAL, LOCAL_IP);
+    interface.send_datagram(datagram(b"lost"), REMOTE_IP);
+    expect_request(&mut interface, REMOTE_IP);
+
+    interface.tick(ARP_REQUEST_INTERVAL);
+    interface.send_datagram(datagram(b"again"), REMOTE_IP);
+    expect_request(&mut interface, REMOTE_IP);
+
+    interface.recv_frame(reply_from_remote());
+    expect_datagram(&mut interface, REMOTE, b"again");
+    assert!(interface.try_send().is_none());
+}
+
+#[test]
+fn mapping_expires() {
+    let mut interface = NetworkInterface::new(LOCAL, LOCAL_IP);
+    interface.recv_frame(reply_from_remote());
+    interface.tick(ARP_CACHE_TTL - 1);
+    interface.send_datagram(datagram(b"cached"), REMOTE_IP);
+    expect_datagram(&mut interface, REMOTE, b"cached");
+
+    interface.tick(1);
+    interface.send_datagram(datagram(b"stale"), REMOTE_IP);
+    expect_request(&mut interface, REMOTE_IP);
+}
+
+#[test]
+fn replies_to_requests_for_own_address() {
+    let mut interface = NetworkInterface::new(LOCAL, LOCAL_IP);
+    let other = ArpMessage::request(REMOTE, REMOTE_IP, Ipv4Addr::new(10, 0, 0, 3));
+    interface.recv_frame(EthernetFrame::from_arp(&other));
+    assert!(interface.try_send().is_none());
+
+    let request = ArpMessage::request(REMOTE, REMOTE_IP, LOCAL_IP);
+    interface.recv_frame(EthernetFrame::from_arp(&request));
+    let frame = interface.try_send().unwrap();
+    assert_eq!(frame.header.dst, REMOTE);
+    assert_eq!(
+        frame.to_arp().unwrap(),
+        ArpMessage::reply(LOCAL, LOCAL_IP, REMOTE, REMOTE_IP)
+    );
+
+    // The request taught us the sender's address as well.
+    interface.send_datagram(datagram(b"learned"), REMOTE_IP);
+    expect_datagram(&mut interface, REMOTE, b"learned");
+}
+
+#[test]
+fn receives_datagrams_addressed_to_us() {
+    let mut interface = NetworkInterface::new(LOCAL, LOCAL_IP);
+    let stranger = EthernetAddress([0x02, 0, 0, 0, 0, 0x03]);
+    interface.recv_frame(
+        EthernetFrame::from_datagram(&datagram(b"not ours"), REMOTE, stranger).unwrap(),
+    );
+    assert!(interface.try_receive().is_none());
+
+    interface.recv_frame(EthernetFrame::from_datagram(&datagram(b"ours"), REMOTE, LOCAL).unwrap());
+    assert_eq!(interface.try_receive(), Some(datagram(b"ours")));
+    assert!(interface.try_receive().is_none());
+}
@@ -0,0 +1,50 @@
+use cs144::plpmtud::{MtuSearch, BASE_MSS, MAX_PROBES, SEARCH_PRECISION};
+
+#[test]
+fn first_probe_tries_the_ceiling() {
+    let mut search = MtuSearch::default();
+    assert_eq!(search.mss(1460), BASE_MSS);
+    assert_eq!(search.next_probe(1460), Some(1460));
+
+    search.probe_acked(1460);
+    assert_eq!(search.mss(1460), 1460);
+    assert_eq!(search.next_probe(1460), None);
+    // A smaller ceiling, from ICMP say, still applies.
+    assert_eq!(search.mss(1000), 1000);
+}
+
+#[test]
+fn losses_bisect_the_range() {
+    let mut search = MtuSearch::new(500);
+    for _ in 1..MAX_PROBES {
+        search.probe_lost(1500);
+        assert_eq!(search.next_probe(1500), Some(1500));
+    }
+    search.probe_lost(1500);
+    assert_eq!(search.next_probe(1500), Some(1000));
+    assert_eq!(search.mss(1500), 500);
+
+    search.probe_acked(1000);
+    assert_eq!(search.next_probe(1500), Some(1250));
+    for _ in 0..MAX_PROBES {
+        search.probe_lost(1250);
+    }
+    assert_eq!(search.next_probe(1500), Some(1125));
+}
+
+#[test]
+fn search_converges_on_the_path_mtu() {
+    let path = 1234;
+    let mut search = MtuSearch::default();
+    let mut probes = 0;
+    while let Some(size) = search.next_probe(9000) {
+        probes += 1;
+        match size <= path {
+            true => search.probe_acked(size),
+            false => search.probe_lost(size),
+        }
+    }
+    let mss = search.mss(9000);
+    assert!(mss <= path && path - mss < SEARCH_PRECISION);
+    assert!(probes < 50);
+}
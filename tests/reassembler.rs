@@ -490,3 +490,46 @@ fn hole_filled_with_overlap() {
     assert_eq!(reassembler.pending(), 0);
     assert_eq!(buf.read_all(), "abcdefgh");
 }
+
+#[test]
+fn buffered_ranges() {
+    let mut reassembler = Reassembler::new(16);
+    let mut buf = ByteStream::new(16);
+    reassembler.push_str(2, "cd", false, &mut buf);
+    reassembler.push_str(8, "ij", false, &mut buf);
+    reassembler.push_str(4, "e", false, &mut buf);
+    assert_eq!(
+        reassembler.buffered().collect::<Vec<_>>(),
+        vec![2..5, 8..10]
+    );
+
+    reassembler.push_str(0, "ab", false, &mut buf);
+    assert_eq!(buf.read_all(), "abcde");
+    assert_eq!(reassembler.buffered().collect::<Vec<_>>(), vec![8..10]);
+}
+
+#[test]
+fn overlapping_pieces_merge() {
+    let mut reassembler = Reassembler::new(32);
+    let mut buf = ByteStream::new(32);
+    reassembler.push_str(4, "ef", false, &mut buf);
+    reassembler.push_str(8, "ij", false, &mut buf);
+    reassembler.push_str(12, "mn", false, &mut buf);
+    reassembler.push_str(20, "u", false, &mut buf);
+    assert_eq!(reassembler.pending(), 7);
+
+    // Spans two pieces and touches a third.
+    reassembler.push_str(5, "fghijkl", false, &mut buf);
+    assert_eq!(
+        reassembler.buffered().collect::<Vec<_>>(),
+        vec![4..14, 20..21]
+    );
+    assert_eq!(reassembler.pending(), 11);
+    assert_eq!(reassembler.buffered_range(9), Some(4..14));
+    assert_eq!(reassembler.buffered_range(14), None);
+
+    reassembler.push_str(0, "abcd", false, &mut buf);
+    assert_eq!(buf.read_all(), "abcdefghijklmn");
+    assert_eq!(reassembler.pending(), 1);
+    assert_eq!(reassembler.buffered_range(9), None);
+}
@@ -0,0 +1,216 @@
+use cs144::{
+    congestion::{AckEvent, CongestionControl},
+    reno::Reno,
+    sequence::RelativeSequence,
+    TcpConfig, TcpReceiverMessage,
+};
+
+const MSS: u64 = 1000;
+
+fn ack(reno: &mut Reno, acked: u64, in_flight: u64) {
+    reno.on_ack(&AckEvent {
+        acked,
+        in_flight,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn initial_window() {
+    assert_eq!(Reno::initial_window(1000), 4000);
+    assert_eq!(Reno::initial_window(1460), 4380);
+    assert_eq!(Reno::initial_window(9000), 18000);
+    let reno = Reno::new(MSS);
+    assert_eq!(reno.cwnd(), 4000);
+    assert_eq!(reno.ssthresh(), u64::MAX);
+}
+
+#[test]
+fn slow_start_then_congestion_avoidance() {
+    let mut reno = Reno::new(MSS);
+    for _ in 0..4 {
+        ack(&mut reno, MSS, 0);
+    }
+    assert_eq!(reno.cwnd(), 8000);
+
+    // A cumulative acknowledgment grows the window by one segment at most.
+    ack(&mut reno, 4 * MSS, 0);
+    assert_eq!(reno.cwnd(), 9000);
+
+    reno.on_timeout(8000);
+    assert_eq!(reno.ssthresh(), 4000);
+    assert_eq!(reno.cwnd(), MSS);
+    for _ in 0..3 {
+        ack(&mut reno, MSS, 0);
+    }
+    assert_eq!(reno.cwnd(), 4000);
+
+    // One segment per window's worth of acknowledgments.
+    for _ in 0..3 {
+        ack(&mut reno, MSS, 0);
+    }
+    assert_eq!(reno.cwnd(), 4000);
+    ack(&mut reno, MSS, 0);
+    assert_eq!(reno.cwnd(), 5000);
+    for _ in 0..5 {
+        ack(&mut reno, MSS, 0);
+    }
+    assert_eq!(reno.cwnd(), 6000);
+}
+
+#[test]
+fn timeout_never_drops_ssthresh_below_two_segments() {
+    let mut reno = Reno::new(MSS);
+    reno.on_timeout(MSS);
+    assert_eq!(reno.ssthresh(), 2 * MSS);
+    assert_eq!(reno.cwnd(), MSS);
+}
+
+#[test]
+fn new_reno_stays_in_recovery_on_partial_ack() {
+    let mut reno = Reno::new(MSS);
+    reno.on_loss(10000);
+    assert!(reno.in_recovery());
+    assert_eq!(reno.ssthresh(), 5000);
+    assert_eq!(reno.cwnd(), 8000);
+
+    reno.on_duplicate_ack();
+    reno.on_duplicate_ack();
+    assert_eq!(reno.cwnd(), 10000);
+
+    // A second loss in the same window does not halve again.
+    reno.on_loss(10000);
+    assert_eq!(reno.ssthresh(), 5000);
+
+    ack(&mut reno, 2000, 8000);
+    assert!(reno.in_recovery());
+    assert_eq!(reno.cwnd(), 9000);
+
+    ack(&mut reno, 8000, 3000);
+    assert!(!reno.in_recovery());
+    assert_eq!(reno.cwnd(), 4000);
+}
+
+#[test]
+fn classic_reno_leaves_recovery_on_first_ack() {
+    let mut reno = Reno::classic(MSS);
+    reno.on_loss(10000);
+    reno.on_duplicate_ack();
+    assert_eq!(reno.cwnd(), 9000);
+
+    ack(&mut reno, 2000, 8000);
+    assert!(!reno.in_recovery());
+    assert_eq!(reno.cwnd(), 5000);
+}
+
+#[test]
+fn sender_recovers_from_two_losses_in_a_window() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .congestion_control(Reno::new(MSS))
+        .generate_parts();
+    sender.push(&mut stream);
+    assert!(sender.try_send().unwrap().syn);
+    let ack = |ack_no| {
+        TcpReceiverMessage::new()
+            .with_ack(ack_no)
+            .with_window_size(60000)
+    };
+    sender.receive(ack(1));
+
+    // Of four segments, the first and third are lost.
+    stream.push_str(&"x".repeat(4000));
+    sender.push(&mut stream);
+    assert_eq!(std::iter::from_fn(|| sender.try_send()).count(), 4);
+    for _ in 0..3 {
+        sender.receive(ack(1));
+    }
+    assert!(sender.congestion_control().in_recovery());
+    assert_eq!(sender.congestion_control().ssthresh(), 2 * MSS);
+    assert_eq!(sender.try_send().unwrap().seq_no, RelativeSequence(1));
+
+    // The partial acknowledgment resends the next hole straight away.
+    sender.receive(ack(2001));
+    assert!(sender.congestion_control().in_recovery());
+    assert_eq!(sender.try_send().unwrap().seq_no, RelativeSequence(2001));
+    sender.receive(ack(4001));
+    assert!(!sender.congestion_control().in_recovery());
+    assert_eq!(sender.fast_retransmits(), 2);
+}
+
+#[test]
+fn sender_fills_congestion_window() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .congestion_control(Reno::new(MSS))
+        .send_capacity(100000)
+        .generate_parts();
+    sender.push(&mut stream);
+    assert!(sender.try_send().unwrap().syn);
+
+    stream.push_str(&"x".repeat(100000));
+    let mut acked = 0;
+    for _ in 0..4 {
+        let window = TcpReceiverMessage::new().with_window_size(60000);
+        sender.receive(window.with_ack(acked + 1));
+        sender.push(&mut stream);
+        let cwnd = sender.congestion_control().cwnd();
+        assert_eq!(sender.seq_in_flight().0, cwnd);
+
+        // Acknowledge every segment on its own, doubling the window.
+        let mut segments = Vec::new();
+        while let Some(segment) = sender.try_send() {
+            segments.push(segment);
+        }
+        for segment in &segments[..segments.len() - 1] {
+            acked += segment.payload.len() as u32;
+            let window = TcpReceiverMessage::new().with_window_size(60000);
+            sender.receive(window.with_ack(acked + 1));
+        }
+        acked += segments.last().unwrap().payload.len() as u32;
+        assert!(sender.congestion_control().cwnd() >= 2 * cwnd - MSS);
+    }
+}
+
+#[test]
+fn sender_uses_peer_mss_for_windows() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .congestion_control(Reno::default())
+        .send_capacity(100000)
+        .generate_parts();
+    sender.push(&mut stream);
+    assert!(sender.try_send().unwrap().syn);
+    sender.permit_mss(Some(536));
+    assert_eq!(sender.mss(), 536);
+    assert_eq!(
+        sender.congestion_control().cwnd(),
+        Reno::initial_window(536)
+    );
+
+    let ack = |ack_no| {
+        TcpReceiverMessage::new()
+            .with_ack(ack_no)
+            .with_window_size(60000)
+    };
+    sender.receive(ack(1));
+    stream.push_str(&"x".repeat(100000));
+    sender.push(&mut stream);
+    let segments: Vec<_> = std::iter::from_fn(|| sender.try_send()).collect();
+    assert_eq!(sender.seq_in_flight().0, sender.congestion_control().cwnd());
+    assert!(segments[..4]
+        .iter()
+        .all(|segment| segment.payload.len() == 536));
+
+    // Slow start grows the window by one 536 byte segment per acknowledgment.
+    sender.receive(ack(537));
+    let cwnd = sender.congestion_control().cwnd();
+    assert!((5 * 536..6 * 536).contains(&cwnd), "cwnd {cwnd}");
+
+    // The timeout leaves one segment of the negotiated size.
+    sender.tick(cs144::DEFAULT_TIMEOUT_RT);
+    assert_eq!(sender.congestion_control().cwnd(), 536);
+    assert_eq!(sender.congestion_control().ssthresh(), 2 * 536);
+}
@@ -0,0 +1,297 @@
+use std::net::Ipv4Addr;
+
+use cs144::{
+    ethernet::{EthernetAddress, ETHERNET_MTU},
+    icmp::ICMP_PROTOCOL,
+    ipv4::{InternetDatagram, Ipv4Header},
+    network_interface::NetworkInterface,
+    router::Router,
+};
+
+fn ethernet(n: u8) -> EthernetAddress {
+    EthernetAddress([0x02, 0, 0, 0, 0, n])
+}
+
+fn datagram(dst: Ipv4Addr, ttl: u8) -> InternetDatagram {
+    let header = Ipv4Header {
+        protocol: 17,
+        ttl,
+        src: Ipv4Addr::new(10, 0, 0, 2),
+        dst,
+        ..Default::default()
+    };
+    InternetDatagram::new(header, b"hello".to_vec())
+}
+
+/// Moves every pending frame across the link between `a` and `b`.
+fn exchange(a: &mut NetworkInterface, b: &mut NetworkInterface) {
+    loop {
+        let mut moved = false;
+        while let Some(frame) = a.try_send() {
+            b.recv_frame(frame);
+            moved = true;
+        }
+        while let Some(frame) = b.try_send() {
+            a.recv_frame(frame);
+            moved = true;
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// A host on 10.0.0.0/24 and one on 10.1.0.0/16, joined by `router`, which
+/// sends anything else to an upstream gateway.
+struct Topology {
+    router: Router,
+    host: NetworkInterface,
+    server: NetworkInterface,
+    gateway: NetworkInterface,
+}
+
+impl Topology {
+    fn new() -> Self {
+        Self::with_servers_mtu(ETHERNET_MTU)
+    }
+
+    /// Makes the link to the servers a bottleneck of `mtu` bytes.
+    fn with_servers_mtu(mtu: usize) -> Self {
+        let mut router = Router::new();
+        let lan = router.add_interface(NetworkInterface::new(
+            ethernet(1),
+            Ipv4Addr::new(10, 0, 0, 1),
+        ));
+        let servers = router.add_interface(
+            NetworkInterface::new(ethernet(2), Ipv4Addr::new(10, 1, 0, 1)).with_mtu(mtu),
+        );
+        let upstream = router.add_interface(NetworkInterface::new(
+            ethernet(3),
+            Ipv4Addr::new(192, 168, 0, 2),
+        ));
+        router.add_route(Ipv4Addr::new(10, 0, 0, 0), 24, None, lan);
+        router.add_route(Ipv4Addr::new(10, 1, 0, 0), 16, None, servers);
+        router.add_route(
+            Ipv4Addr::UNSPECIFIED,
+            0,
+            Some(Ipv4Addr::new(192, 168, 0, 1)),
+            upstream,
+        );
+
+        Topology {
+            router,
+            host: NetworkInterface::new(ethernet(10), Ipv4Addr::new(10, 0, 0, 2)),
+            server: NetworkInterface::new(ethernet(11), Ipv4Addr::new(10, 1, 2, 3)),
+            gateway: NetworkInterface::new(ethernet(12), Ipv4Addr::new(192, 168, 0, 1)),
+        }
+    }
+
+    fn run(&mut self) {
+        for _ in 0..4 {
+            exchange(&mut self.host, self.router.interface_mut(0));
+            exchange(&mut self.server, self.router.interface_mut(1));
+            exchange(&mut self.gateway, self.router.interface_mut(2));
+            self.router.route();
+        }
+    }
+}
+
+#[test]
+fn longest_prefix_wins() {
+    let mut router = Router::new();
+    for n in 0..3 {
+        router.add_interface(NetworkInterface::new(
+            ethernet(n),
+            Ipv4Addr::new(10, 0, 0, n),
+        ));
+    }
+    router.add_route(Ipv4Addr::UNSPECIFIED, 0, None, 0);
+    router.add_route(Ipv4Addr::new(10, 0, 0, 0), 8, None, 1);
+    router.add_route(Ipv4Addr::new(10, 1, 2, 0), 24, None, 2);
+
+    let lookup = |router: &Router, dst| router.lookup(dst).map(|route| route.interface);
+    assert_eq!(lookup(&router, Ipv4Addr::new(10, 1, 2, 3)), Some(2));
+    assert_eq!(lookup(&router, Ipv4Addr::new(10, 1, 3, 3)), Some(1));
+    assert_eq!(lookup(&router, Ipv4Addr::new(8, 8, 8, 8)), Some(0));
+
+    assert!(router.remove_route(Ipv4Addr::new(10, 1, 2, 0), 24));
+    assert!(!router.remove_route(Ipv4Addr::new(10, 1, 2, 0), 24));
+    assert_eq!(lookup(&router, Ipv4Addr::new(10, 1, 2, 3)), Some(1));
+
+    assert!(router.remove_route(Ipv4Addr::UNSPECIFIED, 0));
+    assert_eq!(lookup(&router, Ipv4Addr::new(8, 8, 8, 8)), None);
+    assert_eq!(router.routes().len(), 1);
+}
+
+#[test]
+fn host_routes_match_exactly() {
+    let mut router = Router::new();
+    router.add_interface(NetworkInterface::new(
+        ethernet(1),
+        Ipv4Addr::new(10, 0, 0, 1),
+    ));
+    router.add_route(Ipv4Addr::new(10, 0, 0, 7), 32, None, 0);
+    assert!(router.lookup(Ipv4Addr::new(10, 0, 0, 7)).is_some());
+    assert!(router.lookup(Ipv4Addr::new(10, 0, 0, 6)).is_none());
+}
+
+#[test]
+fn forwards_to_directly_attached_network() {
+    let mut topology = Topology::new();
+    let dst = Ipv4Addr::new(10, 1, 2, 3);
+    topology
+        .host
+        .send_datagram(datagram(dst, 64), Ipv4Addr::new(10, 0, 0, 1));
+    topology.run();
+
+    let received = topology.server.try_receive().unwrap();
+    assert_eq!(received.header.ttl, 63);
+    assert_eq!(received.payload, b"hello");
+    assert_eq!(
+        InternetDatagram::parse(&received.serialize().unwrap()).unwrap(),
+        received
+    );
+    assert!(topology.gateway.try_receive().is_none());
+}
+
+#[test]
+fn default_route_uses_next_hop() {
+    let mut topology = Topology::new();
+    let dst = Ipv4Addr::new(1, 1, 1, 1);
+    topology
+        .host
+        .send_datagram(datagram(dst, 64), Ipv4Addr::new(10, 0, 0, 1));
+    topology.run();
+
+    let received = topology.gateway.try_receive().unwrap();
+    assert_eq!(received.header.dst, dst);
+    assert_eq!(received.header.ttl, 63);
+    assert!(topology.server.try_receive().is_none());
+}
+
+#[test]
+fn expiring_ttl_is_dropped() {
+    let mut topology = Topology::new();
+    let dst = Ipv4Addr::new(10, 1, 2, 3);
+    for ttl in [0, 1] {
+        topology
+            .host
+            .send_datagram(datagram(dst, ttl), Ipv4Addr::new(10, 0, 0, 1));
+    }
+    topology
+        .host
+        .send_datagram(datagram(dst, 2), Ipv4Addr::new(10, 0, 0, 1));
+    topology.run();
+
+    assert_eq!(topology.server.try_receive().unwrap().header.ttl, 1);
+    assert!(topology.server.try_receive().is_none());
+}
+
+#[test]
+fn routes_across_two_routers() {
+    // host -- a -- b -- server, with a and b joined by 172.16.0.0/30.
+    let mut a = Router::new();
+    let a_lan = a.add_interface(NetworkInterface::new(
+        ethernet(1),
+        Ipv4Addr::new(10, 0, 0, 1),
+    ));
+    let a_link = a.add_interface(NetworkInterface::new(
+        ethernet(2),
+        Ipv4Addr::new(172, 16, 0, 1),
+    ));
+    a.add_route(Ipv4Addr::new(10, 0, 0, 0), 24, None, a_lan);
+    a.add_route(
+        Ipv4Addr::new(10, 1, 0, 0),
+        16,
+        Some(Ipv4Addr::new(172, 16, 0, 2)),
+        a_link,
+    );
+
+    let mut b = Router::new();
+    let b_link = b.add_interface(NetworkInterface::new(
+        ethernet(3),
+        Ipv4Addr::new(172, 16, 0, 2),
+    ));
+    let b_lan = b.add_interface(NetworkInterface::new(
+        ethernet(4),
+        Ipv4Addr::new(10, 1, 0, 1),
+    ));
+    b.add_route(Ipv4Addr::new(10, 1, 0, 0), 16, None, b_lan);
+    b.add_route(
+        Ipv4Addr::new(10, 0, 0, 0),
+        24,
+        Some(Ipv4Addr::new(172, 16, 0, 1)),
+        b_link,
+    );
+
+    let mut host = NetworkInterface::new(ethernet(10), Ipv4Addr::new(10, 0, 0, 2));
+    let mut server = NetworkInterface::new(ethernet(11), Ipv4Addr::new(10, 1, 2, 3));
+    host.send_datagram(
+        datagram(Ipv4Addr::new(10, 1, 2, 3), 64),
+        Ipv4Addr::new(10, 0, 0, 1),
+    );
+
+    for _ in 0..6 {
+        exchange(&mut host, a.interface_mut(a_lan));
+        a.route();
+        let (a_side, b_side) = (a.interface_mut(a_link), b.interface_mut(b_link));
+        exchange(a_side, b_side);
+        b.route();
+        exchange(b.interface_mut(b_lan), &mut server);
+    }
+
+    let received = server.try_receive().unwrap();
+    assert_eq!(received.header.ttl, 62);
+    assert_eq!(received.header.src, Ipv4Addr::new(10, 0, 0, 2));
+}
+
+fn large_datagram(dont_fragment: bool) -> InternetDatagram {
+    let header = Ipv4Header {
+        protocol: 17,
+        ttl: 64,
+        dont_fragment,
+        src: Ipv4Addr::new(10, 0, 0, 2),
+        dst: Ipv4Addr::new(10, 1, 2, 3),
+        ..Default::default()
+    };
+    InternetDatagram::new(header, vec![7; 1000])
+}
+
+#[test]
+fn bottleneck_fragments_datagrams() {
+    let mut topology = Topology::with_servers_mtu(576);
+    topology
+        .host
+        .send_datagram(large_datagram(false), Ipv4Addr::new(10, 0, 0, 1));
+    topology.run();
+
+    let mut payload = Vec::new();
+    while let Some(fragment) = topology.server.try_receive() {
+        assert!(fragment.total_length() <= 576);
+        payload.extend_from_slice(&fragment.payload);
+    }
+    assert_eq!(payload, vec![7; 1000]);
+    assert!(topology.host.try_receive().is_none());
+}
+
+#[test]
+fn bottleneck_reports_fragmentation_needed() {
+    let mut topology = Topology::with_servers_mtu(576);
+    let sent = large_datagram(true);
+    topology
+        .host
+        .send_datagram(sent.clone(), Ipv4Addr::new(10, 0, 0, 1));
+    topology.run();
+    assert!(topology.server.try_receive().is_none());
+
+    let reply = topology.host.try_receive().unwrap();
+    assert_eq!(reply.header.protocol, ICMP_PROTOCOL);
+    assert_eq!(reply.header.src, Ipv4Addr::new(10, 0, 0, 1));
+    assert_eq!(reply.header.dst, Ipv4Addr::new(10, 0, 0, 2));
+    let message = reply.to_icmp().unwrap();
+    assert_eq!(message.next_hop_mtu(), Some(576));
+    let (header, quoted) = message.original().unwrap();
+    assert_eq!(header.dst, sent.header.dst);
+    assert_eq!(header.ttl, 63);
+    assert_eq!(quoted, &sent.payload[..8]);
+}
@@ -0,0 +1,254 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use cs144::{
+    ethernet::EthernetAddress, icmp::ICMP_PROTOCOL, ip::IpDatagram,
+    network_interface::NetworkInterface, plpmtud::SEARCH_PRECISION, router::Router,
+    tcp_adapter::TcpAdapter, tcp_segment::ipv4_mss_for_mtu, tcp_state::TcpState, TcpConfig,
+    DEFAULT_TIMEOUT_RT,
+};
+
+fn adapter(ip: IpAddr, port: u16) -> TcpAdapter {
+    TcpAdapter::new(
+        TcpConfig::new().generate_connection(),
+        SocketAddr::new(ip, port),
+    )
+}
+
+/// Carries datagrams between the two adapters through their wire format.
+fn exchange(a: &mut TcpAdapter, b: &mut TcpAdapter) {
+    loop {
+        let mut quiet = true;
+        while let Some(datagram) = a.try_send() {
+            quiet = false;
+            b.receive(&IpDatagram::parse(&datagram.serialize().unwrap()).unwrap());
+        }
+        while let Some(datagram) = b.try_send() {
+            quiet = false;
+            a.receive(&IpDatagram::parse(&datagram.serialize().unwrap()).unwrap());
+        }
+        if quiet {
+            break;
+        }
+    }
+}
+
+fn transfer(client_ip: IpAddr, server_ip: IpAddr) {
+    let mut client = adapter(client_ip, 40000);
+    let mut server = adapter(server_ip, 80);
+    client.connect(server.local()).unwrap();
+    exchange(&mut client, &mut server);
+    assert_eq!(server.remote(), Some(client.local()));
+    assert_eq!(client.connection().state(), TcpState::Established);
+    assert_eq!(server.connection().state(), TcpState::Established);
+
+    client.connection_mut().write(b"GET / HTTP/1.1\r\n\r\n");
+    client.connection_mut().end_input_stream();
+    exchange(&mut client, &mut server);
+    assert_eq!(
+        server.connection_mut().inbound_mut().read_all(),
+        "GET / HTTP/1.1\r\n\r\n"
+    );
+
+    server.connection_mut().write(b"HTTP/1.1 200 OK\r\n\r\n");
+    server.connection_mut().end_input_stream();
+    exchange(&mut client, &mut server);
+    assert_eq!(
+        client.connection_mut().inbound_mut().read_all(),
+        "HTTP/1.1 200 OK\r\n\r\n"
+    );
+    assert!(!server.connection().active());
+    assert_eq!(client.connection().state(), TcpState::TimeWait);
+}
+
+#[test]
+fn transfer_over_ipv4() {
+    transfer(
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+    );
+}
+
+#[test]
+fn transfer_over_ipv6() {
+    transfer(
+        IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1)),
+        IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2)),
+    );
+}
+
+#[test]
+fn foreign_datagrams_are_dropped() {
+    let server_ip = IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2));
+    let mut client = adapter(IpAddr::V6(Ipv6Addr::LOCALHOST), 40000);
+    let mut server = adapter(server_ip, 80);
+
+    client.connect(SocketAddr::new(server_ip, 81)).unwrap();
+    let syn = client.try_send().unwrap();
+    server.receive(&syn);
+    assert_eq!(server.remote(), None);
+    assert!(server.try_send().is_none());
+
+    let mut mixed = adapter(IpAddr::V4(Ipv4Addr::LOCALHOST), 40000);
+    assert!(mixed.connect(server.local()).is_err());
+    assert_eq!(mixed.remote(), None);
+    assert!(mixed.try_send().is_none());
+
+    let mut v4_client = adapter(IpAddr::V4(Ipv4Addr::LOCALHOST), 40000);
+    v4_client
+        .connect(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80))
+        .unwrap();
+    let syn = v4_client.try_send().unwrap();
+    server.receive(&syn);
+    assert_eq!(server.remote(), None);
+    assert_eq!(server.connection().state(), TcpState::Listen);
+}
+
+#[test]
+fn ipv4_ids_increase() {
+    let mut client = adapter(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 40000);
+    client
+        .connect(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80))
+        .unwrap();
+    let Some(IpDatagram::V4(first)) = client.try_send() else {
+        panic!("expected an IPv4 SYN");
+    };
+    client.tick(cs144::DEFAULT_TIMEOUT_RT);
+    let Some(IpDatagram::V4(second)) = client.try_send() else {
+        panic!("expected an IPv4 retransmission");
+    };
+    assert_eq!(second.header.id, first.header.id.wrapping_add(1));
+}
+
+const CLIENT_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+const SERVER_IP: Ipv4Addr = Ipv4Addr::new(10, 1, 0, 2);
+
+/// client -- router -- server, where the router's link to the server only
+/// carries `mtu` bytes. With `filter_icmp`, ICMP never reaches the client.
+struct Bottleneck {
+    router: Router,
+    client: NetworkInterface,
+    server: NetworkInterface,
+    filter_icmp: bool,
+}
+
+impl Bottleneck {
+    fn new(mtu: usize, filter_icmp: bool) -> Self {
+        let ethernet = |n| EthernetAddress([0x02, 0, 0, 0, 0, n]);
+        let mut router = Router::new();
+        let lan = router.add_interface(NetworkInterface::new(
+            ethernet(1),
+            Ipv4Addr::new(10, 0, 0, 1),
+        ));
+        let servers = router.add_interface(
+            NetworkInterface::new(ethernet(2), Ipv4Addr::new(10, 1, 0, 1)).with_mtu(mtu),
+        );
+        router.add_route(Ipv4Addr::new(10, 0, 0, 0), 24, None, lan);
+        router.add_route(Ipv4Addr::new(10, 1, 0, 0), 24, None, servers);
+        Bottleneck {
+            router,
+            client: NetworkInterface::new(ethernet(10), CLIENT_IP),
+            server: NetworkInterface::new(ethernet(11), SERVER_IP),
+            filter_icmp,
+        }
+    }
+
+    /// Moves frames and datagrams until the network is quiet.
+    fn run(&mut self, client: &mut TcpAdapter, server: &mut TcpAdapter) {
+        loop {
+            let mut quiet = true;
+            while let Some(IpDatagram::V4(datagram)) = client.try_send() {
+                quiet = false;
+                self.client
+                    .send_datagram(datagram, Ipv4Addr::new(10, 0, 0, 1));
+            }
+            while let Some(IpDatagram::V4(datagram)) = server.try_send() {
+                quiet = false;
+                self.server
+                    .send_datagram(datagram, Ipv4Addr::new(10, 1, 0, 1));
+            }
+            for _ in 0..2 {
+                quiet &= !link(&mut self.client, self.router.interface_mut(0));
+                quiet &= !link(&mut self.server, self.router.interface_mut(1));
+                self.router.route();
+            }
+            while let Some(datagram) = self.client.try_receive() {
+                quiet = false;
+                if !(self.filter_icmp && datagram.header.protocol == ICMP_PROTOCOL) {
+                    client.receive(&datagram.into());
+                }
+            }
+            while let Some(datagram) = self.server.try_receive() {
+                quiet = false;
+                server.receive(&datagram.into());
+            }
+            if quiet {
+                break;
+            }
+        }
+    }
+}
+
+/// Moves every pending frame across a link, telling whether there were any.
+fn link(a: &mut NetworkInterface, b: &mut NetworkInterface) -> bool {
+    let mut moved = false;
+    loop {
+        let mut quiet = true;
+        while let Some(frame) = a.try_send() {
+            b.recv_frame(frame);
+            quiet = false;
+        }
+        while let Some(frame) = b.try_send() {
+            a.recv_frame(frame);
+            quiet = false;
+        }
+        if quiet {
+            return moved;
+        }
+        moved = true;
+    }
+}
+
+/// Sends `length` bytes from a client configured by `config` across
+/// `network`, returning the client once everything arrived.
+fn transfer_across(mut network: Bottleneck, config: TcpConfig, length: usize) -> TcpAdapter {
+    let client_addr = SocketAddr::new(IpAddr::V4(CLIENT_IP), 40000);
+    let mut client = TcpAdapter::new(config.generate_connection(), client_addr);
+    let mut server = adapter(IpAddr::V4(SERVER_IP), 80);
+    client.connect(server.local()).unwrap();
+    network.run(&mut client, &mut server);
+    assert_eq!(client.connection().state(), TcpState::Established);
+
+    let data: Vec<u8> = (0..length).map(|i| b'a' + (i % 26) as u8).collect();
+    let (mut written, mut received) = (0, Vec::new());
+    for _ in 0..100 {
+        written += client.connection_mut().write(&data[written..]);
+        network.run(&mut client, &mut server);
+        received.extend(server.connection_mut().inbound_mut().read_all().bytes());
+        if received.len() == length {
+            break;
+        }
+        client.tick(DEFAULT_TIMEOUT_RT);
+        server.tick(DEFAULT_TIMEOUT_RT);
+    }
+    assert_eq!(received, data);
+    client
+}
+
+#[test]
+fn icmp_lowers_mss_to_the_bottleneck() {
+    let network = Bottleneck::new(576, false);
+    let client = transfer_across(network, TcpConfig::new(), 20000);
+    assert_eq!(client.connection().sender().mss(), ipv4_mss_for_mtu(576));
+}
+
+#[test]
+fn mtu_probing_works_without_icmp() {
+    let network = Bottleneck::new(800, true);
+    let client = transfer_across(network, TcpConfig::new().mtu_probing(true), 1_000_000);
+    let mss = client.connection().sender().mss();
+    let path_mss = ipv4_mss_for_mtu(800);
+    assert!(
+        mss <= path_mss && path_mss - mss < SEARCH_PRECISION,
+        "settled on {mss}"
+    );
+}
@@ -0,0 +1,602 @@
+use cs144::{
+    sequence::RelativeSequence,
+    tcp_connection::{TcpConnection, LINGER_FACTOR},
+    tcp_receiver::DEFAULT_ACK_TIMEOUT,
+    tcp_state::TcpState,
+    TcpConfig, TcpMessage, TcpReceiverMessage, TcpSenderMessage, DEFAULT_TIMEOUT_RT,
+    MAX_PAYLOAD_SIZE, MAX_RETRY_ATTEMPT,
+};
+use rand::{thread_rng, Rng};
+
+const LINGER: u64 = LINGER_FACTOR * DEFAULT_TIMEOUT_RT;
+
+fn connection(isn: u32) -> TcpConnection {
+    TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .generate_connection()
+}
+
+fn exchange(a: &mut TcpConnection, b: &mut TcpConnection) {
+    loop {
+        let mut quiet = true;
+        while let Some(seg) = a.try_send() {
+            quiet = false;
+            b.receive(seg);
+        }
+        while let Some(seg) = b.try_send() {
+            quiet = false;
+            a.receive(seg);
+        }
+        if quiet {
+            break;
+        }
+    }
+}
+
+fn established() -> (TcpConnection, TcpConnection) {
+    let mut client = connection(thread_rng().gen());
+    let mut server = connection(thread_rng().gen());
+    client.connect();
+    exchange(&mut client, &mut server);
+    (client, server)
+}
+
+#[test]
+fn active_open_sends_syn() {
+    let isn = thread_rng().gen();
+    let mut conn = connection(isn);
+    assert_eq!(conn.state(), TcpState::Listen);
+    assert!(conn.try_send().is_none());
+    conn.connect();
+    assert_eq!(conn.state(), TcpState::SynSent);
+    let seg = conn.try_send().expect("SYN");
+    assert!(seg.sender.syn);
+    assert_eq!(seg.sender.seq_no, RelativeSequence(isn));
+    assert_eq!(seg.receiver.ack_no, None);
+    assert!(conn.try_send().is_none());
+    assert!(conn.active());
+}
+
+#[test]
+fn active_open_acks_syn_ack() {
+    let isn = thread_rng().gen();
+    let peer_isn: u32 = thread_rng().gen();
+    let mut conn = connection(isn);
+    conn.connect();
+    conn.try_send().expect("SYN");
+
+    conn.receive(TcpMessage::new(
+        TcpSenderMessage::new().with_syn().with_seq(peer_isn),
+        TcpReceiverMessage::new()
+            .with_ack(isn.wrapping_add(1))
+            .with_window_size(1000),
+    ));
+    let seg = conn.try_send().expect("ACK");
+    assert!(!seg.sender.syn);
+    assert_eq!(seg.sender.sequence_length(), 0);
+    assert_eq!(seg.sender.seq_no, RelativeSequence(isn.wrapping_add(1)));
+    assert_eq!(
+        seg.receiver.ack_no,
+        Some(RelativeSequence(peer_isn.wrapping_add(1)))
+    );
+    assert_eq!(conn.bytes_in_flight(), 0);
+    assert_eq!(conn.state(), TcpState::Established);
+}
+
+#[test]
+fn passive_open_replies_with_syn_ack() {
+    let isn = thread_rng().gen();
+    let peer_isn: u32 = thread_rng().gen();
+    let mut conn = connection(isn);
+    conn.receive(TcpMessage::new(
+        TcpSenderMessage::new().with_syn().with_seq(peer_isn),
+        TcpReceiverMessage::new().with_window_size(1000),
+    ));
+    let seg = conn.try_send().expect("SYN/ACK");
+    assert!(seg.sender.syn);
+    assert_eq!(seg.sender.seq_no, RelativeSequence(isn));
+    assert_eq!(
+        seg.receiver.ack_no,
+        Some(RelativeSequence(peer_isn.wrapping_add(1)))
+    );
+    assert!(conn.try_send().is_none());
+    assert_eq!(conn.state(), TcpState::SynReceived);
+
+    conn.receive(TcpMessage::new(
+        TcpSenderMessage::new().with_seq(peer_isn.wrapping_add(1)),
+        TcpReceiverMessage::new()
+            .with_ack(isn.wrapping_add(1))
+            .with_window_size(1000),
+    ));
+    assert_eq!(conn.state(), TcpState::Established);
+}
+
+#[test]
+fn listen_ignores_segments_without_syn() {
+    let mut conn = connection(thread_rng().gen());
+    conn.receive(TcpMessage::new(
+        TcpSenderMessage::new().with_seq(5).with_str("hello"),
+        TcpReceiverMessage::new().with_ack(7).with_window_size(1000),
+    ));
+    assert!(conn.try_send().is_none());
+    assert_eq!(conn.inbound().pushed(), 0);
+    assert_eq!(conn.state(), TcpState::Listen);
+}
+
+#[test]
+fn data_flows_both_ways() {
+    let (mut client, mut server) = established();
+    assert_eq!(client.state(), TcpState::Established);
+    assert_eq!(server.state(), TcpState::Established);
+    assert_eq!(client.bytes_in_flight(), 0);
+    assert_eq!(server.bytes_in_flight(), 0);
+
+    client.write(b"hello from the client");
+    server.write(b"hello from the server");
+    exchange(&mut client, &mut server);
+
+    assert_eq!(server.inbound_mut().read_all(), "hello from the client");
+    assert_eq!(client.inbound_mut().read_all(), "hello from the server");
+    assert_eq!(client.bytes_in_flight(), 0);
+    assert_eq!(server.bytes_in_flight(), 0);
+}
+
+#[test]
+fn large_write_is_delivered_in_order() {
+    let (mut client, mut server) = established();
+    let data: String = (0..20000)
+        .map(|i| (b'a' + (i % 26) as u8) as char)
+        .collect();
+    let mut written = 0;
+    let mut received = String::new();
+    while received.len() < data.len() {
+        written += client.write(&data.as_bytes()[written..]);
+        exchange(&mut client, &mut server);
+        received.push_str(&server.inbound_mut().read_all());
+    }
+    assert_eq!(received, data);
+}
+
+#[test]
+fn keep_alive_is_answered() {
+    let (client, mut server) = established();
+    let next = client.sender().next_relative_seq();
+    let mut keep_alive = TcpMessage::default();
+    keep_alive.sender.seq_no = RelativeSequence(next.0.wrapping_sub(1));
+    keep_alive.receiver = TcpReceiverMessage::new().with_window_size(1000);
+    server.receive(keep_alive);
+    let reply = server.try_send().expect("keep-alive reply");
+    assert_eq!(reply.sender.sequence_length(), 0);
+    assert_eq!(reply.receiver.ack_no, Some(next));
+}
+
+#[test]
+fn active_close_lingers() {
+    let (mut client, mut server) = established();
+    client.end_input_stream();
+    assert_eq!(client.state(), TcpState::FinWait1);
+    exchange(&mut client, &mut server);
+    assert!(server.inbound().closed());
+    assert_eq!(client.state(), TcpState::FinWait2);
+    assert_eq!(server.state(), TcpState::CloseWait);
+
+    server.end_input_stream();
+    assert_eq!(server.state(), TcpState::LastAck);
+    exchange(&mut client, &mut server);
+    assert!(client.inbound().closed());
+    assert!(!server.active());
+    assert_eq!(server.state(), TcpState::Closed);
+    assert_eq!(client.state(), TcpState::TimeWait);
+
+    client.tick(LINGER - 1);
+    assert!(client.active());
+    client.tick(1);
+    assert!(!client.active());
+    assert_eq!(client.state(), TcpState::Closed);
+}
+
+#[test]
+fn passive_close_does_not_linger() {
+    let (mut client, mut server) = established();
+    server.end_input_stream();
+    exchange(&mut client, &mut server);
+    assert!(client.inbound().closed());
+
+    client.end_input_stream();
+    exchange(&mut client, &mut server);
+    assert!(!client.active());
+    assert!(server.active());
+    server.tick(LINGER);
+    assert!(!server.active());
+}
+
+#[test]
+fn simultaneous_close_goes_through_closing() {
+    let (mut client, mut server) = established();
+    client.end_input_stream();
+    server.end_input_stream();
+    let client_fin = client.try_send().expect("client FIN");
+    let server_fin = server.try_send().expect("server FIN");
+    client.receive(server_fin);
+    server.receive(client_fin);
+    assert_eq!(client.state(), TcpState::Closing);
+    assert_eq!(server.state(), TcpState::Closing);
+
+    exchange(&mut client, &mut server);
+    assert_eq!(client.state(), TcpState::TimeWait);
+    assert_eq!(server.state(), TcpState::TimeWait);
+    client.tick(LINGER);
+    server.tick(LINGER);
+    assert!(!client.active());
+    assert!(!server.active());
+}
+
+#[test]
+fn abort_closes_immediately() {
+    let (mut client, _) = established();
+    client.write(b"never delivered");
+    client.abort();
+    assert_eq!(client.state(), TcpState::Closed);
+    assert!(!client.active());
+    let rst = client.try_send().expect("RST");
+    assert!(rst.sender.rst);
+    assert_eq!(rst.sender.payload.len(), 0);
+    assert!(client.try_send().is_none());
+    assert!(client.inbound().has_error());
+    assert!(client.outbound().has_error());
+}
+
+#[test]
+fn too_many_retransmissions_kills_connection() {
+    let mut conn = connection(thread_rng().gen());
+    conn.connect();
+    conn.try_send().expect("SYN");
+    for attempt_no in 0..=MAX_RETRY_ATTEMPT {
+        assert!(conn.active());
+        conn.tick(DEFAULT_TIMEOUT_RT << attempt_no);
+        conn.try_send();
+    }
+    assert!(!conn.active());
+    assert_eq!(conn.state(), TcpState::Closed);
+}
+
+#[test]
+fn abort_resets_peer() {
+    let (mut client, mut server) = established();
+    client.abort();
+    exchange(&mut client, &mut server);
+    assert_eq!(server.state(), TcpState::Closed);
+    assert!(server.inbound().has_error());
+    assert!(server.outbound().has_error());
+}
+
+#[test]
+fn blind_rst_earns_challenge_ack() {
+    let (client, mut server) = established();
+    let next = client.sender().next_relative_seq();
+    server.receive(TcpMessage::new(
+        TcpSenderMessage::new()
+            .with_seq(next.0.wrapping_add(10))
+            .with_rst(),
+        TcpReceiverMessage::new(),
+    ));
+    assert_eq!(server.state(), TcpState::Established);
+    assert!(!server.inbound().has_error());
+    let ack = server.try_send().expect("challenge ACK");
+    assert!(!ack.sender.rst);
+    assert_eq!(ack.receiver.ack_no, Some(next));
+    assert!(server.try_send().is_none());
+}
+
+#[test]
+fn rst_in_syn_sent_must_ack_syn() {
+    let isn: u32 = thread_rng().gen();
+    let mut conn = connection(isn);
+    conn.connect();
+    conn.try_send().expect("SYN");
+
+    conn.receive(TcpMessage::new(
+        TcpSenderMessage::new().with_rst(),
+        TcpReceiverMessage::new().with_ack(isn),
+    ));
+    assert_eq!(conn.state(), TcpState::SynSent);
+
+    conn.receive(TcpMessage::new(
+        TcpSenderMessage::new().with_rst(),
+        TcpReceiverMessage::new().with_ack(isn.wrapping_add(1)),
+    ));
+    assert_eq!(conn.state(), TcpState::Closed);
+    assert!(conn.try_send().is_none());
+}
+
+#[test]
+fn retransmission_limit_sends_rst() {
+    let (mut client, _) = established();
+    client.write(b"lost");
+    client.try_send().expect("data");
+    for attempt_no in 0..MAX_RETRY_ATTEMPT {
+        client.tick(DEFAULT_TIMEOUT_RT << attempt_no);
+        assert!(!client.try_send().expect("retransmission").sender.rst);
+    }
+    client.tick(DEFAULT_TIMEOUT_RT << MAX_RETRY_ATTEMPT);
+    assert_eq!(client.state(), TcpState::Closed);
+    assert!(client.try_send().expect("RST").sender.rst);
+    assert!(client.try_send().is_none());
+}
+
+fn sack_connection(isn: u32, sack: bool) -> TcpConnection {
+    TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .sack(sack)
+        .generate_connection()
+}
+
+#[test]
+fn sack_is_negotiated_in_handshake() {
+    for (client_sack, server_sack) in [(true, true), (true, false), (false, true)] {
+        let mut client = sack_connection(thread_rng().gen(), client_sack);
+        let mut server = sack_connection(thread_rng().gen(), server_sack);
+        client.connect();
+        server.receive(client.try_send().unwrap());
+        let syn_ack = server.try_send().unwrap();
+        let both = client_sack && server_sack;
+        assert_eq!(syn_ack.sender.sack_permitted, both);
+        client.receive(syn_ack);
+        exchange(&mut client, &mut server);
+        assert_eq!(client.state(), TcpState::Established);
+        assert_eq!(client.sender().sack_permitted(), both);
+        assert_eq!(client.receiver().sack_permitted(), both);
+        assert_eq!(server.sender().sack_permitted(), both);
+        assert_eq!(server.receiver().sack_permitted(), both);
+    }
+}
+
+#[test]
+fn data_segments_are_no_duplicate_acks() {
+    let (mut client, mut server) = established();
+    client.write(&[b'x'; 1000]);
+    let lost = client.try_send().expect("data");
+    assert_eq!(lost.sender.payload.len(), 1000);
+
+    // Every segment the server sends repeats the same acknowledgment.
+    server.write(&[b'y'; 3000]);
+    let segments: Vec<_> = std::iter::from_fn(|| server.try_send()).collect();
+    assert_eq!(segments.len(), 3);
+    for segment in segments {
+        client.receive(segment);
+    }
+    assert_eq!(client.sender().duplicate_acks(), 0);
+    assert_eq!(client.sender().fast_retransmits(), 0);
+    while let Some(segment) = client.try_send() {
+        assert!(segment.sender.payload.is_empty());
+    }
+}
+
+#[test]
+fn lost_segment_is_repaired_with_sack() {
+    let mut client = sack_connection(thread_rng().gen(), true);
+    let mut server = sack_connection(thread_rng().gen(), true);
+    client.connect();
+    exchange(&mut client, &mut server);
+
+    client.write(&[b'x'; 6000]);
+    let segments: Vec<_> = std::iter::from_fn(|| client.try_send()).collect();
+    assert_eq!(segments.len(), 6);
+    let lost = segments[1].sender.seq_no;
+    let mut acks = Vec::new();
+    for segment in segments.into_iter().filter(|s| s.sender.seq_no != lost) {
+        server.receive(segment);
+        acks.extend(server.try_send());
+    }
+    assert!(acks[1..].iter().all(|ack| !ack.receiver.sack.is_empty()));
+    assert_eq!(acks.last().unwrap().receiver.sack[0].right.0, lost.0 + 5000);
+
+    for ack in acks {
+        client.receive(ack);
+    }
+    let retransmission = client.try_send().expect("retransmission");
+    assert_eq!(retransmission.sender.seq_no, lost);
+    assert!(client.try_send().is_none());
+    server.receive(retransmission);
+    exchange(&mut client, &mut server);
+    assert_eq!(server.inbound_mut().read_all().len(), 6000);
+    assert_eq!(client.bytes_in_flight(), 0);
+}
+
+#[test]
+fn large_receive_window_is_scaled() {
+    let config = || {
+        TcpConfig::new()
+            .nodelay(true)
+            .recv_capacity(1 << 30)
+            .send_capacity(1 << 20)
+    };
+    let mut client = config().generate_connection();
+    let mut server = config().generate_connection();
+    client.connect();
+    let syn = client.try_send().unwrap();
+    assert_eq!(syn.sender.window_scale, Some(14));
+    assert_eq!(syn.receiver.window_size, u16::MAX);
+    server.receive(syn);
+    let syn_ack = server.try_send().unwrap();
+    assert_eq!(syn_ack.receiver.window_size, u16::MAX);
+    client.receive(syn_ack);
+    exchange(&mut client, &mut server);
+    assert_eq!(server.sender().window(), u16::MAX as u64 * (1 << 14));
+
+    // Once the client's window is scaled too, far more than 64 KiB goes out
+    // before the first acknowledgment.
+    client.write(&vec![b'x'; 1 << 20]);
+    exchange(&mut client, &mut server);
+    let window = (1 << 30) - (1 << 20);
+    assert_eq!(client.sender().window(), window);
+    client.write(&vec![b'x'; 1 << 20]);
+    let segments: Vec<_> = std::iter::from_fn(|| client.try_send()).collect();
+    assert_eq!(client.bytes_in_flight(), 1 << 20);
+    for segment in segments {
+        server.receive(segment);
+    }
+    exchange(&mut client, &mut server);
+    assert_eq!(server.inbound_mut().read_all().len(), 2 << 20);
+    assert_eq!(client.bytes_in_flight(), 0);
+}
+
+#[test]
+fn windows_are_clamped_without_peer_scaling() {
+    let mut client = TcpConfig::new()
+        .recv_capacity(1 << 20)
+        .generate_connection();
+    let mut server = TcpConfig::new()
+        .recv_capacity(1 << 20)
+        .generate_connection();
+    client.connect();
+    let mut syn = client.try_send().unwrap();
+    syn.sender.window_scale = None;
+    server.receive(syn);
+    let syn_ack = server.try_send().unwrap();
+    assert_eq!(syn_ack.sender.window_scale, None);
+    client.receive(syn_ack);
+    client.write(b"data");
+    exchange(&mut client, &mut server);
+    assert_eq!(client.state(), TcpState::Established);
+    assert_eq!(client.sender().window(), u16::MAX as u64);
+    assert_eq!(server.sender().window(), u16::MAX as u64);
+}
+
+#[test]
+fn timestamps_are_negotiated_and_echoed() {
+    let config = |timestamps| TcpConfig::new().timestamps(timestamps).adaptive_rto(true);
+    let mut client = config(true).generate_connection();
+    let mut server = config(true).generate_connection();
+    client.connect();
+    server.receive(client.try_send().unwrap());
+    client.tick(20);
+    server.tick(5);
+    let syn_ack = server.try_send().unwrap();
+    assert!(syn_ack.sender.timestamp.is_some());
+    assert_eq!(syn_ack.receiver.timestamp_echo, Some(0));
+    client.receive(syn_ack);
+    assert!(client.sender().timestamps_enabled());
+    assert_eq!(client.sender().srtt(), Some(20));
+    exchange(&mut client, &mut server);
+    assert!(server.receiver().timestamps_enabled());
+    assert_eq!(server.receiver().ts_recent(), Some(20));
+
+    let mut client = config(true).generate_connection();
+    let mut server = config(false).generate_connection();
+    client.connect();
+    exchange(&mut client, &mut server);
+    assert_eq!(client.state(), TcpState::Established);
+    assert!(!client.sender().timestamps_enabled());
+    client.write(b"data");
+    let segment = client.try_send().unwrap();
+    assert_eq!(segment.sender.timestamp, None);
+}
+
+#[test]
+fn paws_rejects_acknowledgment_of_old_duplicate() {
+    let config = || TcpConfig::new().timestamps(true);
+    let mut client = config().generate_connection();
+    let mut server = config().generate_connection();
+    client.connect();
+    exchange(&mut client, &mut server);
+    client.tick(100);
+    server.tick(100);
+
+    server.write(b"data");
+    client.receive(server.try_send().unwrap());
+    let ack = client.try_send().unwrap();
+    let mut old = ack.clone();
+    old.sender.timestamp = Some(u32::MAX);
+    old.receiver.window_size = 0;
+    server.receive(old);
+    assert_eq!(server.sender().seq_in_flight().0, 4);
+    assert_ne!(server.sender().window(), 0);
+
+    server.receive(ack);
+    assert_eq!(server.sender().seq_in_flight().0, 0);
+}
+
+#[test]
+fn delayed_acks_cover_two_segments() {
+    let mut client = connection(thread_rng().gen());
+    let mut server = TcpConfig::new()
+        .fixed_isn(RelativeSequence(thread_rng().gen()))
+        .delayed_ack(true)
+        .generate_connection();
+    client.connect();
+    exchange(&mut client, &mut server);
+    assert_eq!(server.state(), TcpState::Established);
+
+    client.write(&[b'x'; 4 * MAX_PAYLOAD_SIZE]);
+    let segments: Vec<_> = std::iter::from_fn(|| client.try_send()).collect();
+    assert_eq!(segments.len(), 4);
+    let mut acks = Vec::new();
+    for seg in segments {
+        server.receive(seg);
+        acks.extend(server.try_send());
+    }
+    assert_eq!(acks.len(), 2);
+    for ack in acks {
+        client.receive(ack);
+    }
+    assert_eq!(client.bytes_in_flight(), 0);
+
+    // A lone small segment waits for the timeout.
+    client.write(b"hello");
+    server.receive(client.try_send().unwrap());
+    assert!(server.try_send().is_none());
+    server.tick(DEFAULT_ACK_TIMEOUT - 1);
+    assert!(server.try_send().is_none());
+    server.tick(1);
+    client.receive(server.try_send().expect("delayed ack"));
+    assert_eq!(client.bytes_in_flight(), 0);
+}
+
+#[test]
+fn sws_avoidance_is_applied_to_advertised_windows() {
+    let mut client = connection(thread_rng().gen());
+    let mut server = TcpConfig::new()
+        .fixed_isn(RelativeSequence(thread_rng().gen()))
+        .recv_capacity(4000)
+        .sws_avoidance(true)
+        .generate_connection();
+    client.connect();
+    exchange(&mut client, &mut server);
+
+    client.write(&[b'x'; 4000]);
+    exchange(&mut client, &mut server);
+    assert_eq!(client.sender().window(), 0);
+
+    // A keep-alive gets the window the server advertises now.
+    server.inbound_mut().pop(10);
+    let next = client.sender().next_relative_seq();
+    let mut keep_alive = TcpMessage::default();
+    keep_alive.sender.seq_no = RelativeSequence(next.0.wrapping_sub(1));
+    server.receive(keep_alive);
+    assert_eq!(server.try_send().unwrap().receiver.window_size, 0);
+}
+
+#[test]
+fn mss_is_negotiated_in_handshake() {
+    let mut client = TcpConfig::new()
+        .fixed_isn(RelativeSequence(thread_rng().gen()))
+        .mss(8960)
+        .generate_connection();
+    let mut server = TcpConfig::new()
+        .fixed_isn(RelativeSequence(thread_rng().gen()))
+        .mss(8960)
+        .mtu(576)
+        .generate_connection();
+    client.connect();
+    exchange(&mut client, &mut server);
+    assert_eq!(client.sender().mss(), 536);
+    assert_eq!(server.sender().mss(), 536);
+
+    client.write(&[b'x'; 2000]);
+    let segment = client.try_send().unwrap();
+    assert_eq!(segment.sender.payload.len(), 536);
+    server.receive(segment);
+    exchange(&mut client, &mut server);
+    assert_eq!(server.inbound_mut().read_all().len(), 2000);
+}
@@ -1,6 +1,9 @@
 use cs144::{
-    byte_stream::ByteStream, reassembler::Reassembler, sequence::RelativeSequence,
-    tcp_receiver::TcpReceiver, TcpSenderMessage,
+    byte_stream::ByteStream,
+    reassembler::Reassembler,
+    sequence::RelativeSequence,
+    tcp_receiver::{window_scale, TcpReceiver, DEFAULT_ACK_TIMEOUT},
+    SackBlock, TcpSenderMessage, MAX_PAYLOAD_SIZE,
 };
 use rand::Rng;
 
@@ -543,3 +546,380 @@ fn segment_with_null_byte() {
     );
     assert!(!writer.closed());
 }
+
+#[test]
+fn rst_with_exact_seqno_resets_stream() {
+    let mut receiver = TcpReceiver::new();
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+    let isn = rand::thread_rng().gen::<u32>();
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(isn);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(1))
+        .with_str("abc");
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(!receiver.send(&mut writer).rst);
+
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(4))
+        .with_rst();
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(writer.has_error());
+    assert!(!receiver.take_challenge_ack());
+    assert!(receiver.send(&mut writer).rst);
+
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(4))
+        .with_str("def");
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert_eq!(writer.pushed(), 3);
+}
+
+#[test]
+fn rst_in_window_only_earns_challenge_ack() {
+    let mut receiver = TcpReceiver::new();
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+    let isn = rand::thread_rng().gen::<u32>();
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(isn);
+    receiver.receive(message, &mut reassembler, &mut writer);
+
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(100))
+        .with_rst();
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(!writer.has_error());
+    assert!(receiver.take_challenge_ack());
+    assert!(!receiver.take_challenge_ack());
+    assert!(!receiver.send(&mut writer).rst);
+}
+
+#[test]
+fn rst_outside_window_is_ignored() {
+    let mut receiver = TcpReceiver::new();
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+    let isn = rand::thread_rng().gen::<u32>();
+
+    let message = TcpSenderMessage::new().with_rst().with_seq(isn);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(!writer.has_error());
+    assert_eq!(receiver.send(&mut writer).ack_no, None);
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(isn);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    for offset in [0u32, 5000, u32::MAX / 2] {
+        let message = TcpSenderMessage::new()
+            .with_seq(isn.wrapping_add(offset))
+            .with_rst();
+        receiver.receive(message, &mut reassembler, &mut writer);
+        assert!(!writer.has_error());
+        assert!(!receiver.take_challenge_ack());
+    }
+}
+
+#[test]
+fn sack_blocks_report_out_of_order_data() {
+    let mut receiver = TcpReceiver::new().with_sack(true);
+    let mut writer = ByteStream::new(2358);
+    let mut reassembler = Reassembler::new(2358);
+    let isn: u32 = rand::thread_rng().gen();
+    let block = |left: u32, right: u32| SackBlock {
+        left: RelativeSequence(isn.wrapping_add(left)),
+        right: RelativeSequence(isn.wrapping_add(right)),
+    };
+
+    let message = TcpSenderMessage::new()
+        .with_syn()
+        .with_seq(isn)
+        .with_sack_permitted();
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(receiver.sack_permitted());
+    assert!(receiver.send(&mut writer).sack.is_empty());
+
+    let segment = |seq: u32, data| {
+        TcpSenderMessage::new()
+            .with_seq(isn.wrapping_add(seq))
+            .with_str(data)
+    };
+    receiver.receive(segment(5, "efgh"), &mut reassembler, &mut writer);
+    receiver.receive(segment(13, "mn"), &mut reassembler, &mut writer);
+    assert_eq!(
+        receiver.send(&mut writer).sack,
+        vec![block(13, 15), block(5, 9)]
+    );
+
+    // The block that just grew comes first, the others follow by recency.
+    receiver.receive(segment(20, "u"), &mut reassembler, &mut writer);
+    receiver.receive(segment(9, "ij"), &mut reassembler, &mut writer);
+    assert_eq!(
+        receiver.send(&mut writer).sack,
+        vec![block(5, 11), block(20, 21), block(13, 15)]
+    );
+
+    receiver.receive(segment(1, "abcd"), &mut reassembler, &mut writer);
+    let message = receiver.send(&mut writer);
+    assert_eq!(message.ack_no, Some(RelativeSequence(isn.wrapping_add(11))));
+    assert_eq!(message.sack, vec![block(20, 21), block(13, 15)]);
+}
+
+#[test]
+fn no_sack_blocks_unless_permitted() {
+    let mut receiver = TcpReceiver::new().with_sack(true);
+    let mut writer = ByteStream::new(2358);
+    let mut reassembler = Reassembler::new(2358);
+    let isn: u32 = rand::thread_rng().gen();
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(isn);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(5))
+        .with_str("efgh");
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(!receiver.sack_permitted());
+    assert!(receiver.send(&mut writer).sack.is_empty());
+}
+
+#[test]
+fn window_scale_fits_capacity() {
+    assert_eq!(window_scale(64000), 0);
+    assert_eq!(window_scale(65535), 0);
+    assert_eq!(window_scale(65536), 1);
+    assert_eq!(window_scale(1 << 20), 5);
+    assert_eq!(window_scale(1 << 30), 14);
+    assert_eq!(window_scale(usize::MAX), 14);
+}
+
+#[test]
+fn window_is_scaled_once_negotiated() {
+    let capacity = 1 << 20;
+    let isn: u32 = rand::thread_rng().gen();
+    let syn = TcpSenderMessage::new().with_syn().with_seq(isn);
+
+    let mut receiver = TcpReceiver::new().with_window_scale(Some(5));
+    let mut writer = ByteStream::new(capacity);
+    let mut reassembler = Reassembler::new(capacity);
+    receiver.receive(
+        syn.clone().with_window_scale(2),
+        &mut reassembler,
+        &mut writer,
+    );
+    assert_eq!(receiver.window_shift(), 5);
+    assert_eq!(receiver.send(&mut writer).window_size, 1 << 15);
+
+    let data = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(1))
+        .with_str("abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz");
+    receiver.receive(data, &mut reassembler, &mut writer);
+    assert_eq!(
+        receiver.send(&mut writer).window_size,
+        ((capacity - 52) >> 5) as u16
+    );
+
+    // Without the peer's consent windows are clamped instead.
+    let mut receiver = TcpReceiver::new().with_window_scale(Some(5));
+    let mut writer = ByteStream::new(capacity);
+    receiver.receive(syn, &mut reassembler, &mut writer);
+    assert_eq!(receiver.window_shift(), 0);
+    assert_eq!(receiver.send(&mut writer).window_size, u16::MAX);
+}
+
+#[test]
+fn paws_rejects_old_duplicates() {
+    let mut receiver = TcpReceiver::new().with_timestamps(true);
+    let mut writer = ByteStream::new(2358);
+    let mut reassembler = Reassembler::new(2358);
+    let isn: u32 = rand::thread_rng().gen();
+    let segment = |seq: u32, data, timestamp| {
+        TcpSenderMessage::new()
+            .with_seq(isn.wrapping_add(seq))
+            .with_str(data)
+            .with_timestamp(timestamp)
+    };
+
+    let syn = TcpSenderMessage::new()
+        .with_syn()
+        .with_seq(isn)
+        .with_timestamp(100);
+    receiver.receive(syn, &mut reassembler, &mut writer);
+    assert!(receiver.timestamps_enabled());
+    assert_eq!(receiver.send(&mut writer).timestamp_echo, Some(100));
+
+    receiver.receive(segment(1, "abcd", 110), &mut reassembler, &mut writer);
+    assert_eq!(receiver.ts_recent(), Some(110));
+
+    // Out of order data does not move TS.Recent.
+    receiver.receive(segment(9, "ijkl", 130), &mut reassembler, &mut writer);
+    assert_eq!(receiver.ts_recent(), Some(110));
+    receiver.receive(segment(5, "efgh", 120), &mut reassembler, &mut writer);
+    assert_eq!(receiver.send(&mut writer).timestamp_echo, Some(120));
+    assert_eq!(writer.read_all(), "abcdefghijkl");
+
+    // An old segment whose sequence number is valid again, for instance
+    // after the sequence space wrapped, is dropped.
+    receiver.receive(segment(13, "XXXX", 115), &mut reassembler, &mut writer);
+    assert_eq!(writer.pushed(), 12);
+    assert_eq!(reassembler.pending(), 0);
+    receiver.receive(segment(13, "mnop", 140), &mut reassembler, &mut writer);
+    assert_eq!(writer.read_all(), "mnop");
+    assert_eq!(receiver.ts_recent(), Some(140));
+
+    // Timestamps compare modulo 2^32, so the clock may wrap too.
+    let wrapped = 140u32.wrapping_add(u32::MAX / 2);
+    receiver.receive(segment(17, "qr", wrapped), &mut reassembler, &mut writer);
+    let wrapped = wrapped.wrapping_add(u32::MAX / 2);
+    receiver.receive(segment(19, "st", wrapped), &mut reassembler, &mut writer);
+    assert_eq!(writer.read_all(), "qrst");
+    assert_eq!(receiver.ts_recent(), Some(138));
+}
+
+#[test]
+fn timestamps_ignored_unless_negotiated() {
+    let mut receiver = TcpReceiver::new().with_timestamps(true);
+    let mut writer = ByteStream::new(2358);
+    let mut reassembler = Reassembler::new(2358);
+    let isn: u32 = rand::thread_rng().gen();
+
+    let syn = TcpSenderMessage::new().with_syn().with_seq(isn);
+    receiver.receive(syn, &mut reassembler, &mut writer);
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(1))
+        .with_str("abcd")
+        .with_timestamp(100);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    let message = TcpSenderMessage::new()
+        .with_seq(isn.wrapping_add(5))
+        .with_str("efgh")
+        .with_timestamp(50);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert_eq!(writer.read_all(), "abcdefgh");
+    assert_eq!(receiver.send(&mut writer).timestamp_echo, None);
+}
+
+#[test]
+fn delayed_ack_covers_two_full_segments() {
+    let mut receiver = TcpReceiver::new().with_delayed_ack(Some(DEFAULT_ACK_TIMEOUT));
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+    let isn: u32 = rand::thread_rng().gen();
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(isn);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    assert!(receiver.ack_due());
+    receiver.send(&mut writer);
+    assert!(!receiver.ack_due());
+
+    let segment = |seq: u32| {
+        TcpSenderMessage::new()
+            .with_seq(isn.wrapping_add(seq))
+            .with_payload(&[b'x'; MAX_PAYLOAD_SIZE])
+    };
+    receiver.receive(segment(1), &mut reassembler, &mut writer);
+    assert!(!receiver.ack_due());
+    receiver.receive(segment(1001), &mut reassembler, &mut writer);
+    assert!(receiver.ack_due());
+    let message = receiver.send(&mut writer);
+    assert_eq!(
+        message.ack_no,
+        Some(RelativeSequence(isn.wrapping_add(2001)))
+    );
+    assert!(!receiver.ack_due());
+}
+
+#[test]
+fn delayed_ack_times_out() {
+    let mut receiver = TcpReceiver::new().with_delayed_ack(Some(DEFAULT_ACK_TIMEOUT));
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(0);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    receiver.send(&mut writer);
+    receiver.tick(DEFAULT_ACK_TIMEOUT);
+    assert!(!receiver.ack_due());
+
+    let message = TcpSenderMessage::new().with_seq(1).with_str("abc");
+    receiver.receive(message, &mut reassembler, &mut writer);
+    receiver.tick(DEFAULT_ACK_TIMEOUT - 1);
+    assert!(!receiver.ack_due());
+    receiver.tick(1);
+    assert!(receiver.ack_due());
+    assert_eq!(receiver.send(&mut writer).ack_no, Some(RelativeSequence(4)));
+}
+
+#[test]
+fn delayed_ack_is_skipped_for_holes_and_fin() {
+    let mut receiver = TcpReceiver::new().with_delayed_ack(Some(DEFAULT_ACK_TIMEOUT));
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(0);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    receiver.send(&mut writer);
+
+    let segment = |seq: u32, data| TcpSenderMessage::new().with_seq(seq).with_str(data);
+    receiver.receive(segment(4, "def"), &mut reassembler, &mut writer);
+    assert!(receiver.ack_due());
+    receiver.send(&mut writer);
+
+    // Filling the hole.
+    receiver.receive(segment(1, "abc"), &mut reassembler, &mut writer);
+    assert!(receiver.ack_due());
+    receiver.send(&mut writer);
+
+    // A duplicate.
+    receiver.receive(segment(1, "abc"), &mut reassembler, &mut writer);
+    assert!(receiver.ack_due());
+    receiver.send(&mut writer);
+
+    receiver.receive(segment(7, "").with_fin(), &mut reassembler, &mut writer);
+    assert!(receiver.ack_due());
+    assert_eq!(receiver.send(&mut writer).ack_no, Some(RelativeSequence(8)));
+}
+
+#[test]
+fn acks_are_not_delayed_by_default() {
+    let mut receiver = TcpReceiver::new();
+    let mut writer = ByteStream::new(4000);
+    let mut reassembler = Reassembler::new(4000);
+    assert!(!receiver.delays_acks());
+
+    let message = TcpSenderMessage::new().with_syn().with_seq(0);
+    receiver.receive(message, &mut reassembler, &mut writer);
+    let message = TcpSenderMessage::new().with_seq(1).with_str("abc");
+    receiver.receive(message, &mut reassembler, &mut writer);
+    receiver.tick(DEFAULT_ACK_TIMEOUT);
+    // The caller acknowledges every segment itself.
+    assert!(!receiver.ack_due());
+}
+
+#[test]
+fn sws_avoidance_holds_small_window_updates() {
+    for sws_avoidance in [false, true] {
+        let mut receiver = TcpReceiver::new().with_sws_avoidance(sws_avoidance);
+        let mut writer = ByteStream::new(4000);
+        let mut reassembler = Reassembler::new(4000);
+
+        let message = TcpSenderMessage::new().with_syn().with_seq(0);
+        receiver.receive(message, &mut reassembler, &mut writer);
+        assert_eq!(receiver.send(&mut writer).window_size, 4000);
+        let message = TcpSenderMessage::new()
+            .with_seq(1)
+            .with_payload(&[b'x'; 4000]);
+        receiver.receive(message, &mut reassembler, &mut writer);
+        assert_eq!(receiver.send(&mut writer).window_size, 0);
+
+        // The application reads slowly.
+        writer.pop(1);
+        let small = if sws_avoidance { 0 } else { 1 };
+        assert_eq!(receiver.send(&mut writer).window_size, small);
+        writer.pop(998);
+        let small = if sws_avoidance { 0 } else { 999 };
+        assert_eq!(receiver.send(&mut writer).window_size, small);
+        writer.pop(1);
+        assert_eq!(receiver.send(&mut writer).window_size, 1000);
+    }
+}
@@ -0,0 +1,363 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use cs144::{
+    parser::ParseError,
+    sequence::RelativeSequence,
+    tcp_segment::{ipv4_mss_for_mtu, ipv6_mss_for_mtu, tcp_checksum, TcpOption, TcpSegment},
+    SackBlock, TcpMessage, TcpReceiverMessage, TcpSenderMessage,
+};
+
+const SRC: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+const DST: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+const HELLO: [u8; 25] = [
+    4, 210, 0, 80, 1, 2, 3, 4, 10, 11, 12, 13, 80, 24, 3, 232, 52, 203, 0, 0, 104, 101, 108, 108,
+    111,
+];
+
+fn hello_segment() -> TcpSegment {
+    TcpSegment {
+        src_port: 1234,
+        dst_port: 80,
+        seq_no: RelativeSequence(0x01020304),
+        ack_no: RelativeSequence(0x0a0b0c0d),
+        ack: true,
+        psh: true,
+        window_size: 1000,
+        payload: b"hello".to_vec(),
+        ..Default::default()
+    }
+}
+
+fn fix_checksum(bytes: &mut [u8]) {
+    bytes[16..18].copy_from_slice(&[0, 0]);
+    let checksum = tcp_checksum(SRC, DST, bytes);
+    bytes[16..18].copy_from_slice(&checksum.to_be_bytes());
+}
+
+#[test]
+fn serialize_matches_reference() {
+    assert_eq!(hello_segment().serialize(SRC, DST), HELLO);
+}
+
+#[test]
+fn parse_reference() {
+    let segment = TcpSegment::parse(&HELLO, SRC, DST).unwrap();
+    assert_eq!(segment.checksum, 0x34cb);
+    assert_eq!(
+        segment,
+        TcpSegment {
+            checksum: 0x34cb,
+            ..hello_segment()
+        }
+    );
+}
+
+#[test]
+fn checksum_covers_pseudo_header() {
+    let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+    assert_eq!(
+        TcpSegment::parse(&HELLO, SRC, other),
+        Err(ParseError::BadChecksum)
+    );
+
+    let mut corrupted = HELLO;
+    corrupted[22] ^= 0x20;
+    assert_eq!(
+        TcpSegment::parse(&corrupted, SRC, DST),
+        Err(ParseError::BadChecksum)
+    );
+}
+
+#[test]
+fn ipv6_round_trip() {
+    let src = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+    let dst = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2));
+    let bytes = hello_segment().serialize(src, dst);
+    assert_eq!(tcp_checksum(src, dst, &bytes), 0);
+    assert!(TcpSegment::parse(&bytes, src, dst).is_ok());
+    assert_eq!(
+        TcpSegment::parse(&bytes, SRC, DST),
+        Err(ParseError::BadChecksum)
+    );
+}
+
+#[test]
+fn options_are_padded_and_parsed() {
+    let segment = TcpSegment {
+        syn: true,
+        options: vec![
+            TcpOption::Nop,
+            TcpOption::Unknown {
+                kind: 42,
+                data: vec![1, 2, 3],
+            },
+        ],
+        ..Default::default()
+    };
+    let bytes = segment.serialize(SRC, DST);
+    assert_eq!(bytes.len(), 28);
+    assert_eq!(bytes[12] >> 4, 7);
+
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap();
+    assert_eq!(parsed.options, segment.options);
+    assert!(parsed.syn);
+    assert!(parsed.payload.is_empty());
+}
+
+#[test]
+fn options_that_do_not_fit_are_dropped() {
+    let segment = TcpSegment {
+        syn: true,
+        options: vec![
+            TcpOption::Mss(1460),
+            TcpOption::Unknown {
+                kind: 42,
+                data: vec![0; 300],
+            },
+            TcpOption::Sack(vec![SackBlock::default(); 40]),
+            TcpOption::Unknown {
+                kind: 43,
+                data: vec![7; 30],
+            },
+            TcpOption::WindowScale(7),
+        ],
+        ..Default::default()
+    };
+    let bytes = segment.serialize(SRC, DST);
+    assert_eq!(bytes[12] >> 4, 15);
+
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap();
+    assert_eq!(
+        parsed.options,
+        vec![
+            TcpOption::Mss(1460),
+            TcpOption::Unknown {
+                kind: 43,
+                data: vec![7; 30],
+            },
+            TcpOption::WindowScale(7),
+        ]
+    );
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    assert_eq!(
+        TcpSegment::parse(&HELLO[..19], SRC, DST),
+        Err(ParseError::Truncated)
+    );
+
+    let mut short = HELLO;
+    short[12] = 4 << 4;
+    assert_eq!(
+        TcpSegment::parse(&short, SRC, DST),
+        Err(ParseError::HeaderTooShort)
+    );
+
+    let mut bad_option = TcpSegment::default().serialize(SRC, DST);
+    bad_option[12] = 6 << 4;
+    bad_option.extend_from_slice(&[42, 1, 0, 0]);
+    fix_checksum(&mut bad_option);
+    assert_eq!(
+        TcpSegment::parse(&bad_option, SRC, DST),
+        Err(ParseError::BadOption)
+    );
+}
+
+#[test]
+fn message_round_trip() {
+    let message = TcpMessage::new(
+        TcpSenderMessage::new()
+            .with_seq(77)
+            .with_syn()
+            .with_str("data")
+            .with_fin(),
+        TcpReceiverMessage::new().with_ack(99).with_window_size(512),
+    );
+    let segment = TcpSegment::from_message(&message, 4000, 5000);
+    assert!(segment.ack && segment.syn && segment.fin && !segment.rst);
+
+    let bytes = segment.serialize(SRC, DST);
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap().to_message();
+    assert_eq!(parsed.sender.seq_no, RelativeSequence(77));
+    assert!(parsed.sender.syn && parsed.sender.fin);
+    assert_eq!(parsed.sender.payload, b"data");
+    assert_eq!(parsed.receiver.ack_no, Some(RelativeSequence(99)));
+    assert_eq!(parsed.receiver.window_size, 512);
+
+    let no_ack = TcpSegment::from_message(
+        &TcpMessage::new(
+            TcpSenderMessage::new().with_rst(),
+            TcpReceiverMessage::new(),
+        ),
+        1,
+        2,
+    );
+    assert!(!no_ack.ack && no_ack.rst);
+    let message = no_ack.to_message();
+    assert_eq!(message.receiver.ack_no, None);
+    assert!(message.sender.rst && message.receiver.rst);
+}
+
+#[test]
+fn window_scale_only_on_syn() {
+    let syn = TcpMessage::new(
+        TcpSenderMessage::new().with_syn().with_window_scale(7),
+        TcpReceiverMessage::new().with_window_size(65535),
+    );
+    let segment = TcpSegment::from_message(&syn, 4000, 5000);
+    assert_eq!(segment.options, vec![TcpOption::WindowScale(7)]);
+    let bytes = segment.serialize(SRC, DST);
+    assert_eq!(bytes.len(), 24);
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap();
+    assert_eq!(parsed.options, segment.options);
+    assert_eq!(parsed.to_message().sender.window_scale, Some(7));
+
+    let data = TcpMessage::new(
+        TcpSenderMessage::new().with_window_scale(7),
+        TcpReceiverMessage::new(),
+    );
+    let segment = TcpSegment::from_message(&data, 4000, 5000);
+    assert!(segment.options.is_empty());
+    let mut segment = TcpSegment::from_message(&syn, 4000, 5000);
+    segment.syn = false;
+    assert_eq!(segment.to_message().sender.window_scale, None);
+}
+
+#[test]
+fn sack_options_round_trip() {
+    let message = TcpMessage::new(
+        TcpSenderMessage::new()
+            .with_seq(77)
+            .with_syn()
+            .with_sack_permitted(),
+        TcpReceiverMessage::new()
+            .with_ack(99)
+            .with_window_size(512)
+            .with_sack(200, 300)
+            .with_sack(400, 500),
+    );
+    let segment = TcpSegment::from_message(&message, 4000, 5000);
+    let block = |left, right| SackBlock {
+        left: RelativeSequence(left),
+        right: RelativeSequence(right),
+    };
+    assert_eq!(
+        segment.options,
+        vec![
+            TcpOption::SackPermitted,
+            TcpOption::Sack(vec![block(200, 300), block(400, 500)]),
+        ]
+    );
+
+    // Two bytes of SACK-permitted and 18 of SACK, padded to 20.
+    let bytes = segment.serialize(SRC, DST);
+    assert_eq!(bytes.len(), 40);
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap().to_message();
+    assert!(parsed.sender.sack_permitted);
+    assert_eq!(parsed.receiver.sack, message.receiver.sack);
+
+    // Only four blocks fit.
+    let mut receiver = TcpReceiverMessage::new().with_ack(1);
+    for i in 0..6 {
+        receiver = receiver.with_sack(i * 10, i * 10 + 5);
+    }
+    let message = TcpMessage::new(TcpSenderMessage::new(), receiver);
+    let segment = TcpSegment::from_message(&message, 1, 2);
+    assert!(matches!(&segment.options[..], [TcpOption::Sack(blocks)] if blocks.len() == 4));
+
+    let mut bad_sack = TcpSegment::default().serialize(SRC, DST);
+    bad_sack[12] = 7 << 4;
+    bad_sack.extend_from_slice(&[5, 6, 0, 0, 0, 1, 0, 0]);
+    fix_checksum(&mut bad_sack);
+    assert_eq!(
+        TcpSegment::parse(&bad_sack, SRC, DST),
+        Err(ParseError::BadOption)
+    );
+}
+
+#[test]
+fn timestamps_round_trip() {
+    let message = TcpMessage::new(
+        TcpSenderMessage::new().with_seq(77).with_timestamp(1234),
+        TcpReceiverMessage::new()
+            .with_ack(99)
+            .with_timestamp_echo(5678),
+    );
+    let segment = TcpSegment::from_message(&message, 4000, 5000);
+    assert_eq!(
+        segment.options,
+        vec![TcpOption::Timestamps {
+            value: 1234,
+            echo: 5678
+        }]
+    );
+    let bytes = segment.serialize(SRC, DST);
+    assert_eq!(bytes.len(), 32);
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap().to_message();
+    assert_eq!(parsed.sender.timestamp, Some(1234));
+    assert_eq!(parsed.receiver.timestamp_echo, Some(5678));
+
+    // TSecr means nothing without ACK.
+    let mut segment = segment;
+    segment.ack = false;
+    assert_eq!(segment.to_message().receiver.timestamp_echo, None);
+
+    // Nor does a TSecr of zero, which only says there was nothing to echo.
+    let message = TcpMessage::new(
+        TcpSenderMessage::new().with_timestamp(1234),
+        TcpReceiverMessage::new().with_ack(99),
+    );
+    let segment = TcpSegment::from_message(&message, 4000, 5000);
+    let bytes = segment.serialize(SRC, DST);
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap().to_message();
+    assert_eq!(parsed.sender.timestamp, Some(1234));
+    assert_eq!(parsed.receiver.timestamp_echo, None);
+
+    // Timestamps leave room for three SACK blocks.
+    let mut receiver = TcpReceiverMessage::new().with_ack(1);
+    for i in 0..4 {
+        receiver = receiver.with_sack(i * 10, i * 10 + 5);
+    }
+    let message = TcpMessage::new(TcpSenderMessage::new().with_timestamp(1), receiver);
+    let segment = TcpSegment::from_message(&message, 1, 2);
+    assert!(matches!(&segment.options[1], TcpOption::Sack(blocks) if blocks.len() == 3));
+    assert_eq!(segment.serialize(SRC, DST).len(), 56);
+}
+
+#[test]
+fn mss_only_on_syn() {
+    assert_eq!(ipv4_mss_for_mtu(1500), 1460);
+    assert_eq!(ipv4_mss_for_mtu(30), 0);
+    assert_eq!(ipv6_mss_for_mtu(1500), 1440);
+
+    let syn = TcpMessage::new(
+        TcpSenderMessage::new().with_syn().with_mss(1460),
+        TcpReceiverMessage::new().with_window_size(65535),
+    );
+    let segment = TcpSegment::from_message(&syn, 4000, 5000);
+    assert_eq!(segment.options, vec![TcpOption::Mss(1460)]);
+    let bytes = segment.serialize(SRC, DST);
+    assert_eq!(bytes.len(), 24);
+    let parsed = TcpSegment::parse(&bytes, SRC, DST).unwrap();
+    assert_eq!(parsed.options, segment.options);
+    assert_eq!(parsed.to_message().sender.mss, Some(1460));
+
+    let data = TcpMessage::new(
+        TcpSenderMessage::new().with_mss(1460),
+        TcpReceiverMessage::new(),
+    );
+    assert!(TcpSegment::from_message(&data, 4000, 5000)
+        .options
+        .is_empty());
+
+    let mut bad_mss = TcpSegment::default().serialize(SRC, DST);
+    bad_mss[12] = 6 << 4;
+    bad_mss.extend_from_slice(&[2, 3, 5, 0]);
+    fix_checksum(&mut bad_mss);
+    assert_eq!(
+        TcpSegment::parse(&bad_mss, SRC, DST),
+        Err(ParseError::BadOption)
+    );
+}
@@ -1,7 +1,9 @@
 use cs144::{
     byte_stream::ByteStream,
+    plpmtud::BASE_MSS,
+    reno::Reno,
     sequence::{AbsoluteSequence, RelativeSequence},
-    tcp_sender::TcpSender,
+    tcp_sender::{TcpSender, DEFAULT_PEER_MSS},
     TcpConfig, TcpReceiverMessage, DEFAULT_TIMEOUT_RT, MAX_PAYLOAD_SIZE, MAX_RETRY_ATTEMPT,
 };
 use rand::{thread_rng, Rng};
@@ -12,6 +14,7 @@ const DEFAULT_TEST_WINDOW: u16 = 137;
 struct Message {
     syn: Option<bool>,
     fin: Option<bool>,
+    rst: Option<bool>,
     seq_no: Option<RelativeSequence>,
     data: Option<String>,
     payload_size: Option<usize>,
@@ -22,6 +25,7 @@ impl Message {
         Message {
             syn: None,
             fin: None,
+            rst: None,
             seq_no: None,
             data: None,
             payload_size: None,
@@ -38,6 +42,11 @@ impl Message {
         self
     }
 
+    fn rst(mut self, rst: bool) -> Message {
+        self.rst = Some(rst);
+        self
+    }
+
     fn no_flag(mut self) -> Message {
         self.syn = Some(false);
         self.fin = Some(false);
@@ -110,6 +119,9 @@ impl SenderTester {
         if let Some(fin) = message.fin {
             assert_eq!(fin, seg.fin);
         };
+        if let Some(rst) = message.rst {
+            assert_eq!(rst, seg.rst);
+        };
         if let Some(seq_no) = message.seq_no {
             assert_eq!(seq_no, seg.seq_no);
         };
@@ -162,6 +174,26 @@ impl SenderTester {
         )
     }
 
+    fn permit_sack(mut self) -> Self {
+        self.sender.permit_sack(true);
+        self
+    }
+
+    fn receive_sack(self, ack_no: u32, blocks: &[(u32, u32)]) -> Self {
+        let message = blocks.iter().fold(
+            TcpReceiverMessage::new()
+                .with_ack(ack_no)
+                .with_window_size(DEFAULT_TEST_WINDOW),
+            |message, (left, right)| message.with_sack(*left, *right),
+        );
+        self.receive(message, true)
+    }
+
+    fn expect_sacked_bytes(self, bytes: u64) -> Self {
+        assert_eq!(self.sender.sacked_bytes(), bytes);
+        self
+    }
+
     fn expect_seq_in_flight(self, seq: u64) -> Self {
         assert_eq!(self.sender.seq_in_flight(), AbsoluteSequence(seq));
         self
@@ -180,6 +212,53 @@ impl SenderTester {
         self
     }
 
+    fn expect_rtt(self, srtt: Option<u64>, rttvar: Option<u64>) -> Self {
+        assert_eq!(self.sender.srtt(), srtt);
+        assert_eq!(self.sender.rttvar(), rttvar);
+        self
+    }
+
+    fn expect_rto(self, rto: u64) -> Self {
+        assert_eq!(self.sender.rto(), rto);
+        self
+    }
+
+    fn expect_duplicate_acks(self, count: u32) -> Self {
+        assert_eq!(self.sender.duplicate_acks(), count);
+        self
+    }
+
+    fn expect_fast_retransmits(self, count: u64) -> Self {
+        assert_eq!(self.sender.fast_retransmits(), count);
+        self
+    }
+
+    fn expect_window_probes(self, count: u64) -> Self {
+        assert_eq!(self.sender.window_probes(), count);
+        self
+    }
+
+    fn expect_persist_timeout(self, timeout: Option<u64>) -> Self {
+        assert_eq!(self.sender.persist_timeout(), timeout);
+        self
+    }
+
+    fn reduce_mss(mut self, mss: usize) -> Self {
+        self.sender.reduce_mss(mss);
+        self
+    }
+
+    fn expect_mss(self, mss: usize) -> Self {
+        assert_eq!(self.sender.mss(), mss);
+        self
+    }
+
+    fn expect_error(self, to_be: bool) -> Self {
+        assert_eq!(to_be, self.sender.has_error());
+        assert_eq!(to_be, self.stream.has_error());
+        self
+    }
+
     fn expect_max_retx_exceeded(self, to_be: bool) -> Self {
         assert_eq!(
             to_be,
@@ -205,6 +284,189 @@ fn repeat_ack_is_ignored() {
         .expect_no_segment();
 }
 
+#[test]
+fn three_duplicate_acks_trigger_fast_retransmit() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno(isn + 1)
+        .push("a")
+        .expect_message(Message::new().data("a"))
+        .push("b")
+        .expect_message(Message::new().data("b"))
+        .push("c")
+        .expect_message(Message::new().data("c"))
+        .push("d")
+        .expect_message(Message::new().data("d"))
+        .receive_ackno(isn + 2)
+        .receive_ackno(isn + 2)
+        .receive_ackno(isn + 2)
+        .expect_duplicate_acks(2)
+        .expect_no_segment()
+        .receive_ackno(isn + 2)
+        .expect_duplicate_acks(3)
+        .expect_fast_retransmits(1)
+        .expect_message(Message::new().data("b").seq(isn + 2))
+        .expect_no_segment()
+        .receive_ackno(isn + 2)
+        .expect_duplicate_acks(4)
+        .expect_no_segment()
+        .expect_fast_retransmits(1)
+        .receive_ackno(isn + 5)
+        .expect_duplicate_acks(0)
+        .expect_seq_in_flight(0);
+}
+
+#[test]
+fn window_updates_are_not_duplicate_acks() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 1000)
+        .push("abc")
+        .expect_message(Message::new().data("abc"))
+        .receive_ackno_with_window(isn + 1, 1000)
+        .receive_ackno_with_window(isn + 1, 1001)
+        .receive_ackno_with_window(isn + 1, 1002)
+        .receive_ackno_with_window(isn + 1, 1002)
+        .expect_duplicate_acks(1)
+        .expect_no_segment()
+        .expect_fast_retransmits(0);
+}
+
+#[test]
+fn fast_retransmit_does_not_wait_for_rto() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno(isn + 1)
+        .push("a")
+        .expect_message(Message::new().data("a"))
+        .push("b")
+        .expect_message(Message::new().data("b"))
+        .tick(DEFAULT_TIMEOUT_RT / 2)
+        .receive_ackno(isn + 1)
+        .receive_ackno(isn + 1)
+        .receive_ackno(isn + 1)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .expect_no_segment()
+        .receive_ackno(isn + 3)
+        .expect_seq_in_flight(0)
+        .tick(DEFAULT_TIMEOUT_RT)
+        .expect_no_segment();
+}
+
+/// A SACK-enabled sender with the one-byte segments "a" to "h" in flight.
+fn sack_sender(isn: u32) -> SenderTester {
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .sack(true)
+        .nodelay(true)
+        .into();
+    let mut tester = tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .permit_sack()
+        .receive_ackno(isn + 1);
+    for data in ["a", "b", "c", "d", "e", "f", "g", "h"] {
+        tester = tester.push(data).expect_message(Message::new().data(data));
+    }
+    tester
+}
+
+#[test]
+fn sack_permitted_is_offered_in_syn() {
+    let (mut sender, mut stream) = TcpConfig::new().sack(true).generate_parts();
+    sender.push(&mut stream);
+    let syn = sender.try_send().unwrap();
+    assert!(syn.syn && syn.sack_permitted);
+    assert!(!sender.sack_permitted());
+    sender.permit_sack(true);
+    assert!(sender.sack_permitted());
+}
+
+#[test]
+fn sacked_data_presumes_loss_early() {
+    // "b" is missing while the three segments after it arrived, which is
+    // enough to resend it on the first duplicate acknowledgment.
+    let isn = thread_rng().gen();
+    sack_sender(isn)
+        .receive_ackno(isn + 2)
+        .receive_sack(isn + 2, &[(isn + 3, isn + 6)])
+        .expect_duplicate_acks(1)
+        .expect_sacked_bytes(3)
+        .expect_message(Message::new().data("b").seq(isn + 2))
+        .expect_no_segment()
+        .expect_fast_retransmits(1);
+}
+
+#[test]
+fn sack_recovery_resends_only_holes() {
+    // "b" and "d" are lost, "c" is never resent.
+    let isn = thread_rng().gen();
+    sack_sender(isn)
+        .receive_ackno(isn + 2)
+        .receive_sack(isn + 2, &[(isn + 3, isn + 4)])
+        .receive_sack(isn + 2, &[(isn + 5, isn + 6), (isn + 3, isn + 4)])
+        .expect_no_segment()
+        .receive_sack(isn + 2, &[(isn + 5, isn + 7), (isn + 3, isn + 4)])
+        .expect_message(Message::new().data("b").seq(isn + 2))
+        .expect_no_segment()
+        .receive_sack(isn + 2, &[(isn + 5, isn + 8), (isn + 3, isn + 4)])
+        .expect_message(Message::new().data("d").seq(isn + 4))
+        .expect_no_segment()
+        .receive_sack(isn + 2, &[(isn + 5, isn + 9), (isn + 3, isn + 4)])
+        .expect_no_segment()
+        .expect_fast_retransmits(2)
+        .receive_sack(isn + 4, &[(isn + 5, isn + 9)])
+        .expect_no_segment()
+        .receive_ackno(isn + 9)
+        .expect_seq_in_flight(0)
+        .expect_sacked_bytes(0);
+}
+
+#[test]
+fn timeout_forgets_sacked_data() {
+    let isn = thread_rng().gen();
+    sack_sender(isn)
+        .receive_ackno(isn + 1)
+        .receive_sack(isn + 1, &[(isn + 2, isn + 4)])
+        .expect_sacked_bytes(2)
+        .tick(DEFAULT_TIMEOUT_RT)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .expect_sacked_bytes(0);
+}
+
+#[test]
+fn sack_blocks_are_ignored_unless_negotiated() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .sack(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno(isn + 1)
+        .push("abc")
+        .expect_message(Message::new().data("abc"))
+        .receive_sack(isn + 1, &[(isn + 2, isn + 4)])
+        .expect_sacked_bytes(0)
+        .expect_no_segment();
+}
+
 #[test]
 fn old_ack_is_ignored() {
     let isn = thread_rng().gen();
@@ -426,6 +688,7 @@ fn timer_stays_running_when_new_segment_sent() {
     let rto = thread_rng().gen_range(30..10000);
     let tester: SenderTester = TcpConfig::new()
         .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
         .rt_timeout(rto)
         .into();
     tester
@@ -451,6 +714,7 @@ fn retransmission_still_happens_when_expiration_time_not_hit_exactly() {
     let rto = thread_rng().gen_range(30..10000);
     let tester: SenderTester = TcpConfig::new()
         .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
         .rt_timeout(rto)
         .into();
     tester
@@ -476,6 +740,7 @@ fn timer_restarts_on_ack_of_new_data() {
     let rto = thread_rng().gen_range(30..10000);
     let tester: SenderTester = TcpConfig::new()
         .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
         .rt_timeout(rto)
         .into();
     tester
@@ -501,6 +766,7 @@ fn timer_doesnt_restart_without_ack_of_new_data() {
     let rto = thread_rng().gen_range(30..10000);
     let tester: SenderTester = TcpConfig::new()
         .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
         .rt_timeout(rto)
         .into();
     tester
@@ -530,6 +796,7 @@ fn rto_resets_on_ack_of_new_data() {
     let rto = thread_rng().gen_range(30..10000);
     let tester: SenderTester = TcpConfig::new()
         .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
         .rt_timeout(rto)
         .into();
     tester
@@ -742,3 +1009,683 @@ fn send_some_data_the_retx_and_succeed_then_retx_till_limit() {
         .tick(1)
         .expect_max_retx_exceeded(true);
 }
+
+#[test]
+fn rst_after_too_many_retransmissions() {
+    let isn = thread_rng().gen();
+    let retx_timeout = thread_rng().gen_range(10..10000);
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .rt_timeout(retx_timeout)
+        .into();
+    let mut tester = tester
+        .push("")
+        .expect_message(Message::new().syn(true).rst(false).seq(isn))
+        .receive_ackno(isn + 1)
+        .push("abcd")
+        .expect_message(Message::new().data("abcd").rst(false));
+    for attempt_no in 0..MAX_RETRY_ATTEMPT {
+        tester = tester
+            .tick(retx_timeout << attempt_no)
+            .expect_message(Message::new().data("abcd").rst(false));
+    }
+    tester
+        .tick(retx_timeout << MAX_RETRY_ATTEMPT)
+        .expect_message(Message::new().rst(true).payload_size(0).seq(isn + 5))
+        .expect_no_segment()
+        .expect_seq_in_flight(0)
+        .push("efgh")
+        .expect_error(true)
+        .expect_no_segment();
+}
+
+#[test]
+fn rst_from_peer_abandons_outstanding_data() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno(isn + 1)
+        .push("abcd")
+        .expect_message(Message::new().data("abcd"))
+        .expect_error(false)
+        .receive(TcpReceiverMessage::new().with_rst(), true)
+        .expect_error(true)
+        .expect_seq_in_flight(0)
+        .tick(DEFAULT_TIMEOUT_RT)
+        .expect_no_segment();
+}
+
+#[test]
+fn rtt_is_estimated_from_samples() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .adaptive_rto(true)
+        .min_rto(1)
+        .into();
+    tester
+        .expect_rtt(None, None)
+        .expect_rto(DEFAULT_TIMEOUT_RT)
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .tick(100)
+        .receive_ackno(isn + 1)
+        .expect_rtt(Some(100), Some(50))
+        .expect_rto(300)
+        .push("abcd")
+        .expect_message(Message::new().data("abcd"))
+        .tick(200)
+        .receive_ackno(isn + 5)
+        .expect_rtt(Some(112), Some(62))
+        .expect_rto(360)
+        .push("efgh")
+        .expect_message(Message::new().data("efgh"))
+        .tick(359)
+        .expect_no_segment()
+        .tick(1)
+        .expect_message(Message::new().data("efgh"));
+}
+
+#[test]
+fn retransmitted_segments_are_not_sampled() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .adaptive_rto(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .tick(DEFAULT_TIMEOUT_RT)
+        .expect_message(Message::new().syn(true).seq(isn))
+        .expect_rto(DEFAULT_TIMEOUT_RT * 2)
+        .tick(10)
+        .receive_ackno(isn + 1)
+        .expect_rtt(None, None)
+        .expect_rto(DEFAULT_TIMEOUT_RT * 2)
+        .push("abcd")
+        .expect_message(Message::new().data("abcd"))
+        .tick(50)
+        .receive_ackno(isn + 5)
+        .expect_rtt(Some(50), Some(25))
+        .expect_rto(200);
+}
+
+#[test]
+fn cumulative_ack_over_retransmission_is_not_sampled() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .nodelay(true)
+        .adaptive_rto(true)
+        .min_rto(1)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .tick(100)
+        .receive_ackno(isn + 1)
+        .expect_rtt(Some(100), Some(50))
+        .push("a")
+        .expect_message(Message::new().data("a"))
+        .push("b")
+        .expect_message(Message::new().data("b"))
+        .tick(300)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .tick(10)
+        .receive_ackno(isn + 3)
+        .expect_rtt(Some(100), Some(50))
+        .expect_seq_in_flight(0);
+}
+
+#[test]
+fn adaptive_rto_is_clamped() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .adaptive_rto(true)
+        .min_rto(500)
+        .max_rto(1500)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .tick(10)
+        .receive_ackno(isn + 1)
+        .expect_rto(500)
+        .push("abcd")
+        .expect_message(Message::new().data("abcd"))
+        .tick(500)
+        .expect_message(Message::new().data("abcd"))
+        .expect_rto(1000)
+        .tick(1000)
+        .expect_message(Message::new().data("abcd"))
+        .expect_rto(1500)
+        .tick(1500)
+        .expect_message(Message::new().data("abcd"))
+        .expect_rto(1500);
+}
+
+#[test]
+fn fixed_rto_ignores_samples() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .tick(100)
+        .receive_ackno(isn + 1)
+        .expect_rtt(Some(100), Some(50))
+        .expect_rto(DEFAULT_TIMEOUT_RT);
+}
+
+#[test]
+fn echoed_timestamps_are_rtt_samples() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .timestamps(true)
+        .adaptive_rto(true)
+        .generate_parts();
+    sender.push(&mut stream);
+    let syn = sender.try_send().unwrap();
+    assert_eq!(syn.timestamp, Some(0));
+    sender.permit_timestamps(true);
+    sender.tick(30);
+    sender.receive(
+        TcpReceiverMessage::new()
+            .with_ack(1)
+            .with_window_size(1000)
+            .with_timestamp_echo(0),
+    );
+    assert_eq!(sender.srtt(), Some(30));
+    assert_eq!(sender.send_empty_message().timestamp, Some(30));
+
+    // The retransmission is what arrived, so its echo is a fair sample.
+    stream.push_str("abc");
+    sender.push(&mut stream);
+    assert_eq!(sender.try_send().unwrap().timestamp, Some(30));
+    sender.tick(1000);
+    let retransmission = sender.try_send().unwrap();
+    assert_eq!(retransmission.timestamp, Some(1030));
+    sender.tick(30);
+    sender.receive(
+        TcpReceiverMessage::new()
+            .with_ack(4)
+            .with_window_size(1000)
+            .with_timestamp_echo(1030),
+    );
+    assert_eq!(sender.srtt(), Some(30));
+    assert_eq!(sender.rto(), 200);
+}
+
+#[test]
+fn timestamps_need_both_offers() {
+    let (mut sender, mut stream) = TcpConfig::new().timestamps(true).generate_parts();
+    sender.permit_timestamps(false);
+    assert!(!sender.timestamps_offered());
+    sender.push(&mut stream);
+    assert_eq!(sender.try_send().unwrap().timestamp, None);
+
+    let (mut sender, mut stream) = TcpConfig::new().timestamps(true).generate_parts();
+    sender.push(&mut stream);
+    assert!(sender.try_send().unwrap().timestamp.is_some());
+    sender.permit_timestamps(false);
+    assert!(sender.timestamps_offered() && !sender.timestamps_enabled());
+    assert_eq!(sender.send_empty_message().timestamp, None);
+}
+
+#[test]
+fn scaled_window_is_decoded() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .recv_capacity(1 << 20)
+        .send_capacity(1 << 20)
+        .generate_parts();
+    assert_eq!(sender.window_scale(), Some(5));
+    sender.push(&mut stream);
+    assert_eq!(sender.try_send().unwrap().window_scale, Some(5));
+
+    // The peer's SYN-ACK window is taken as is.
+    let ack = |window| {
+        TcpReceiverMessage::new()
+            .with_ack(1)
+            .with_window_size(window)
+    };
+    sender.receive(ack(40000));
+    assert_eq!(sender.window(), 40000);
+    sender.permit_window_scale(Some(3));
+    sender.receive(ack(40000));
+    assert_eq!(sender.window(), 320000);
+
+    stream.push_str(&"x".repeat(400000));
+    sender.push(&mut stream);
+    assert_eq!(sender.seq_in_flight().0, 320000);
+}
+
+#[test]
+fn window_scale_needs_both_offers() {
+    let (sender, _) = TcpConfig::new().generate_parts();
+    assert_eq!(sender.window_scale(), Some(0));
+    let mut sender = TcpSender::new(RelativeSequence(0), DEFAULT_TIMEOUT_RT);
+    assert_eq!(sender.window_scale(), None);
+    sender.permit_window_scale(Some(3));
+    sender.receive(TcpReceiverMessage::new().with_window_size(40000));
+    assert_eq!(sender.window(), 40000);
+}
+
+#[test]
+fn zero_window_probes_back_off() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 0)
+        .expect_persist_timeout(Some(DEFAULT_TIMEOUT_RT))
+        .push("abc")
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .expect_no_segment()
+        .tick(DEFAULT_TIMEOUT_RT - 1)
+        .expect_no_segment()
+        .tick(1)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .expect_persist_timeout(Some(2 * DEFAULT_TIMEOUT_RT))
+        .tick(2 * DEFAULT_TIMEOUT_RT - 1)
+        .expect_no_segment()
+        .tick(1)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .expect_persist_timeout(Some(4 * DEFAULT_TIMEOUT_RT))
+        .expect_window_probes(3);
+}
+
+#[test]
+fn zero_window_probes_are_not_retries() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    let mut tester = tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 0)
+        .push("abc")
+        .expect_message(Message::new().data("a").seq(isn + 1));
+    // As long as the peer answers, it may keep its window closed for good.
+    for _ in 0..2 * MAX_RETRY_ATTEMPT {
+        tester = tester
+            .tick(60_000)
+            .expect_message(Message::new().data("a").seq(isn + 1))
+            .receive_ackno_with_window(isn + 1, 0);
+    }
+    tester
+        .expect_max_retx_exceeded(false)
+        .expect_error(false)
+        .expect_persist_timeout(Some(60_000));
+}
+
+#[test]
+fn unanswered_zero_window_probes_reset() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    let mut tester = tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 0)
+        .push("abc")
+        .expect_message(Message::new().data("a").seq(isn + 1));
+    for _ in 0..MAX_RETRY_ATTEMPT {
+        tester = tester
+            .tick(60_000)
+            .expect_message(Message::new().data("a").seq(isn + 1))
+            .expect_error(false);
+    }
+    tester
+        .tick(60_000)
+        .expect_message(Message::new().rst(true).payload_size(0))
+        .expect_no_segment()
+        .push("d")
+        .expect_error(true)
+        .expect_no_segment();
+}
+
+#[test]
+fn acked_probe_keeps_probing_closed_window() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 0)
+        .push("abc")
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .tick(DEFAULT_TIMEOUT_RT)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        // The probe got in, but the window is still closed.
+        .receive_ackno_with_window(isn + 2, 0)
+        .expect_no_segment()
+        .expect_persist_timeout(Some(2 * DEFAULT_TIMEOUT_RT))
+        .tick(2 * DEFAULT_TIMEOUT_RT)
+        .push("")
+        .expect_message(Message::new().data("b").seq(isn + 2))
+        .expect_no_segment();
+}
+
+#[test]
+fn opened_window_stops_persist_timer() {
+    let isn = thread_rng().gen();
+    let tester: SenderTester = TcpConfig::new().fixed_isn(RelativeSequence(isn)).into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 0)
+        .push("abc")
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .tick(DEFAULT_TIMEOUT_RT)
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .receive_ackno_with_window(isn + 2, 10)
+        .expect_persist_timeout(None)
+        .expect_message(Message::new().data("bc").seq(isn + 2))
+        .expect_window_probes(2)
+        // Lost data is retransmitted on the usual schedule again.
+        .tick(DEFAULT_TIMEOUT_RT - 1)
+        .expect_no_segment()
+        .tick(1)
+        .expect_message(Message::new().data("bc").seq(isn + 2))
+        .expect_max_retx_exceeded(false);
+}
+
+fn one_byte_writes(nodelay: bool) -> SenderTester {
+    let isn = 0;
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .nodelay(nodelay)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(isn))
+        .receive_ackno_with_window(isn + 1, 4000)
+        .push("a")
+        .expect_message(Message::new().data("a").seq(isn + 1))
+        .push("b")
+        .push("c")
+}
+
+#[test]
+fn nodelay_sends_every_write() {
+    one_byte_writes(true)
+        .expect_message(Message::new().data("b").seq(2))
+        .expect_message(Message::new().data("c").seq(3))
+        .expect_no_segment();
+}
+
+#[test]
+fn nagle_coalesces_writes_until_ack() {
+    one_byte_writes(false)
+        .expect_no_segment()
+        .receive_ackno_with_window(2, 4000)
+        .expect_message(Message::new().data("bc").seq(2))
+        .expect_no_segment()
+        .receive_ackno_with_window(4, 4000)
+        .push("d")
+        .expect_message(Message::new().data("d").seq(4));
+}
+
+#[test]
+fn nagle_sends_full_segments() {
+    one_byte_writes(false)
+        .push(&"x".repeat(MAX_PAYLOAD_SIZE))
+        .expect_message(Message::new().payload_size(MAX_PAYLOAD_SIZE).seq(2))
+        .expect_no_segment()
+        .expect_seq_in_flight(1 + MAX_PAYLOAD_SIZE as u64);
+}
+
+#[test]
+fn nagle_does_not_hold_fin() {
+    one_byte_writes(false)
+        .push_and_close("d")
+        .expect_message(Message::new().data("bcd").seq(2).fin(true))
+        .expect_no_segment();
+}
+
+#[test]
+fn nagle_timer_restarts_on_ack_of_new_data() {
+    let isn = thread_rng().gen();
+    let rto = thread_rng().gen_range(30..10000);
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(isn))
+        .rt_timeout(rto)
+        .nodelay(false)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).payload_size(0).seq(isn))
+        .receive_ackno_with_window(isn + 1, 1000)
+        .push("abc")
+        .expect_message(Message::new().data("abc").seq(isn + 1))
+        .tick(rto - 5)
+        .push("def")
+        .expect_no_segment()
+        .receive_ackno_with_window(isn + 4, 1000)
+        .expect_message(Message::new().data("def").seq(isn + 4))
+        .tick(rto - 1)
+        .expect_no_segment()
+        .tick(2)
+        .expect_message(Message::new().data("def").seq(isn + 4));
+}
+
+fn small_window(sws_avoidance: bool) -> SenderTester {
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .sws_avoidance(sws_avoidance)
+        .nodelay(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(0))
+        .receive_ackno_with_window(1, 1500)
+        .push(&"x".repeat(2000))
+        .expect_message(Message::new().payload_size(1000).seq(1))
+}
+
+#[test]
+fn without_sws_avoidance_small_windows_are_filled() {
+    small_window(false)
+        .expect_message(Message::new().payload_size(500).seq(1001))
+        .expect_no_segment();
+}
+
+#[test]
+fn sws_avoidance_waits_for_a_useful_window() {
+    small_window(true)
+        .expect_no_segment()
+        .receive_ackno_with_window(501, 1000)
+        .expect_no_segment()
+        .receive_ackno_with_window(1001, 1500)
+        .expect_message(Message::new().payload_size(1000).seq(1001))
+        .expect_no_segment();
+}
+
+#[test]
+fn sws_avoidance_sends_when_nothing_is_in_flight() {
+    small_window(true)
+        .receive_ackno_with_window(1001, 400)
+        .expect_message(Message::new().payload_size(400).seq(1001))
+        .expect_no_segment();
+}
+
+#[test]
+fn configured_mss_limits_segments() {
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .mss(500)
+        .into();
+    let mut tester = tester.push("");
+    let syn = tester.sender.try_send().unwrap();
+    assert!(syn.syn);
+    assert_eq!(syn.mss, Some(500));
+    tester
+        .receive_ackno_with_window(1, 4000)
+        .push(&"x".repeat(1200))
+        .expect_message(Message::new().payload_size(500).seq(1))
+        .expect_message(Message::new().payload_size(500).seq(501))
+        .expect_message(Message::new().payload_size(200).seq(1001))
+        .expect_no_segment();
+}
+
+#[test]
+fn peer_mss_limits_segments() {
+    for (peer_mss, expected) in [
+        (Some(1460), 1460),
+        (Some(9000), 8960),
+        (None, DEFAULT_PEER_MSS),
+    ] {
+        let (mut sender, mut stream) = TcpConfig::new()
+            .fixed_isn(RelativeSequence(0))
+            .mtu(9000)
+            .mss(u16::MAX as usize)
+            .generate_parts();
+        assert_eq!(sender.advertised_mss(), 8960);
+        sender.push(&mut stream);
+        assert_eq!(sender.try_send().unwrap().mss, Some(8960));
+        sender.permit_mss(peer_mss);
+        assert_eq!(sender.mss(), expected);
+
+        sender.receive(
+            TcpReceiverMessage::new()
+                .with_ack(1)
+                .with_window_size(60000),
+        );
+        stream.push_str(&"x".repeat(20000));
+        sender.push(&mut stream);
+        assert_eq!(sender.try_send().unwrap().payload.len(), expected);
+    }
+}
+
+#[test]
+fn reduced_mss_resegments_outstanding_data() {
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(0))
+        .receive_ackno_with_window(1, 4000)
+        .push(&"x".repeat(2500))
+        .expect_message(Message::new().payload_size(1000).seq(1))
+        .expect_message(Message::new().payload_size(1000).seq(1001))
+        // The path dropped both, what is still queued only gets split.
+        .reduce_mss(400)
+        .expect_mss(400)
+        .expect_message(Message::new().payload_size(400).seq(1))
+        .expect_message(Message::new().payload_size(400).seq(401))
+        .expect_message(Message::new().payload_size(200).seq(801))
+        .expect_message(Message::new().payload_size(400).seq(1001))
+        .expect_message(Message::new().payload_size(400).seq(1401))
+        .expect_message(Message::new().payload_size(200).seq(1801))
+        .expect_message(Message::new().payload_size(400).seq(2001))
+        .expect_message(Message::new().payload_size(100).seq(2401))
+        .expect_no_segment()
+        .expect_seq_in_flight(2500)
+        .expect_rto(DEFAULT_TIMEOUT_RT)
+        .expect_fast_retransmits(0)
+        .receive_ackno_with_window(1801, 4000)
+        .expect_seq_in_flight(700)
+        // Only a smaller MTU lowers the MSS further.
+        .reduce_mss(1000)
+        .expect_mss(400)
+        .close()
+        .expect_message(Message::new().fin(true).payload_size(0).seq(2501));
+}
+
+#[test]
+fn acknowledged_probe_raises_mss() {
+    let tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .mtu_probing(true)
+        .into();
+    tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(0))
+        .receive_ackno_with_window(1, 10000)
+        .expect_mss(BASE_MSS)
+        .push(&"x".repeat(2000))
+        .expect_message(Message::new().payload_size(1000).seq(1))
+        .expect_message(Message::new().payload_size(BASE_MSS).seq(1001))
+        .expect_message(Message::new().payload_size(1000 - BASE_MSS).seq(1537))
+        .expect_no_segment()
+        .receive_ackno_with_window(1001, 10000)
+        .expect_mss(1000)
+        .push(&"x".repeat(2000))
+        .expect_message(Message::new().payload_size(1000).seq(2001))
+        .expect_message(Message::new().payload_size(1000).seq(3001));
+}
+
+#[test]
+fn lost_probes_narrow_the_search() {
+    let mut tester: SenderTester = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .nodelay(true)
+        .mtu_probing(true)
+        .into();
+    tester = tester
+        .push("")
+        .expect_message(Message::new().syn(true).seq(0))
+        .receive_ackno_with_window(1, 10000);
+    let mut seq = 1;
+    for _ in 0..3 {
+        // A lost probe is no congestion signal and not a retry either, its
+        // data simply goes out again in smaller segments.
+        tester = tester
+            .push(&"x".repeat(1000))
+            .expect_message(Message::new().payload_size(1000).seq(seq))
+            .tick(DEFAULT_TIMEOUT_RT)
+            .expect_message(Message::new().payload_size(BASE_MSS).seq(seq))
+            .expect_message(Message::new().payload_size(1000 - BASE_MSS).seq(seq + 536))
+            .expect_no_segment()
+            .expect_rto(DEFAULT_TIMEOUT_RT)
+            .expect_max_retx_exceeded(false)
+            .receive_ackno_with_window(seq + 1000, 10000)
+            .expect_mss(BASE_MSS);
+        seq += 1000;
+    }
+    assert_eq!(tester.sender.consecutive_retransmissions(), 0);
+
+    // Three duplicate acknowledgments give a lost probe away just as well.
+    tester
+        .push(&"x".repeat(2000))
+        .expect_message(Message::new().payload_size(768).seq(seq))
+        .expect_message(Message::new().payload_size(BASE_MSS).seq(seq + 768))
+        .expect_message(Message::new().payload_size(BASE_MSS).seq(seq + 1304))
+        .expect_message(Message::new().payload_size(160).seq(seq + 1840))
+        .receive_ackno_with_window(seq, 10000)
+        .receive_ackno_with_window(seq, 10000)
+        .receive_ackno_with_window(seq, 10000)
+        .expect_message(Message::new().payload_size(BASE_MSS).seq(seq))
+        .expect_message(Message::new().payload_size(232).seq(seq + 536))
+        .expect_no_segment()
+        .expect_fast_retransmits(0)
+        .expect_duplicate_acks(0);
+}
+
+#[test]
+fn congestion_window_follows_path_mss() {
+    let (mut sender, mut stream) = TcpConfig::new()
+        .fixed_isn(RelativeSequence(0))
+        .congestion_control(Reno::default())
+        .mtu_probing(true)
+        .generate_parts();
+    assert_eq!(sender.mss(), BASE_MSS);
+    assert_eq!(
+        sender.congestion_control().cwnd(),
+        Reno::initial_window(BASE_MSS as u64)
+    );
+
+    sender.push(&mut stream);
+    sender.try_send().unwrap();
+    sender.reduce_mss(268);
+    assert_eq!(sender.mss(), 268);
+    assert_eq!(sender.congestion_control().cwnd(), 4 * 268);
+}
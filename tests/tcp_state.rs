@@ -0,0 +1,105 @@
+use cs144::tcp_state::{TcpEvent, TcpState};
+
+fn run(start: TcpState, events: &[TcpEvent]) -> TcpState {
+    events.iter().fold(start, |state, event| {
+        state
+            .next(*event)
+            .unwrap_or_else(|| panic!("{event:?} is not valid in {state:?}"))
+    })
+}
+
+#[test]
+fn three_way_handshake() {
+    assert_eq!(
+        run(
+            TcpState::Closed,
+            &[TcpEvent::ActiveOpen, TcpEvent::ReceiveSynAck]
+        ),
+        TcpState::Established
+    );
+    assert_eq!(
+        run(
+            TcpState::Closed,
+            &[
+                TcpEvent::PassiveOpen,
+                TcpEvent::ReceiveSyn,
+                TcpEvent::ReceiveAckOfSyn
+            ]
+        ),
+        TcpState::Established
+    );
+}
+
+#[test]
+fn simultaneous_open() {
+    assert_eq!(
+        run(
+            TcpState::SynSent,
+            &[TcpEvent::ReceiveSyn, TcpEvent::ReceiveAckOfSyn]
+        ),
+        TcpState::Established
+    );
+}
+
+#[test]
+fn active_close() {
+    let state = run(TcpState::Established, &[TcpEvent::Close]);
+    assert_eq!(state, TcpState::FinWait1);
+    let state = run(state, &[TcpEvent::ReceiveAckOfFin]);
+    assert_eq!(state, TcpState::FinWait2);
+    let state = run(state, &[TcpEvent::ReceiveFin]);
+    assert_eq!(state, TcpState::TimeWait);
+    assert_eq!(run(state, &[TcpEvent::TimeWaitExpired]), TcpState::Closed);
+}
+
+#[test]
+fn simultaneous_close() {
+    let state = run(
+        TcpState::Established,
+        &[TcpEvent::Close, TcpEvent::ReceiveFin],
+    );
+    assert_eq!(state, TcpState::Closing);
+    assert_eq!(run(state, &[TcpEvent::ReceiveAckOfFin]), TcpState::TimeWait);
+}
+
+#[test]
+fn passive_close() {
+    let state = run(TcpState::Established, &[TcpEvent::ReceiveFin]);
+    assert_eq!(state, TcpState::CloseWait);
+    let state = run(state, &[TcpEvent::Close]);
+    assert_eq!(state, TcpState::LastAck);
+    assert_eq!(run(state, &[TcpEvent::ReceiveAckOfFin]), TcpState::Closed);
+}
+
+#[test]
+fn abort_and_reset_close_from_anywhere() {
+    let states = [
+        TcpState::SynSent,
+        TcpState::SynReceived,
+        TcpState::Established,
+        TcpState::FinWait1,
+        TcpState::FinWait2,
+        TcpState::Closing,
+        TcpState::TimeWait,
+        TcpState::CloseWait,
+        TcpState::LastAck,
+    ];
+    for state in states {
+        assert_eq!(state.next(TcpEvent::Abort), Some(TcpState::Closed));
+        assert_eq!(state.next(TcpEvent::ReceiveRst), Some(TcpState::Closed));
+        assert_eq!(
+            state.next(TcpEvent::RetransmissionLimit),
+            Some(TcpState::Closed)
+        );
+    }
+    assert_eq!(TcpState::Listen.next(TcpEvent::ReceiveRst), None);
+}
+
+#[test]
+fn invalid_events_are_rejected() {
+    assert_eq!(TcpState::Closed.next(TcpEvent::ReceiveSyn), None);
+    assert_eq!(TcpState::Listen.next(TcpEvent::ReceiveFin), None);
+    assert_eq!(TcpState::Established.next(TcpEvent::ReceiveSynAck), None);
+    assert_eq!(TcpState::FinWait2.next(TcpEvent::Close), None);
+    assert_eq!(TcpState::TimeWait.next(TcpEvent::ReceiveFin), None);
+}